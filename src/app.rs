@@ -1,4 +1,9 @@
-use std::sync::Arc;
+use std::{
+    cell::OnceCell,
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
 
 use adw::prelude::*;
 use gtk::prelude::{ApplicationExt, ApplicationWindowExt, GtkWindowExt, SettingsExt, WidgetExt};
@@ -9,38 +14,104 @@ use relm4::{
     Controller, SimpleComponent,
 };
 
+use crate::backlinks::BacklinkIndex;
+use crate::clipper_server::{self, ClipperServer};
 use crate::config::{APP_ID, PROFILE};
-use crate::content_page::{ContentPageInit, ContentPageModel};
+use crate::content_page::{ContentPageInit, ContentPageInput, ContentPageModel, ContentPageOutput};
+use crate::data_dir::{self, DataDir};
 use crate::login_page::{LoginPageModel, LoginPageOutput};
 use crate::modals::about::AboutDialog;
+use crate::modals::preferences::{PreferencesInput, PreferencesModel, PreferencesOutput};
 use ruslin_data::RuslinData;
+use tokio::sync::broadcast;
 
 pub struct App {
     about_dialog: Controller<AboutDialog>,
     content_page: Controller<ContentPageModel>,
     login_page: Controller<LoginPageModel>,
+    stack: gtk::Stack,
+    preferences: Controller<PreferencesModel>,
+    settings: gio::Settings,
+    clipper_server: Option<ClipperServer>,
     ctx: AppContext,
+    shared_ctx: SharedContext,
 }
 
 #[derive(Debug)]
 pub enum AppMsg {
     Quit,
     RefreshPageStack,
+    OpenNote { id: String },
+    Sync,
+    UpdateClipperServer,
+    // A Web Clipper asked for the token.
+    AuthorizeClipper { auth_token: String },
+    AnswerClipperAuth { auth_token: String, accepted: bool },
+    ContentPage(ContentPageInput),
+    SwitchProfile { profile: Option<String> },
 }
 
 relm4::new_action_group!(pub(super) WindowActionGroup, "win");
 relm4::new_stateless_action!(PreferencesAction, WindowActionGroup, "preferences");
 relm4::new_stateless_action!(pub(super) ShortcutsAction, WindowActionGroup, "show-help-overlay");
 relm4::new_stateless_action!(AboutAction, WindowActionGroup, "about");
+relm4::new_stateless_action!(pub(super) NewNoteAction, WindowActionGroup, "new-note");
+relm4::new_stateless_action!(pub(super) NewFolderAction, WindowActionGroup, "new-folder");
+relm4::new_stateless_action!(pub(super) SearchAction, WindowActionGroup, "search");
+relm4::new_stateless_action!(pub(super) SyncAction, WindowActionGroup, "sync");
+relm4::new_stateless_action!(pub(super) ToggleSidebarAction, WindowActionGroup, "toggle-sidebar");
+relm4::new_stateless_action!(pub(super) TogglePreviewAction, WindowActionGroup, "toggle-preview");
+relm4::new_stateless_action!(pub(super) PrintAction, WindowActionGroup, "print");
+relm4::new_stateless_action!(pub(super) DeleteNoteAction, WindowActionGroup, "delete-note");
+relm4::new_stateless_action!(pub(super) FocusNoteListAction, WindowActionGroup, "focus-note-list");
+relm4::new_stateless_action!(pub(super) FocusEditorAction, WindowActionGroup, "focus-editor");
+relm4::new_stateless_action!(pub(super) NextNoteAction, WindowActionGroup, "next-note");
+relm4::new_stateless_action!(pub(super) PreviousNoteAction, WindowActionGroup, "previous-note");
 
 #[derive(Debug, Clone)]
 pub struct AppContext {
     pub data: Arc<RuslinData>,
+    pub resources_dir: PathBuf,
+    pub note_events: broadcast::Sender<NoteEvent>,
+    pub backlinks: Arc<BacklinkIndex>,
+    // `None` is the default profile.
+    pub profile: Option<String>,
+}
+
+impl AppContext {
+    pub fn open(data_dir: &DataDir) -> Result<AppContext, String> {
+        let resources_dir = data_dir.path.join("resources");
+        let data = RuslinData::new(&data_dir.path, &resources_dir).map_err(|e| {
+            format!(
+                "Couldn't open the notes in {}: {e:?}",
+                data_dir.path.display()
+            )
+        })?;
+        Ok(AppContext {
+            data: Arc::new(data),
+            resources_dir,
+            note_events: broadcast::channel(64).0,
+            backlinks: Arc::default(),
+            profile: data_dir.profile.clone(),
+        })
+    }
+}
+
+// The context of the open profile, for everything that outlives a profile switch.
+pub type SharedContext = Arc<RwLock<AppContext>>;
+
+#[derive(Debug, Clone)]
+pub enum NoteEvent {
+    Updated { id: String, editor_id: usize },
+    Changed { id: String },
+    FavouritesChanged,
 }
 
-#[derive(Debug)]
 pub struct AppInit {
-    pub ctx: AppContext,
+    // Set by the time the primary instance activates, see `start_primary`.
+    pub ctx: Rc<OnceCell<SharedContext>>,
+    // Sent by the `app.*` actions, which are set up before the window exists.
+    pub messages: relm4::Receiver<AppMsg>,
 }
 
 #[relm4::component(pub)]
@@ -63,6 +134,8 @@ impl SimpleComponent for App {
     view! {
         main_window = adw::ApplicationWindow::new(&main_application()) {
             set_width_request: 380,
+            #[watch]
+            set_title: Some(&window_title(&model.ctx)),
 
             connect_close_request[sender] => move |_| {
                 sender.input(AppMsg::Quit);
@@ -85,12 +158,7 @@ impl SimpleComponent for App {
                     None
                 },
 
-            #[wrap(Some)]
-            set_content: stack = &gtk::Stack {
-                set_transition_type: gtk::StackTransitionType::None,
-                add_child: model.login_page.widget(),
-                add_child: model.content_page.widget(),
-            },
+            set_content: Some(&model.stack),
         }
     }
 
@@ -104,24 +172,48 @@ impl SimpleComponent for App {
             .launch(())
             .detach();
 
-        let content_page = ContentPageModel::builder()
-            .launch(ContentPageInit {
-                ctx: init.ctx.clone(),
-            })
-            .detach();
+        let shared_ctx = init
+            .ctx
+            .get()
+            .expect("notes opened before activation")
+            .clone();
+        let ctx = shared_ctx.read().unwrap().clone();
+        let content_page = launch_content_page(&ctx, &sender);
+        let login_page = launch_login_page(&ctx, &sender);
 
-        let login_page = LoginPageModel::builder().launch(init.ctx.clone()).forward(
-            sender.input_sender(),
-            |msg| match msg {
-                LoginPageOutput::LoginSuccess => AppMsg::RefreshPageStack,
-            },
-        );
+        let preferences = PreferencesModel::builder()
+            .transient_for(root)
+            .launch(shared_ctx.clone())
+            .forward(sender.input_sender(), |msg| match msg {
+                PreferencesOutput::SyncRemote => AppMsg::ContentPage(ContentPageInput::SyncRemote),
+                PreferencesOutput::SyncFromScratch => {
+                    AppMsg::ContentPage(ContentPageInput::SyncFromScratch)
+                }
+            });
+
+        let settings = gio::Settings::new(APP_ID);
+        for key in ["clipper-server-enabled", "clipper-token"] {
+            let sender = sender.clone();
+            settings.connect_changed(Some(key), move |_, _| {
+                sender.input(AppMsg::UpdateClipperServer);
+            });
+        }
+
+        // Kept in the model, the pages in it are replaced when switching profiles.
+        let stack = gtk::Stack::new();
+        stack.add_child(login_page.widget());
+        stack.add_child(content_page.widget());
 
         let model = Self {
             about_dialog,
             content_page,
             login_page,
-            ctx: init.ctx,
+            stack,
+            preferences,
+            settings,
+            clipper_server: None,
+            ctx,
+            shared_ctx,
         };
 
         let widgets = view_output!();
@@ -135,6 +227,13 @@ impl SimpleComponent for App {
             })
         };
 
+        let preferences_action = {
+            let sender = model.preferences.sender().clone();
+            RelmAction::<PreferencesAction>::new_stateless(move |_| {
+                sender.send(PreferencesInput::Show).unwrap();
+            })
+        };
+
         let about_action = {
             let sender = model.about_dialog.sender().clone();
             RelmAction::<AboutAction>::new_stateless(move |_| {
@@ -142,8 +241,134 @@ impl SimpleComponent for App {
             })
         };
 
+        // The content page is replaced when switching profiles, so its actions go through the app.
+        let content_page_sender = sender.input_sender().clone();
+
+        let new_note_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<NewNoteAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::CreateNote))
+                    .unwrap();
+            })
+        };
+
+        let new_folder_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<NewFolderAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(
+                        ContentPageInput::ShowCreateFolderDialog,
+                    ))
+                    .unwrap();
+            })
+        };
+
+        let search_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<SearchAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::ToggleSearch))
+                    .unwrap();
+            })
+        };
+
+        let sync_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<SyncAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::SyncRemote))
+                    .unwrap();
+            })
+        };
+
+        let toggle_sidebar_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<ToggleSidebarAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::ToggleSidebar))
+                    .unwrap();
+            })
+        };
+
+        let toggle_preview_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<TogglePreviewAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::TogglePreview))
+                    .unwrap();
+            })
+        };
+
+        let print_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<PrintAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::Print))
+                    .unwrap();
+            })
+        };
+
+        let delete_note_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<DeleteNoteAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::DeleteNote))
+                    .unwrap();
+            })
+        };
+
+        let focus_note_list_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<FocusNoteListAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::FocusNoteList))
+                    .unwrap();
+            })
+        };
+
+        let focus_editor_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<FocusEditorAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::FocusEditor))
+                    .unwrap();
+            })
+        };
+
+        let next_note_action = {
+            let sender = content_page_sender.clone();
+            RelmAction::<NextNoteAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::SelectNextNote))
+                    .unwrap();
+            })
+        };
+
+        let previous_note_action = {
+            let sender = content_page_sender;
+            RelmAction::<PreviousNoteAction>::new_stateless(move |_| {
+                sender
+                    .send(AppMsg::ContentPage(ContentPageInput::SelectPreviousNote))
+                    .unwrap();
+            })
+        };
+
+        actions.add_action(&preferences_action);
         actions.add_action(&shortcuts_action);
         actions.add_action(&about_action);
+        actions.add_action(&new_note_action);
+        actions.add_action(&new_folder_action);
+        actions.add_action(&search_action);
+        actions.add_action(&sync_action);
+        actions.add_action(&toggle_sidebar_action);
+        actions.add_action(&toggle_preview_action);
+        actions.add_action(&print_action);
+        actions.add_action(&delete_note_action);
+        actions.add_action(&focus_note_list_action);
+        actions.add_action(&focus_editor_action);
+        actions.add_action(&next_note_action);
+        actions.add_action(&previous_note_action);
 
         widgets
             .main_window
@@ -151,15 +376,115 @@ impl SimpleComponent for App {
 
         widgets.load_window_size();
 
+        let input_sender = sender.input_sender().clone();
+        relm4::spawn_local(async move {
+            while let Some(message) = init.messages.recv().await {
+                input_sender.send(message).unwrap();
+            }
+        });
+
         sender.input(AppMsg::RefreshPageStack);
+        sender.input(AppMsg::UpdateClipperServer);
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
             AppMsg::Quit => main_application().quit(),
             AppMsg::RefreshPageStack => {}
+            AppMsg::OpenNote { id } => {
+                // Sent by other processes, the note may not exist (anymore).
+                match self.ctx.data.db.load_note(&id) {
+                    Ok(_) => self.content_page.emit(ContentPageInput::EditNote { id }),
+                    Err(e) => log::warn!("cannot open note {id}: {:?}", e),
+                }
+                if let Some(window) = main_application().active_window() {
+                    window.present();
+                }
+            }
+            AppMsg::Sync => {
+                self.content_page.emit(ContentPageInput::SyncRemote);
+            }
+            AppMsg::UpdateClipperServer => {
+                if !self.settings.boolean("clipper-server-enabled") {
+                    self.clipper_server = None;
+                    return;
+                }
+                let token = self.settings.string("clipper-token");
+                if token.is_empty() {
+                    // Storing a token notifies us again, the server starts then.
+                    if let Err(e) = self
+                        .settings
+                        .set_string("clipper-token", &clipper_server::new_token())
+                    {
+                        log::warn!("save clipper token failed: {e}");
+                    }
+                    return;
+                }
+                if let Some(clipper_server) = &self.clipper_server {
+                    clipper_server.set_token(token.to_string());
+                    return;
+                }
+                self.clipper_server = Some(ClipperServer::start(
+                    self.shared_ctx.clone(),
+                    token.to_string(),
+                    sender.input_sender().clone(),
+                ));
+            }
+            AppMsg::AuthorizeClipper { auth_token } => {
+                let dialog = adw::MessageDialog::new(
+                    main_application().active_window().as_ref(),
+                    Some("Allow the Web Clipper?"),
+                    Some("A Web Clipper wants access to your notes. Only allow this if you just started the authorization from the Joplin Web Clipper in your browser."),
+                );
+                dialog.add_response("reject", "_Reject");
+                dialog.add_response("allow", "_Allow");
+                dialog.set_response_appearance("allow", adw::ResponseAppearance::Suggested);
+                dialog.set_close_response("reject");
+                dialog.connect_response(None, move |_, response| {
+                    sender.input(AppMsg::AnswerClipperAuth {
+                        auth_token: auth_token.clone(),
+                        accepted: response == "allow",
+                    });
+                });
+                dialog.present();
+            }
+            AppMsg::AnswerClipperAuth {
+                auth_token,
+                accepted,
+            } => {
+                if let Some(clipper_server) = &self.clipper_server {
+                    clipper_server.answer_auth_request(&auth_token, accepted);
+                }
+            }
+            AppMsg::ContentPage(input) => {
+                self.content_page.emit(input);
+            }
+            AppMsg::SwitchProfile { profile } => {
+                if profile == self.ctx.profile {
+                    return;
+                }
+                if let Some(name) = profile
+                    .as_deref()
+                    .filter(|name| !data_dir::is_valid_profile_name(name))
+                {
+                    show_profile_error(&format!(
+                        "“{name}” isn't a valid profile name. Names can't be empty, start with a dot or contain slashes."
+                    ));
+                    return;
+                }
+                match data_dir::open_profile(profile.as_deref())
+                    .map_err(|e| format!("Couldn't create the profile folder: {e}"))
+                    .and_then(|data_dir| AppContext::open(&data_dir))
+                {
+                    Ok(ctx) => self.switch_context(ctx, &sender),
+                    Err(e) => {
+                        log::error!("{e}");
+                        show_profile_error(&e);
+                    }
+                }
+            }
         }
     }
 
@@ -169,14 +494,77 @@ impl SimpleComponent for App {
 
     fn pre_view() {
         if model.ctx.data.sync_exists() {
-            stack.set_visible_child(model.content_page.widget());
+            model.stack.set_visible_child(model.content_page.widget());
         } else {
-            stack.set_visible_child(model.login_page.widget());
+            model.stack.set_visible_child(model.login_page.widget());
         }
     }
 
     fn post_view() {
-        stack.set_transition_type(gtk::StackTransitionType::SlideLeft);
+        model
+            .stack
+            .set_transition_type(gtk::StackTransitionType::SlideLeft);
+    }
+}
+
+impl App {
+    fn switch_context(&mut self, ctx: AppContext, sender: &ComponentSender<Self>) {
+        log::info!("switching to profile {:?}", ctx.profile);
+        if let Err(e) = self
+            .settings
+            .set_string("profile", ctx.profile.as_deref().unwrap_or_default())
+        {
+            log::warn!("save profile failed: {e}");
+        }
+        *self.shared_ctx.write().unwrap() = ctx.clone();
+
+        self.stack.remove(self.login_page.widget());
+        self.stack.remove(self.content_page.widget());
+        // Dropping the old controllers shuts the pages down, which closes their note windows.
+        self.login_page = launch_login_page(&ctx, sender);
+        self.content_page = launch_content_page(&ctx, sender);
+        self.stack.add_child(self.login_page.widget());
+        self.stack.add_child(self.content_page.widget());
+        self.ctx = ctx;
+    }
+}
+
+fn launch_content_page(
+    ctx: &AppContext,
+    sender: &ComponentSender<App>,
+) -> Controller<ContentPageModel> {
+    ContentPageModel::builder()
+        .launch(ContentPageInit { ctx: ctx.clone() })
+        .forward(sender.input_sender(), |msg| match msg {
+            ContentPageOutput::SwitchProfile { profile } => AppMsg::SwitchProfile { profile },
+        })
+}
+
+fn launch_login_page(
+    ctx: &AppContext,
+    sender: &ComponentSender<App>,
+) -> Controller<LoginPageModel> {
+    LoginPageModel::builder()
+        .launch(ctx.clone())
+        .forward(sender.input_sender(), |msg| match msg {
+            LoginPageOutput::LoginSuccess => AppMsg::RefreshPageStack,
+        })
+}
+
+fn show_profile_error(message: &str) {
+    let dialog = adw::MessageDialog::new(
+        main_application().active_window().as_ref(),
+        Some("Couldn't Switch Profile"),
+        Some(message),
+    );
+    dialog.add_response("close", "_Close");
+    dialog.present();
+}
+
+fn window_title(ctx: &AppContext) -> String {
+    match &ctx.profile {
+        Some(profile) => format!("Ruslin — {profile}"),
+        None => "Ruslin".to_string(),
     }
 }
 
@@ -0,0 +1,82 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use ruslin_data::{AbbrNote, DatabaseError, RuslinData};
+
+use crate::markdown;
+
+// The item ids each note links to. Only the notes updated since the last lookup, or marked
+// changed, are read again.
+#[derive(Debug, Default)]
+pub struct BacklinkIndex {
+    // By note id, its update time and the ids it links to. Never held while reading notes.
+    notes: Mutex<HashMap<String, (String, HashSet<String>)>>,
+    // Notes edited since they were indexed. Marked from the main thread, so it has its own lock.
+    changed: Mutex<HashSet<String>>,
+}
+
+impl BacklinkIndex {
+    // Reads every note that isn't indexed yet, call it off the main thread.
+    pub fn backlinks(&self, data: &RuslinData, id: &str) -> Result<Vec<AbbrNote>, DatabaseError> {
+        let abbr_notes = data.db.load_abbr_notes(None)?;
+        let changed = std::mem::take(&mut *self.changed.lock().unwrap());
+        let stale: Vec<(&AbbrNote, String)> = {
+            let notes = self.notes.lock().unwrap();
+            abbr_notes
+                .iter()
+                .map(|abbr_note| (abbr_note, abbr_note.user_updated_time.format_ymd_hms()))
+                .filter(|(abbr_note, updated)| {
+                    changed.contains(&abbr_note.id)
+                        || notes
+                            .get(&abbr_note.id)
+                            .map_or(true, |(indexed, _)| indexed != updated)
+                })
+                .collect()
+        };
+        let mut read = Vec::with_capacity(stale.len());
+        for (abbr_note, updated) in stale {
+            match data.db.load_note(&abbr_note.id) {
+                Ok(note) => read.push((
+                    abbr_note.id.clone(),
+                    updated,
+                    markdown::linked_item_ids(&note.body),
+                )),
+                Err(e) => {
+                    // Read again next time.
+                    self.note_changed(&abbr_note.id);
+                    return Err(e);
+                }
+            }
+        }
+
+        let existing: HashSet<&str> = abbr_notes.iter().map(|note| note.id.as_str()).collect();
+        let mut notes = self.notes.lock().unwrap();
+        for (note_id, updated, links) in read {
+            notes.insert(note_id, (updated, links));
+        }
+        notes.retain(|note_id, _| existing.contains(note_id.as_str()));
+        let backlinks = abbr_notes
+            .into_iter()
+            .filter(|abbr_note| abbr_note.id != id)
+            .filter(|abbr_note| {
+                notes
+                    .get(&abbr_note.id)
+                    .map_or(false, |(_, links)| links.contains(id))
+            })
+            .collect();
+        Ok(backlinks)
+    }
+
+    // Reads one note, to tell whether an edit of it can change the backlinks of `to`.
+    pub fn links_to(data: &RuslinData, from: &str, to: &str) -> Result<bool, DatabaseError> {
+        let note = data.db.load_note(from)?;
+        Ok(markdown::linked_item_ids(&note.body).contains(to))
+    }
+
+    // Update times have a resolution of seconds, so edits are reported as well.
+    pub fn note_changed(&self, id: &str) {
+        self.changed.lock().unwrap().insert(id.to_string());
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Debug;
+
+#[derive(Debug)]
+pub struct BulkChangeError<E> {
+    pub error: E,
+    // Items that were changed before the error and couldn't be changed back.
+    pub not_restored: usize,
+}
+
+// Changes every item or none. ruslin-data has no transactions, so when one item fails the items
+// already changed are changed back with `undo`, last first. Returns what `undo` needs for each.
+pub fn apply_all<T, U, E: Debug>(
+    items: &[T],
+    mut change: impl FnMut(&T) -> Result<U, E>,
+    mut undo: impl FnMut(&U) -> Result<(), E>,
+) -> Result<Vec<U>, BulkChangeError<E>> {
+    let mut changed = Vec::with_capacity(items.len());
+    for item in items {
+        match change(item) {
+            Ok(undo_item) => changed.push(undo_item),
+            Err(error) => {
+                changed.reverse();
+                return Err(BulkChangeError {
+                    error,
+                    not_restored: undo_all(&changed, &mut undo),
+                });
+            }
+        }
+    }
+    Ok(changed)
+}
+
+// Undoes each change even after one fails. Returns how many failed.
+pub fn undo_all<U, E: Debug>(changed: &[U], mut undo: impl FnMut(&U) -> Result<(), E>) -> usize {
+    changed
+        .iter()
+        .filter(|undo_item| match undo(undo_item) {
+            Ok(()) => false,
+            Err(e) => {
+                log::warn!("undo failed: {e:?}");
+                true
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // Note ids to folder ids, failing for the ids in `broken`.
+    struct Store {
+        parents: HashMap<&'static str, &'static str>,
+        broken: Vec<&'static str>,
+    }
+
+    impl Store {
+        fn new(broken: &[&'static str]) -> Store {
+            Store {
+                parents: HashMap::from([("a", "inbox"), ("b", "inbox"), ("c", "inbox")]),
+                broken: broken.to_vec(),
+            }
+        }
+
+        fn set_parent(&mut self, id: &'static str, parent: &'static str) -> Result<(), String> {
+            if self.broken.contains(&id) {
+                return Err(format!("{id} is broken"));
+            }
+            self.parents.insert(id, parent);
+            Ok(())
+        }
+
+        fn move_all(
+            &mut self,
+            ids: &[&'static str],
+            to: &'static str,
+        ) -> Result<Vec<(&'static str, &'static str)>, BulkChangeError<String>> {
+            let store = std::cell::RefCell::new(self);
+            apply_all(
+                ids,
+                |id| {
+                    let from = store.borrow().parents[id];
+                    store.borrow_mut().set_parent(id, to)?;
+                    Ok((*id, from))
+                },
+                |(id, from)| store.borrow_mut().set_parent(id, from),
+            )
+        }
+    }
+
+    #[test]
+    fn changes_every_item() {
+        let mut store = Store::new(&[]);
+        let moved = store.move_all(&["a", "b"], "archive").unwrap();
+        assert_eq!(moved, [("a", "inbox"), ("b", "inbox")]);
+        assert_eq!(store.parents["a"], "archive");
+        assert_eq!(store.parents["b"], "archive");
+        assert_eq!(store.parents["c"], "inbox");
+    }
+
+    #[test]
+    fn a_failed_item_changes_the_others_back() {
+        let mut store = Store::new(&["c"]);
+        let error = store.move_all(&["a", "b", "c"], "archive").unwrap_err();
+        assert_eq!(error.error, "c is broken");
+        assert_eq!(error.not_restored, 0);
+        assert!(store.parents.values().all(|parent| *parent == "inbox"));
+    }
+
+    #[test]
+    fn counts_items_that_couldnt_be_changed_back() {
+        let mut undo_calls = Vec::new();
+        let error = apply_all(
+            &[1, 2, 3],
+            |item| {
+                if *item == 3 {
+                    Err(item * 10)
+                } else {
+                    Ok(*item)
+                }
+            },
+            |item| {
+                undo_calls.push(*item);
+                if *item == 1 {
+                    Err(item * 100)
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.error, 30);
+        assert_eq!(error.not_restored, 1);
+        assert_eq!(undo_calls, [2, 1]);
+    }
+
+    #[test]
+    fn undo_goes_on_after_a_failure() {
+        let failed = undo_all(&[1, 2, 3], |item| if *item == 2 { Err(()) } else { Ok(()) });
+        assert_eq!(failed, 1);
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    future::{self, Future},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::Poll,
+};
+
+// Set to stop a running task, shared with it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Runs `future` until it finishes, or returns `None` once `flag` is set. The flag is checked
+// whenever the future is woken, so a sync stops between two requests and never inside one.
+pub async fn until_cancelled<F: Future>(flag: &CancelFlag, future: F) -> Option<F::Output> {
+    tokio::pin!(future);
+    future::poll_fn(|cx| {
+        if flag.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        future.as_mut().poll(cx).map(Some)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for a sync, each step is one item.
+    async fn steps(flag: CancelFlag, cancel_after: usize, done: &mut usize) {
+        for _ in 0..10 {
+            if *done == cancel_after {
+                flag.cancel();
+            }
+            tokio::task::yield_now().await;
+            *done += 1;
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_between_steps() {
+        let flag = CancelFlag::default();
+        let mut done = 0;
+        let result = until_cancelled(&flag, steps(flag.clone(), 3, &mut done)).await;
+        assert_eq!(result, None);
+        assert_eq!(done, 3);
+    }
+
+    #[tokio::test]
+    async fn finishes_without_the_flag() {
+        let flag = CancelFlag::default();
+        let mut done = 0;
+        let result = until_cancelled(&flag, steps(flag.clone(), 20, &mut done)).await;
+        assert_eq!(result, Some(()));
+        assert_eq!(done, 10);
+    }
+
+    #[tokio::test]
+    async fn set_flag_stops_before_starting() {
+        let flag = CancelFlag::default();
+        flag.cancel();
+        let mut done = 0;
+        let result = until_cancelled(&flag, steps(flag.clone(), 20, &mut done)).await;
+        assert_eq!(result, None);
+        assert_eq!(done, 0);
+    }
+}
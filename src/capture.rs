@@ -0,0 +1,125 @@
+use gtk::{
+    gio,
+    glib::{self, ToVariant},
+};
+use relm4::gtk;
+use ruslin_data::{Folder, Note, RuslinData, UpdateSource};
+
+use crate::{
+    app::{NoteEvent, SharedContext},
+    dbus, markdown, AppContext,
+};
+
+const INTERFACE: &str = r#"
+<node>
+  <interface name="org.dianqk.ruslin.Notes">
+    <method name="CreateNote">
+      <arg type="s" name="folder" direction="in"/>
+      <arg type="s" name="title" direction="in"/>
+      <arg type="s" name="body" direction="in"/>
+      <arg type="s" name="id" direction="out"/>
+    </method>
+    <method name="AppendToNote">
+      <arg type="s" name="id" direction="in"/>
+      <arg type="s" name="text" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+// An empty folder puts the note at the top level, an empty title is taken from the body.
+pub fn create_note(
+    data: &RuslinData,
+    folder: &str,
+    title: &str,
+    body: &str,
+) -> Result<Note, String> {
+    let folder_id = match folder {
+        "" => None,
+        folder => Some(find_folder(data, folder)?.id),
+    };
+    let title = match title {
+        "" => markdown::title_from_body(body),
+        title => title.to_string(),
+    };
+    let note = Note::new(folder_id, title, body.to_string());
+    data.db
+        .replace_note(&note, UpdateSource::LocalEdit)
+        .map_err(|e| format!("{e:?}"))?;
+    Ok(note)
+}
+
+pub fn append_to_note(data: &RuslinData, id: &str, text: &str) -> Result<Note, String> {
+    let mut note = data.db.load_note(id).map_err(|e| format!("{e:?}"))?;
+    if !note.body.is_empty() && !note.body.ends_with('\n') {
+        note.body.push('\n');
+    }
+    note.body.push_str(text);
+    data.db
+        .replace_note(&note, UpdateSource::LocalEdit)
+        .map_err(|e| format!("{e:?}"))?;
+    Ok(note)
+}
+
+// Lets the open note list and editors pick up a change made outside of them.
+pub fn notify_changed(ctx: &AppContext, id: String) {
+    // Sending only fails when nothing is listening.
+    let _ = ctx.note_events.send(NoteEvent::Changed { id });
+}
+
+pub fn find_folder(data: &RuslinData, folder: &str) -> Result<Folder, String> {
+    data.db
+        .load_folders()
+        .map_err(|e| format!("{e:?}"))?
+        .into_iter()
+        .find(|f| f.id == folder || f.title == folder)
+        .ok_or_else(|| format!("no folder “{folder}”"))
+}
+
+// Unlike the `app.new-note` action this returns the new note id, and failures are reported as D-Bus errors.
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+    ctx: SharedContext,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTERFACE)?;
+    let interface = node.lookup_interface("org.dianqk.ruslin.Notes").unwrap();
+    connection.register_object(
+        &format!("{object_path}/Notes"),
+        &interface,
+        move |_, _, _, _, method, parameters, invocation| {
+            let ctx = ctx.read().unwrap().clone();
+            let result = match method {
+                "CreateNote" => {
+                    let Some((folder, title, body)) = parameters.get::<(String, String, String)>()
+                    else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    create_note(&ctx.data, &folder, &title, &body)
+                        .map(|note| (note.id.clone(), Some((note.id,).to_variant())))
+                }
+                "AppendToNote" => {
+                    let Some((id, text)) = parameters.get::<(String, String)>() else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    // Only an existing note is changed and announced.
+                    if let Err(e) = ctx.data.db.load_note(&id) {
+                        return invocation
+                            .return_dbus_error(dbus::NOT_FOUND, &format!("no note {id}: {e:?}"));
+                    }
+                    append_to_note(&ctx.data, &id, &text).map(|note| (note.id, None))
+                }
+                _ => return dbus::unknown_method(invocation, method),
+            };
+            match result {
+                Ok((id, reply)) => {
+                    notify_changed(&ctx, id);
+                    invocation.return_value(reply.as_ref());
+                }
+                Err(e) => invocation.return_dbus_error(dbus::FAILED, &e),
+            }
+        },
+        |_, _, _, _, property| dbus::get_property(property),
+        |_, _, _, _, property, _| dbus::set_property(property),
+    )
+}
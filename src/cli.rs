@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use gtk::{
+    gio,
+    glib::{self, ToVariant},
+    prelude::*,
+};
+use relm4::gtk;
+
+use crate::{
+    capture,
+    config::APP_ID,
+    data_dir::{self, DataDir},
+    export::{self, ExportFormat, ExportItems, ExportReport, ExportSelection},
+    print::{self, PrintOptions},
+    search, AppContext,
+};
+
+const SUMMARY: &str = "Commands:
+  new [--folder FOLDER] [TEXT]    Create a note, the text is read from stdin when omitted.
+                                  The running Ruslin window creates it when there is one
+  search [--] QUERY               Print the id and title of notes matching a Joplin search query,
+                                  put -- before a query that starts with -, as in
+                                  `ruslin search -- -tag:done`
+  sync                            Synchronize with the configured server, or ask the running
+                                  Ruslin window to
+  export [--note NOTE_ID | --folder FOLDER] [--format FORMAT] DEST
+                                  Export as markdown, html, pdf or jex, only while Ruslin
+                                  isn't running
+  open NOTE_ID                    Open a note in the running Ruslin window";
+
+pub fn add_main_options(app: &impl IsA<gio::Application>) {
+    app.add_main_option(
+        "folder",
+        glib::Char::from(b'f'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Folder id or title",
+        Some("FOLDER"),
+    );
+    app.add_main_option(
+        "note",
+        glib::Char::from(b'n'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Note id",
+        Some("NOTE_ID"),
+    );
+    app.add_main_option(
+        "format",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Export format: markdown, html, pdf or jex",
+        Some("FORMAT"),
+    );
+    app.add_main_option(
+        "profile",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        &format!(
+            "Use a separate profile, {} sets the data folder directly",
+            data_dir::DATA_DIR_ENV
+        ),
+        Some("NAME"),
+    );
+    app.add_main_option(
+        glib::OPTION_REMAINING,
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::StringArray,
+        "",
+        Some("COMMAND [ARGS…]"),
+    );
+    app.set_option_context_parameter_string(Some("[COMMAND [ARGS…]]"));
+    app.set_option_context_summary(Some(SUMMARY));
+}
+
+pub enum LocalOptions {
+    Exit(i32),
+    // Register and start the window, or hand over to the running instance.
+    Start,
+}
+
+// Only the commands that run here open the notes.
+pub fn handle_local_options(
+    app: &impl IsA<gio::Application>,
+    options: &glib::VariantDict,
+    data_dir: &DataDir,
+) -> LocalOptions {
+    let args: Vec<String> = options
+        .lookup(glib::OPTION_REMAINING)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let option = |name: &str| options.lookup::<String>(name).ok().flatten();
+    let Some((command, args)) = args.split_first() else {
+        return LocalOptions::Start;
+    };
+    // The running window owns the database, the commands go through it or wait for it to quit.
+    let running = running_instance(data_dir);
+    let result = match command.as_str() {
+        "new" => new_note(running.as_ref(), data_dir, option("folder"), args),
+        "search" => search(running.as_ref(), data_dir, args),
+        "sync" => sync(running.as_ref(), data_dir),
+        "export" if running.is_some() => {
+            Err("Ruslin is running, export from its window or quit it first".to_string())
+        }
+        "export" => AppContext::open(data_dir).and_then(|ctx| {
+            export_notes(
+                &ctx,
+                option("note"),
+                option("folder"),
+                option("format"),
+                args,
+            )
+        }),
+        "open" => return open(app.as_ref(), args),
+        _ => Err(format!("unknown command “{command}”, see --help")),
+    };
+    match result {
+        Ok(()) => LocalOptions::Exit(0),
+        Err(e) => {
+            eprintln!("ruslin: {e}");
+            LocalOptions::Exit(1)
+        }
+    }
+}
+
+fn new_note(
+    running: Option<&gio::DBusConnection>,
+    data_dir: &DataDir,
+    folder: Option<String>,
+    args: &[String],
+) -> Result<(), String> {
+    let body = if args.is_empty() {
+        let mut body = String::new();
+        io::stdin()
+            .read_to_string(&mut body)
+            .map_err(|e| e.to_string())?;
+        body
+    } else {
+        args.join(" ")
+    };
+    let folder = folder.unwrap_or_default();
+    let id = match running {
+        Some(connection) => {
+            let reply = call_running_instance(
+                connection,
+                "/Notes",
+                "org.dianqk.ruslin.Notes",
+                "CreateNote",
+                (folder, "", body).to_variant(),
+                "(s)",
+            )?;
+            let (id,) = reply.get::<(String,)>().unwrap_or_default();
+            id
+        }
+        None => {
+            let ctx = AppContext::open(data_dir)?;
+            capture::create_note(&ctx.data, &folder, "", &body)?.id
+        }
+    };
+    println!("{id}");
+    Ok(())
+}
+
+fn search(
+    running: Option<&gio::DBusConnection>,
+    data_dir: &DataDir,
+    args: &[String],
+) -> Result<(), String> {
+    if args.is_empty() {
+        return Err("search needs a query".to_string());
+    }
+    if let Some(unsupported) = search::Query::parse(&args.join(" ")).unsupported() {
+        return Err(unsupported);
+    }
+    let notes = match running {
+        Some(connection) => search_running_instance(connection, args)?,
+        None => {
+            let ctx = AppContext::open(data_dir)?;
+            let query = search::Query::parse(&args.join(" "));
+            search::query_notes(&ctx.data, &ctx.resources_dir, &query)
+                .map_err(|e| format!("{e:?}"))?
+                .into_iter()
+                .map(|abbr_note| (abbr_note.id, abbr_note.title))
+                .collect()
+        }
+    };
+    for (id, title) in notes {
+        println!("{id}\t{title}");
+    }
+    Ok(())
+}
+
+// The search provider runs the same query, the metas carry the titles.
+fn search_running_instance(
+    connection: &gio::DBusConnection,
+    terms: &[String],
+) -> Result<Vec<(String, String)>, String> {
+    const SEARCH_PROVIDER: &str = "org.gnome.Shell.SearchProvider2";
+    let reply = call_running_instance(
+        connection,
+        "/SearchProvider",
+        SEARCH_PROVIDER,
+        "GetInitialResultSet",
+        (terms.to_vec(),).to_variant(),
+        "(as)",
+    )?;
+    let (ids,) = reply.get::<(Vec<String>,)>().unwrap_or_default();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let reply = call_running_instance(
+        connection,
+        "/SearchProvider",
+        SEARCH_PROVIDER,
+        "GetResultMetas",
+        (ids,).to_variant(),
+        "(aa{sv})",
+    )?;
+    let (metas,) = reply
+        .get::<(Vec<HashMap<String, glib::Variant>>,)>()
+        .unwrap_or_default();
+    let meta = |meta: &HashMap<String, glib::Variant>, key: &str| {
+        meta.get(key)
+            .and_then(|value| value.get::<String>())
+            .unwrap_or_default()
+    };
+    Ok(metas
+        .iter()
+        .map(|item| (meta(item, "id"), meta(item, "name")))
+        .collect())
+}
+
+// Two syncs of the same database at once would race, a running window syncs by itself.
+fn sync(running: Option<&gio::DBusConnection>, data_dir: &DataDir) -> Result<(), String> {
+    if let Some(connection) = running {
+        call_running_instance(
+            connection,
+            "",
+            "org.gtk.Actions",
+            "Activate",
+            (
+                "sync",
+                Vec::<glib::Variant>::new(),
+                HashMap::<String, glib::Variant>::new(),
+            )
+                .to_variant(),
+            "()",
+        )?;
+        println!("Syncing in the running Ruslin window.");
+        return Ok(());
+    }
+    let ctx = AppContext::open(data_dir)?;
+    let data = &ctx.data;
+    if !data.sync_exists() {
+        return Err("no server configured, log in from the app first".to_string());
+    }
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    let info = runtime
+        .block_on(data.synchronize(false))
+        .map_err(|e| format!("{e:?}"))?;
+    println!("{info:?}");
+    Ok(())
+}
+
+fn export_notes(
+    ctx: &AppContext,
+    note: Option<String>,
+    folder: Option<String>,
+    format: Option<String>,
+    args: &[String],
+) -> Result<(), String> {
+    let [dest] = args else {
+        return Err("export needs a destination".to_string());
+    };
+    let dest = PathBuf::from(dest);
+    let selection = match (note, folder) {
+        (Some(id), None) => ExportSelection::Note { id },
+        (None, Some(folder)) => ExportSelection::Folder {
+            id: capture::find_folder(&ctx.data, &folder)?.id,
+        },
+        _ => return Err("export needs either --note or --folder".to_string()),
+    };
+    let format = match format.as_deref() {
+        None | Some("markdown") => ExportFormat::Markdown,
+        Some("html") => ExportFormat::Html,
+        Some("pdf") => ExportFormat::Pdf,
+        Some("jex") => ExportFormat::Jex,
+        Some(format) => return Err(format!("unknown format “{format}”")),
+    };
+    let report = if format == ExportFormat::Pdf {
+        gtk::init().map_err(|e| e.to_string())?;
+        let items = ExportItems::load(&ctx.data, &selection).map_err(|e| e.to_string())?;
+        print::export_pdf(
+            items.print_notes(),
+            PrintOptions::load(),
+            &dest,
+            gtk::Window::NONE,
+        )
+        .map_err(|e| e.to_string())?;
+        ExportReport {
+            notes: items.notes.len(),
+            resources: 0,
+        }
+    } else {
+        export::export(
+            ctx.data.clone(),
+            &ctx.resources_dir,
+            &selection,
+            format,
+            &dest,
+        )
+        .map_err(|e| e.to_string())?
+    };
+    println!(
+        "Exported {} notes and {} resources.",
+        report.notes, report.resources
+    );
+    Ok(())
+}
+
+// The session bus when the primary instance owns its name there. Asking it directly doesn't
+// register this one, which would start GTK. An overridden data folder runs on its own.
+fn running_instance(data_dir: &DataDir) -> Option<gio::DBusConnection> {
+    if data_dir.is_override {
+        return None;
+    }
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok()?;
+    let (has_owner,) = connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            Some(&(APP_ID,).to_variant()),
+            Some(glib::VariantTy::new("(b)").unwrap()),
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        )
+        .ok()?
+        .get::<(bool,)>()?;
+    has_owner.then_some(connection)
+}
+
+// `path` is relative to the application's object path.
+fn call_running_instance(
+    connection: &gio::DBusConnection,
+    path: &str,
+    interface: &str,
+    method: &str,
+    parameters: glib::Variant,
+    reply_type: &str,
+) -> Result<glib::Variant, String> {
+    let object_path = format!("/{}{path}", APP_ID.replace('.', "/"));
+    connection
+        .call_sync(
+            Some(APP_ID),
+            &object_path,
+            interface,
+            method,
+            Some(&parameters),
+            Some(glib::VariantTy::new(reply_type).unwrap()),
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        )
+        .map_err(|e| format!("couldn't reach the running Ruslin: {e}"))
+}
+
+// Handled by the primary instance, which starts when none is running yet.
+fn open(app: &gio::Application, args: &[String]) -> LocalOptions {
+    let [id] = args else {
+        eprintln!("ruslin: open needs a note id");
+        return LocalOptions::Exit(1);
+    };
+    if let Err(e) = app.register(gio::Cancellable::NONE) {
+        eprintln!("ruslin: {e}");
+        return LocalOptions::Exit(1);
+    }
+    app.activate_action("open-note", Some(&id.to_variant()));
+    if app.is_remote() {
+        LocalOptions::Exit(0)
+    } else {
+        LocalOptions::Start
+    }
+}
@@ -0,0 +1,740 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    body::HttpBody,
+    header::{self, HeaderValue},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use ruslin_data::{Folder, Note, RuslinData, UpdateSource};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+use crate::{
+    app::{AppMsg, SharedContext},
+    capture, export, search, AppContext,
+};
+
+// The port the Joplin Web Clipper and scripts expect.
+pub const PORT: u16 = 41184;
+
+const PAGE_LIMIT: usize = 100;
+
+// Clipped pages carry their images inline, this leaves room for those.
+const MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
+
+// Unanswered requests and answers the clipper never picked up are dropped after this.
+const AUTH_REQUEST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const MAX_AUTH_REQUESTS: usize = 16;
+
+// Serves a subset of the Joplin Data API on localhost until dropped.
+pub struct ClipperServer {
+    shutdown: Option<oneshot::Sender<()>>,
+    shared: Arc<Shared>,
+}
+
+// What the request handlers share with the app.
+struct Shared {
+    ctx: SharedContext,
+    token: RwLock<String>,
+    auth_requests: Mutex<AuthRequests>,
+    app_sender: relm4::Sender<AppMsg>,
+}
+
+// A Web Clipper asking for the token, see `POST /auth`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AuthStatus {
+    Waiting,
+    Accepted,
+    Rejected,
+}
+
+// By auth token, with the time the request was made.
+#[derive(Debug, Default)]
+struct AuthRequests(HashMap<String, (AuthStatus, Instant)>);
+
+impl AuthRequests {
+    // Only one request waits for the user at a time, so a client can't pile dialogs up.
+    fn start(&mut self, now: Instant) -> Option<String> {
+        self.prune(now);
+        if self
+            .0
+            .values()
+            .any(|(status, _)| *status == AuthStatus::Waiting)
+        {
+            return None;
+        }
+        if self.0.len() >= MAX_AUTH_REQUESTS {
+            let oldest = self
+                .0
+                .iter()
+                .min_by_key(|(_, (_, started))| *started)
+                .map(|(auth_token, _)| auth_token.clone());
+            if let Some(oldest) = oldest {
+                self.0.remove(&oldest);
+            }
+        }
+        let auth_token = new_token();
+        self.0
+            .insert(auth_token.clone(), (AuthStatus::Waiting, now));
+        Some(auth_token)
+    }
+
+    fn answer(&mut self, auth_token: &str, accepted: bool) {
+        if let Some((status @ AuthStatus::Waiting, _)) = self.0.get_mut(auth_token) {
+            *status = if accepted {
+                AuthStatus::Accepted
+            } else {
+                AuthStatus::Rejected
+            };
+        }
+    }
+
+    // An answer is handed out once.
+    fn check(&mut self, auth_token: &str, now: Instant) -> Option<AuthStatus> {
+        self.prune(now);
+        let (status, _) = *self.0.get(auth_token)?;
+        if status != AuthStatus::Waiting {
+            self.0.remove(auth_token);
+        }
+        Some(status)
+    }
+
+    fn prune(&mut self, now: Instant) {
+        self.0
+            .retain(|_, (_, started)| now.duration_since(*started) < AUTH_REQUEST_TIMEOUT);
+    }
+}
+
+impl ClipperServer {
+    // Requests are served from whichever profile is open when they arrive.
+    pub fn start(
+        ctx: SharedContext,
+        token: String,
+        app_sender: relm4::Sender<AppMsg>,
+    ) -> ClipperServer {
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let shared = Arc::new(Shared {
+            ctx,
+            token: RwLock::new(token),
+            auth_requests: Mutex::default(),
+            app_sender,
+        });
+        let server_shared = shared.clone();
+        relm4::spawn(async move {
+            let shared = server_shared;
+            let addr = SocketAddr::from(([127, 0, 0, 1], PORT));
+            let make_service = make_service_fn(move |_| {
+                let shared = shared.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| handle(shared.clone(), request)))
+                }
+            });
+            let server = match Server::try_bind(&addr) {
+                Ok(builder) => builder.serve(make_service),
+                Err(e) => {
+                    log::warn!("web clipper service failed to listen on {addr}: {e}");
+                    return;
+                }
+            };
+            log::info!("web clipper service listening on {addr}");
+            let server = server.with_graceful_shutdown(async {
+                let _ = shutdown_receiver.await;
+            });
+            if let Err(e) = server.await {
+                log::warn!("web clipper service failed: {e}");
+            }
+        });
+        ClipperServer {
+            shutdown: Some(shutdown),
+            shared,
+        }
+    }
+
+    // Takes effect for the next request, the listening socket is kept.
+    pub fn set_token(&self, token: String) {
+        *self.shared.token.write().unwrap() = token;
+    }
+
+    // The clipper picks the answer up with `GET /auth/check`.
+    pub fn answer_auth_request(&self, auth_token: &str, accepted: bool) {
+        self.shared
+            .auth_requests
+            .lock()
+            .unwrap()
+            .answer(auth_token, accepted);
+    }
+}
+
+impl Drop for ClipperServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+pub fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+type ApiResult<T> = Result<T, (StatusCode, String)>;
+
+// Web pages could otherwise reach the API through the user's browser, only the clipper
+// extension and local scripts, which send no origin, are served.
+fn is_extension_origin(origin: &str) -> bool {
+    [
+        "moz-extension://",
+        "chrome-extension://",
+        "safari-web-extension://",
+    ]
+    .iter()
+    .any(|scheme| origin.starts_with(scheme))
+}
+
+async fn handle(shared: Arc<Shared>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let origin = request.headers().get(header::ORIGIN).cloned();
+    let allowed_origin = match &origin {
+        None => None,
+        Some(origin) if origin.to_str().map_or(false, is_extension_origin) => Some(origin.clone()),
+        Some(_) => {
+            return Ok(json_response(
+                StatusCode::FORBIDDEN,
+                json!({ "error": "Only the Web Clipper extension can use this service" }),
+            ))
+        }
+    };
+    let mut response = match route(&shared, request).await {
+        Ok(response) => response,
+        Err((status, message)) => json_response(status, json!({ "error": message })),
+    };
+    if let Some(origin) = allowed_origin {
+        let headers = response.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST, PUT, DELETE"),
+        );
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static("Content-Type"),
+        );
+    }
+    Ok(response)
+}
+
+async fn route(shared: &Shared, request: Request<Body>) -> ApiResult<Response<Body>> {
+    let method = request.method().clone();
+    let query: HashMap<String, String> = request
+        .uri()
+        .query()
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let path: Vec<String> = request
+        .uri()
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if method == Method::OPTIONS {
+        return Ok(Response::new(Body::empty()));
+    }
+    match (
+        &method,
+        path.iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice(),
+    ) {
+        (&Method::GET, ["ping"]) => return Ok(Response::new(Body::from("JoplinClipperServer"))),
+        (&Method::POST, ["auth"]) => {
+            let auth_token = shared
+                .auth_requests
+                .lock()
+                .unwrap()
+                .start(Instant::now())
+                .ok_or((
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Another authorization request is waiting".to_string(),
+                ))?;
+            shared
+                .app_sender
+                .send(AppMsg::AuthorizeClipper {
+                    auth_token: auth_token.clone(),
+                })
+                .map_err(|_| {
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Ruslin is shutting down".to_string(),
+                    )
+                })?;
+            return Ok(json_response(
+                StatusCode::OK,
+                json!({ "auth_token": auth_token }),
+            ));
+        }
+        (&Method::GET, ["auth", "check"]) => {
+            let auth_token = query.get("auth_token").map_or("", String::as_str);
+            let status = shared
+                .auth_requests
+                .lock()
+                .unwrap()
+                .check(auth_token, Instant::now());
+            let reply = match status {
+                None => return Err((StatusCode::NOT_FOUND, "Unknown auth token".to_string())),
+                Some(AuthStatus::Waiting) => json!({ "status": "waiting" }),
+                Some(AuthStatus::Accepted) => {
+                    let token = shared.token.read().unwrap().clone();
+                    json!({ "status": "accepted", "token": token })
+                }
+                Some(AuthStatus::Rejected) => json!({ "status": "rejected" }),
+            };
+            return Ok(json_response(StatusCode::OK, reply));
+        }
+        _ => {}
+    }
+    if query.get("token") != Some(&*shared.token.read().unwrap()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Invalid \"token\" parameter".to_string(),
+        ));
+    }
+    let payload = if method == Method::POST || method == Method::PUT {
+        let bytes = read_body(request).await?;
+        serde_json::from_slice::<Value>(&bytes)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    } else {
+        Value::Null
+    };
+
+    let ctx = shared.ctx.read().unwrap().clone();
+    tokio::task::spawn_blocking(move || {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        serve(&ctx, &method, &path, &query, &payload)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+// Gives up past MAX_BODY_SIZE, before reading when the length is declared.
+async fn read_body(request: Request<Body>) -> ApiResult<Vec<u8>> {
+    let too_large = || {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body too large".to_string(),
+        )
+    };
+    let declared_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+    if declared_length.map_or(false, |length| length > MAX_BODY_SIZE) {
+        return Err(too_large());
+    }
+    let mut body = request.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if bytes.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+// Reads and writes the database, so it runs on a blocking thread.
+fn serve(
+    ctx: &AppContext,
+    method: &Method,
+    path: &[&str],
+    query: &HashMap<String, String>,
+    payload: &Value,
+) -> ApiResult<Response<Body>> {
+    let data = &ctx.data;
+    let reply = match (method, path) {
+        (&Method::GET, ["folders"]) => {
+            let folders = data.db.load_folders().map_err(database_error)?;
+            paginate(query, folders.iter().map(folder_json).collect())
+        }
+        (&Method::POST, ["folders"]) => folder_json(&create_folder(data, payload)?),
+        (&Method::GET, ["folders", id]) => folder_json(&find_folder(data, id)?),
+        (&Method::GET, ["folders", id, "notes"]) => {
+            let notes = data.db.load_abbr_notes(Some(*id)).map_err(database_error)?;
+            paginate(
+                query,
+                notes
+                    .iter()
+                    .map(|note| json!({ "id": note.id, "parent_id": id, "title": note.title }))
+                    .collect(),
+            )
+        }
+        (&Method::GET, ["notes"]) => {
+            let notes = data.db.load_abbr_notes(None).map_err(database_error)?;
+            paginate(
+                query,
+                notes
+                    .iter()
+                    .map(|note| json!({ "id": note.id, "title": note.title }))
+                    .collect(),
+            )
+        }
+        (&Method::POST, ["notes"]) => {
+            let note = create_note(ctx, payload)?;
+            note_json(&note)
+        }
+        (&Method::GET, ["notes", id]) => note_json(&load_note(data, id)?),
+        (&Method::PUT, ["notes", id]) => {
+            let note = update_note(ctx, id, payload)?;
+            note_json(&note)
+        }
+        (&Method::DELETE, ["notes", id]) => {
+            data.db.delete_note(id).map_err(database_error)?;
+            capture::notify_changed(ctx, id.to_string());
+            Value::Null
+        }
+        // Tags aren't stored by ruslin-data, so there are never any.
+        (&Method::GET, ["tags"]) | (&Method::GET, ["notes", _, "tags"]) => {
+            paginate(query, Vec::new())
+        }
+        (&Method::GET, ["resources"]) => paginate(
+            query,
+            resource_files(&ctx.resources_dir)
+                .iter()
+                .map(|(id, path)| resource_json(id, path))
+                .collect(),
+        ),
+        (&Method::GET, ["resources", id]) => {
+            let path = find_resource(&ctx.resources_dir, id)?;
+            resource_json(id, &path)
+        }
+        (&Method::GET, ["resources", id, "file"]) => {
+            let path = find_resource(&ctx.resources_dir, id)?;
+            let bytes =
+                fs::read(&path).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let mut response = Response::new(Body::from(bytes));
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(export::mime_type(&path)),
+            );
+            return Ok(response);
+        }
+        (&Method::GET, ["search"]) => {
+            let search_query = search::Query::parse(query.get("query").map_or("", String::as_str));
+            if let Some(unsupported) = search_query.unsupported() {
+                return Err((StatusCode::BAD_REQUEST, unsupported));
+            }
+            let notes = search::query_notes(data, &ctx.resources_dir, &search_query)
+                .map_err(database_error)?;
+            paginate(
+                query,
+                notes
+                    .iter()
+                    .map(|note| json!({ "id": note.id, "title": note.title }))
+                    .collect(),
+            )
+        }
+        // ruslin-data has no API for adding resources or tags. Screenshots sent as
+        // `image_data_url` with a new note are kept inline in its body instead.
+        (_, ["tags", ..]) | (_, ["resources", ..]) => {
+            return Err((
+                StatusCode::NOT_IMPLEMENTED,
+                "Creating tags and resources isn't supported".to_string(),
+            ))
+        }
+        _ => return Err((StatusCode::NOT_FOUND, "Not Found".to_string())),
+    };
+    Ok(json_response(StatusCode::OK, reply))
+}
+
+fn json_response(status: StatusCode, value: Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(value.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn database_error(e: impl std::fmt::Debug) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}"))
+}
+
+// `page` starts at 1, like Joplin.
+fn paginate(query: &HashMap<String, String>, items: Vec<Value>) -> Value {
+    let limit = query
+        .get("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(PAGE_LIMIT)
+        .clamp(1, PAGE_LIMIT);
+    let page = query
+        .get("page")
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1usize)
+        .max(1);
+    let start = (page - 1).saturating_mul(limit);
+    let has_more = items.len().saturating_sub(start) > limit;
+    let items: Vec<Value> = items.into_iter().skip(start).take(limit).collect();
+    json!({ "items": items, "has_more": has_more })
+}
+
+fn str_field<'a>(body: &'a Value, key: &str) -> Option<&'a str> {
+    body.get(key).and_then(Value::as_str)
+}
+
+fn folder_json(folder: &Folder) -> Value {
+    json!({ "id": folder.id, "title": folder.title })
+}
+
+fn note_json(note: &Note) -> Value {
+    json!({
+        "id": note.id,
+        "parent_id": note.parent_id.clone().unwrap_or_default(),
+        "title": note.get_title(),
+        "body": note.body,
+    })
+}
+
+fn find_folder(data: &RuslinData, id: &str) -> ApiResult<Folder> {
+    data.db
+        .load_folders()
+        .map_err(database_error)?
+        .into_iter()
+        .find(|folder| folder.id == id)
+        .ok_or((StatusCode::NOT_FOUND, "Not Found".to_string()))
+}
+
+fn create_folder(data: &RuslinData, body: &Value) -> ApiResult<Folder> {
+    let title = str_field(body, "title").unwrap_or_default().to_string();
+    let known_ids: Vec<String> = data
+        .db
+        .load_folders()
+        .map_err(database_error)?
+        .into_iter()
+        .map(|folder| folder.id)
+        .collect();
+    data.db.insert_root_folder(title).map_err(database_error)?;
+    data.db
+        .load_folders()
+        .map_err(database_error)?
+        .into_iter()
+        .find(|folder| !known_ids.contains(&folder.id))
+        .ok_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "created folder not found".to_string(),
+            )
+        })
+}
+
+fn load_note(data: &RuslinData, id: &str) -> ApiResult<Note> {
+    data.db
+        .load_note(id)
+        .map_err(|_| (StatusCode::NOT_FOUND, "Not Found".to_string()))
+}
+
+// The clipper sends `body_html` for pages and selections, and `image_data_url` for screenshots.
+fn create_note(ctx: &AppContext, body: &Value) -> ApiResult<Note> {
+    let mut text = match (str_field(body, "body"), str_field(body, "body_html")) {
+        (Some(markdown), _) => markdown.to_string(),
+        (None, Some(html)) => html2md::parse_html(html),
+        (None, None) => String::new(),
+    };
+    if let Some(image) = str_field(body, "image_data_url") {
+        if !image.starts_with("data:image/") {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "image_data_url must be an image data URL".to_string(),
+            ));
+        }
+        // Without a resource API the screenshot stays in the note body, it isn't synced as a
+        // separate resource like Joplin does.
+        let title = str_field(body, "title").unwrap_or("Screenshot");
+        text = format!("{text}\n\n![{title}]({image})");
+    }
+    if let Some(source_url) = str_field(body, "source_url") {
+        text = format!("{text}\n\n[Source]({source_url})");
+    }
+    let text = text.trim_start().to_string();
+    let note = capture::create_note(
+        &ctx.data,
+        str_field(body, "parent_id").unwrap_or_default(),
+        str_field(body, "title").unwrap_or_default(),
+        &text,
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    capture::notify_changed(ctx, note.id.clone());
+    Ok(note)
+}
+
+fn update_note(ctx: &AppContext, id: &str, body: &Value) -> ApiResult<Note> {
+    let mut note = load_note(&ctx.data, id)?;
+    if let Some(title) = str_field(body, "title") {
+        note.set_title(title);
+    }
+    if let Some(text) = str_field(body, "body") {
+        note.body = text.to_string();
+    }
+    match str_field(body, "parent_id") {
+        None => {}
+        // Joplin's top level.
+        Some("") => note.parent_id = None,
+        Some(parent_id) => {
+            let folder = find_folder(&ctx.data, parent_id).map_err(|(status, e)| match status {
+                StatusCode::NOT_FOUND => {
+                    (StatusCode::BAD_REQUEST, format!("No folder {parent_id}"))
+                }
+                status => (status, e),
+            })?;
+            note.parent_id = Some(folder.id);
+        }
+    }
+    ctx.data
+        .db
+        .replace_note(&note, UpdateSource::LocalEdit)
+        .map_err(database_error)?;
+    capture::notify_changed(ctx, note.id.clone());
+    Ok(note)
+}
+
+// Resources are stored as `<resources_dir>/<id>.<extension>`.
+fn resource_files(resources_dir: &Path) -> Vec<(String, PathBuf)> {
+    fs::read_dir(resources_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path.file_stem()?.to_str()?.to_string();
+            Some((id, path))
+        })
+        .collect()
+}
+
+fn find_resource(resources_dir: &Path, id: &str) -> ApiResult<PathBuf> {
+    resource_files(resources_dir)
+        .into_iter()
+        .find(|(resource_id, _)| resource_id == id)
+        .map(|(_, path)| path)
+        .ok_or((StatusCode::NOT_FOUND, "Not Found".to_string()))
+}
+
+fn resource_json(id: &str, path: &Path) -> Value {
+    json!({
+        "id": id,
+        "title": path.file_name().map(|name| name.to_string_lossy().to_string()),
+        "mime": export::mime_type(path),
+        "file_extension": path.extension().map(|extension| extension.to_string_lossy().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_extensions_are_allowed_origins() {
+        assert!(is_extension_origin("moz-extension://1234-abcd"));
+        assert!(is_extension_origin("chrome-extension://abcdef"));
+        assert!(!is_extension_origin("https://example.com"));
+        assert!(!is_extension_origin("http://localhost:41184"));
+        assert!(!is_extension_origin("null"));
+    }
+
+    #[test]
+    fn paginate_pages_from_one() {
+        let items: Vec<Value> = (0..5).map(|i| json!(i)).collect();
+        let query = HashMap::from([
+            ("limit".to_string(), "2".to_string()),
+            ("page".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(
+            paginate(&query, items.clone()),
+            json!({ "items": [2, 3], "has_more": true })
+        );
+        let query = HashMap::from([
+            ("limit".to_string(), "2".to_string()),
+            ("page".to_string(), "3".to_string()),
+        ]);
+        assert_eq!(
+            paginate(&query, items),
+            json!({ "items": [4], "has_more": false })
+        );
+    }
+
+    #[test]
+    fn paginate_past_the_end() {
+        let items: Vec<Value> = (0..5).map(|i| json!(i)).collect();
+        let query = HashMap::from([
+            ("limit".to_string(), "100".to_string()),
+            ("page".to_string(), usize::MAX.to_string()),
+        ]);
+        assert_eq!(
+            paginate(&query, items),
+            json!({ "items": [], "has_more": false })
+        );
+    }
+
+    #[test]
+    fn one_auth_request_waits_at_a_time() {
+        let now = Instant::now();
+        let mut requests = AuthRequests::default();
+        let first = requests.start(now).unwrap();
+        assert_eq!(requests.start(now), None);
+        requests.answer(&first, false);
+        let second = requests.start(now).unwrap();
+        assert_eq!(requests.check(&first, now), Some(AuthStatus::Rejected));
+        assert_eq!(requests.check(&first, now), None);
+        requests.answer(&second, true);
+        // The answer can't be changed once given.
+        requests.answer(&second, false);
+        assert_eq!(requests.check(&second, now), Some(AuthStatus::Accepted));
+    }
+
+    #[test]
+    fn auth_requests_expire() {
+        let now = Instant::now();
+        let mut requests = AuthRequests::default();
+        let auth_token = requests.start(now).unwrap();
+        assert_eq!(requests.check(&auth_token, now), Some(AuthStatus::Waiting));
+        let later = now + AUTH_REQUEST_TIMEOUT;
+        assert_eq!(requests.check(&auth_token, later), None);
+        assert!(requests.start(later).is_some());
+    }
+
+    #[test]
+    fn unpicked_answers_are_capped() {
+        let now = Instant::now();
+        let mut requests = AuthRequests::default();
+        let mut auth_tokens = Vec::new();
+        for i in 0..MAX_AUTH_REQUESTS + 2 {
+            let auth_token = requests.start(now + Duration::from_secs(i as u64)).unwrap();
+            requests.answer(&auth_token, true);
+            auth_tokens.push(auth_token);
+        }
+        assert_eq!(requests.0.len(), MAX_AUTH_REQUESTS);
+        let now = now + Duration::from_secs(MAX_AUTH_REQUESTS as u64 + 2);
+        assert_eq!(requests.check(&auth_tokens[0], now), None);
+        assert_eq!(
+            requests.check(auth_tokens.last().unwrap(), now),
+            Some(AuthStatus::Accepted)
+        );
+    }
+}
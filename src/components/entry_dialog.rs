@@ -16,6 +16,8 @@ pub struct EntryDialogInit {
 #[derive(Debug)]
 pub enum EntryDialogInput {
     Show,
+    // Shows the dialog with the text to edit.
+    ShowWithText(String),
     Hide,
     ChangeButtonSensitive(bool),
     ConfirmText,
@@ -111,6 +113,10 @@ impl SimpleComponent for EntryDialogModel {
     fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>) {
         match input {
             EntryDialogInput::Show => self.visible = true,
+            EntryDialogInput::ShowWithText(text) => {
+                self.entry.set_text(&text);
+                self.visible = true;
+            }
             EntryDialogInput::Hide => self.visible = false,
             EntryDialogInput::ChangeButtonSensitive(sensitive) => self.button_sensitive = sensitive,
             EntryDialogInput::ConfirmText => {
@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use relm4::{gtk, Component, ComponentParts, ComponentSender, RelmWidgetExt};
+
+use crate::{
+    export::{self, ExportFormat, ExportItems, ExportReport, ExportSelection},
+    print::{self, PrintOptions},
+    AppContext,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportPage {
+    Choose,
+    Exporting,
+    Finished,
+}
+
+impl ExportPage {
+    fn name(self) -> &'static str {
+        match self {
+            ExportPage::Choose => "choose",
+            ExportPage::Exporting => "exporting",
+            ExportPage::Finished => "finished",
+        }
+    }
+}
+
+pub struct ExportDialogModel {
+    ctx: AppContext,
+    visible: bool,
+    page: ExportPage,
+    selection: Option<ExportSelection>,
+    name: String,
+    summary: String,
+    file_chooser: Option<gtk::FileChooserNative>,
+}
+
+#[derive(Debug)]
+pub enum ExportDialogInput {
+    Show {
+        selection: ExportSelection,
+        name: String,
+    },
+    Hide,
+    ChooseFormat(ExportFormat),
+    Export {
+        format: ExportFormat,
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug)]
+pub enum ExportDialogCommand {
+    Finished(ExportReport),
+    Failed(String),
+}
+
+#[relm4::component(pub)]
+impl Component for ExportDialogModel {
+    type Init = AppContext;
+    type Input = ExportDialogInput;
+    type Output = ();
+    type CommandOutput = ExportDialogCommand;
+
+    view! {
+        adw::Window {
+            #[watch]
+            set_visible: model.visible,
+            set_modal: true,
+            set_default_width: 420,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+
+                adw::HeaderBar {
+                    #[wrap(Some)]
+                    set_title_widget = &adw::WindowTitle {
+                        set_title: "Export",
+                        #[watch]
+                        set_subtitle: &model.name,
+                        add_css_class: "flat",
+                    },
+                },
+
+                gtk::Stack {
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible_child_name: model.page.name(),
+
+                    add_named[Some("choose")] = &adw::PreferencesGroup {
+                        set_margin_all: 12,
+                        set_valign: gtk::Align::Start,
+
+                        add = &adw::ActionRow {
+                            set_title: "Markdown",
+                            set_subtitle: "A directory of .md files with their resources",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ExportDialogInput::ChooseFormat(ExportFormat::Markdown));
+                            },
+                        },
+                        add = &adw::ActionRow {
+                            set_title: "HTML",
+                            set_subtitle: "Standalone pages with embedded images",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ExportDialogInput::ChooseFormat(ExportFormat::Html));
+                            },
+                        },
+                        add = &adw::ActionRow {
+                            set_title: "PDF",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ExportDialogInput::ChooseFormat(ExportFormat::Pdf));
+                            },
+                        },
+                        add = &adw::ActionRow {
+                            set_title: "Joplin Export",
+                            set_subtitle: "A .jex archive that Joplin can import",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ExportDialogInput::ChooseFormat(ExportFormat::Jex));
+                            },
+                        },
+                    },
+
+                    add_named[Some("exporting")] = &gtk::Spinner {
+                        set_valign: gtk::Align::Center,
+                        set_halign: gtk::Align::Center,
+                        set_size_request: (32, 32),
+                        #[watch]
+                        set_spinning: model.page == ExportPage::Exporting,
+                    },
+
+                    add_named[Some("finished")] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 12,
+                        set_margin_all: 12,
+
+                        gtk::Label {
+                            set_wrap: true,
+                            set_xalign: 0.0,
+                            set_vexpand: true,
+                            #[watch]
+                            set_label: &model.summary,
+                        },
+                        gtk::Button {
+                            set_label: "Close",
+                            set_halign: gtk::Align::End,
+                            add_css_class: "suggested-action",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(ExportDialogInput::Hide);
+                            },
+                        },
+                    },
+                },
+            },
+
+            connect_close_request[sender] => move |_| {
+                sender.input(ExportDialogInput::Hide);
+                gtk::Inhibit(true)
+            },
+        }
+    }
+
+    fn init(
+        ctx: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ExportDialogModel {
+            ctx,
+            visible: false,
+            page: ExportPage::Choose,
+            selection: None,
+            name: String::new(),
+            summary: String::new(),
+            file_chooser: None,
+        };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match input {
+            ExportDialogInput::Show { selection, name } => {
+                self.selection = Some(selection);
+                self.name = name;
+                self.page = ExportPage::Choose;
+                self.visible = true;
+            }
+            ExportDialogInput::Hide => {
+                if self.page != ExportPage::Exporting {
+                    self.visible = false;
+                }
+            }
+            ExportDialogInput::ChooseFormat(format) => {
+                let is_single_note = matches!(self.selection, Some(ExportSelection::Note { .. }));
+                let select_folder = match format {
+                    ExportFormat::Markdown => true,
+                    ExportFormat::Html => !is_single_note,
+                    ExportFormat::Pdf | ExportFormat::Jex => false,
+                };
+                let file_chooser = if select_folder {
+                    gtk::FileChooserNative::new(
+                        Some("Select Folder"),
+                        Some(root),
+                        gtk::FileChooserAction::SelectFolder,
+                        Some("Export"),
+                        Some("Cancel"),
+                    )
+                } else {
+                    let file_chooser = gtk::FileChooserNative::new(
+                        Some("Export As"),
+                        Some(root),
+                        gtk::FileChooserAction::Save,
+                        Some("Export"),
+                        Some("Cancel"),
+                    );
+                    if let Some(extension) = format.extension() {
+                        file_chooser.set_current_name(&format!("{}.{extension}", self.name));
+                    }
+                    file_chooser
+                };
+                file_chooser.connect_response(move |file_chooser, response| {
+                    if response == gtk::ResponseType::Accept {
+                        if let Some(path) = file_chooser.file().and_then(|file| file.path()) {
+                            sender.input(ExportDialogInput::Export { format, path });
+                        }
+                    }
+                });
+                file_chooser.show();
+                self.file_chooser = Some(file_chooser);
+            }
+            ExportDialogInput::Export { format, path } => {
+                self.file_chooser = None;
+                let Some(selection) = self.selection.clone() else {
+                    return;
+                };
+                if format == ExportFormat::Pdf {
+                    let result = ExportItems::load(&self.ctx.data, &selection)
+                        .map_err(|e| e.to_string())
+                        .and_then(|items| {
+                            let notes = items.notes.len();
+                            let options = PrintOptions::load();
+                            print::export_pdf(items.print_notes(), options, &path, Some(root))
+                                .map(|_| ExportReport {
+                                    notes,
+                                    resources: 0,
+                                })
+                                .map_err(|e| e.to_string())
+                        });
+                    sender
+                        .command_sender()
+                        .send(match result {
+                            Ok(report) => ExportDialogCommand::Finished(report),
+                            Err(e) => ExportDialogCommand::Failed(e),
+                        })
+                        .unwrap();
+                    return;
+                }
+                self.page = ExportPage::Exporting;
+                let data = self.ctx.data.clone();
+                let resources_dir = self.ctx.resources_dir.clone();
+                sender.spawn_oneshot_command(move || {
+                    match export::export(data, &resources_dir, &selection, format, &path) {
+                        Ok(report) => ExportDialogCommand::Finished(report),
+                        Err(e) => ExportDialogCommand::Failed(e.to_string()),
+                    }
+                });
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        self.page = ExportPage::Finished;
+        self.summary = match message {
+            ExportDialogCommand::Finished(report) if report.resources > 0 => format!(
+                "Exported {} notes and {} resources.",
+                report.notes, report.resources
+            ),
+            ExportDialogCommand::Finished(report) => format!("Exported {} notes.", report.notes),
+            ExportDialogCommand::Failed(e) => format!("Export failed: {e}"),
+        };
+    }
+}
@@ -0,0 +1,322 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use relm4::{gtk, Component, ComponentParts, ComponentSender, RelmWidgetExt};
+
+use crate::{
+    import::{self, ImportProgress, ImportReport, ImportSource},
+    AppContext,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportPage {
+    Choose,
+    Importing,
+    Finished,
+}
+
+impl ImportPage {
+    fn name(self) -> &'static str {
+        match self {
+            ImportPage::Choose => "choose",
+            ImportPage::Importing => "importing",
+            ImportPage::Finished => "finished",
+        }
+    }
+}
+
+pub struct ImportDialogModel {
+    ctx: AppContext,
+    visible: bool,
+    page: ImportPage,
+    progress: f64,
+    status: String,
+    summary: String,
+    skipped: String,
+    file_chooser: Option<gtk::FileChooserNative>,
+}
+
+#[derive(Debug)]
+pub enum ImportDialogInput {
+    Show,
+    Hide,
+    ChooseSource(ImportSource),
+    Import { source: ImportSource, path: PathBuf },
+}
+
+#[derive(Debug)]
+pub enum ImportDialogCommand {
+    Progress(ImportProgress),
+    Finished(ImportReport),
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub enum ImportDialogOutput {
+    Imported,
+}
+
+#[relm4::component(pub)]
+impl Component for ImportDialogModel {
+    type Init = AppContext;
+    type Input = ImportDialogInput;
+    type Output = ImportDialogOutput;
+    type CommandOutput = ImportDialogCommand;
+
+    view! {
+        adw::Window {
+            #[watch]
+            set_visible: model.visible,
+            set_modal: true,
+            set_default_width: 420,
+            set_default_height: 360,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+
+                adw::HeaderBar {
+                    #[wrap(Some)]
+                    set_title_widget = &adw::WindowTitle {
+                        set_title: "Import",
+                        add_css_class: "flat",
+                    },
+                },
+
+                gtk::Stack {
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible_child_name: model.page.name(),
+
+                    add_named[Some("choose")] = &adw::PreferencesGroup {
+                        set_margin_all: 12,
+                        set_valign: gtk::Align::Start,
+                        set_description: Some("Attachments, to-do state and note times aren't imported yet. Notes that fail to import are listed at the end."),
+
+                        add = &adw::ActionRow {
+                            set_title: "Markdown Folder",
+                            set_subtitle: "A directory of .md files",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ImportDialogInput::ChooseSource(ImportSource::MarkdownDirectory));
+                            },
+                        },
+                        add = &adw::ActionRow {
+                            set_title: "Joplin Export",
+                            set_subtitle: "A .jex archive",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ImportDialogInput::ChooseSource(ImportSource::Jex));
+                            },
+                        },
+                        add = &adw::ActionRow {
+                            set_title: "Evernote Export",
+                            set_subtitle: "An .enex file",
+                            set_activatable: true,
+                            connect_activated[sender] => move |_| {
+                                sender.input(ImportDialogInput::ChooseSource(ImportSource::Enex));
+                            },
+                        },
+                    },
+
+                    add_named[Some("importing")] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_valign: gtk::Align::Center,
+                        set_spacing: 12,
+                        set_margin_all: 24,
+
+                        gtk::ProgressBar {
+                            #[watch]
+                            set_fraction: model.progress,
+                        },
+                        gtk::Label {
+                            set_ellipsize: gtk::pango::EllipsizeMode::Middle,
+                            add_css_class: "dim-label",
+                            #[watch]
+                            set_label: &model.status,
+                        },
+                    },
+
+                    add_named[Some("finished")] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 12,
+                        set_margin_all: 12,
+
+                        gtk::Label {
+                            set_wrap: true,
+                            set_xalign: 0.0,
+                            add_css_class: "heading",
+                            #[watch]
+                            set_label: &model.summary,
+                        },
+                        gtk::ScrolledWindow {
+                            set_vexpand: true,
+                            #[watch]
+                            set_visible: !model.skipped.is_empty(),
+
+                            gtk::Label {
+                                set_selectable: true,
+                                set_wrap: true,
+                                set_xalign: 0.0,
+                                set_yalign: 0.0,
+                                #[watch]
+                                set_label: &model.skipped,
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Close",
+                            set_halign: gtk::Align::End,
+                            add_css_class: "suggested-action",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(ImportDialogInput::Hide);
+                            },
+                        },
+                    },
+                },
+            },
+
+            connect_close_request[sender] => move |_| {
+                sender.input(ImportDialogInput::Hide);
+                gtk::Inhibit(true)
+            },
+        }
+    }
+
+    fn init(
+        ctx: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ImportDialogModel {
+            ctx,
+            visible: false,
+            page: ImportPage::Choose,
+            progress: 0.0,
+            status: String::new(),
+            summary: String::new(),
+            skipped: String::new(),
+            file_chooser: None,
+        };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match input {
+            ImportDialogInput::Show => {
+                self.page = ImportPage::Choose;
+                self.visible = true;
+            }
+            ImportDialogInput::Hide => {
+                // An import can't be interrupted halfway, the dialog stays until it is done.
+                if self.page != ImportPage::Importing {
+                    self.visible = false;
+                }
+            }
+            ImportDialogInput::ChooseSource(source) => {
+                let (title, action) = match source {
+                    ImportSource::MarkdownDirectory => {
+                        ("Select Folder", gtk::FileChooserAction::SelectFolder)
+                    }
+                    ImportSource::Jex | ImportSource::Enex => {
+                        ("Select File", gtk::FileChooserAction::Open)
+                    }
+                };
+                let file_chooser = gtk::FileChooserNative::new(
+                    Some(title),
+                    Some(root),
+                    action,
+                    Some("Import"),
+                    Some("Cancel"),
+                );
+                let pattern = match source {
+                    ImportSource::MarkdownDirectory => None,
+                    ImportSource::Jex => Some("*.jex"),
+                    ImportSource::Enex => Some("*.enex"),
+                };
+                if let Some(pattern) = pattern {
+                    let filter = gtk::FileFilter::new();
+                    filter.add_pattern(pattern);
+                    filter.set_name(Some(pattern));
+                    file_chooser.add_filter(&filter);
+                }
+                file_chooser.connect_response(move |file_chooser, response| {
+                    if response == gtk::ResponseType::Accept {
+                        if let Some(path) = file_chooser.file().and_then(|file| file.path()) {
+                            sender.input(ImportDialogInput::Import { source, path });
+                        }
+                    }
+                });
+                file_chooser.show();
+                self.file_chooser = Some(file_chooser);
+            }
+            ImportDialogInput::Import { source, path } => {
+                self.file_chooser = None;
+                self.page = ImportPage::Importing;
+                self.progress = 0.0;
+                self.status = path.display().to_string();
+                let data = self.ctx.data.clone();
+                sender.spawn_command(move |out| {
+                    let progress_out = out.clone();
+                    let result = import::import(data, source, &path, |progress| {
+                        progress_out
+                            .send(ImportDialogCommand::Progress(progress))
+                            .unwrap();
+                    });
+                    out.send(match result {
+                        Ok(report) => ImportDialogCommand::Finished(report),
+                        Err(e) => ImportDialogCommand::Failed(e.to_string()),
+                    })
+                    .unwrap();
+                });
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            ImportDialogCommand::Progress(progress) => {
+                if progress.total > 0 {
+                    self.progress = progress.done as f64 / progress.total as f64;
+                }
+                self.status = progress.current;
+            }
+            ImportDialogCommand::Finished(report) => {
+                self.page = ImportPage::Finished;
+                self.summary = format!(
+                    "Imported {} notes into {} folders, {} items skipped.",
+                    report.notes,
+                    report.folders,
+                    report.skipped.len()
+                );
+                let not_kept = report
+                    .not_kept
+                    .iter()
+                    .map(|what| format!("Not kept: {what}"));
+                self.skipped = not_kept
+                    .chain(
+                        report
+                            .skipped
+                            .iter()
+                            .map(|item| format!("{}: {}", item.name, item.reason)),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                sender.output(ImportDialogOutput::Imported).unwrap();
+            }
+            ImportDialogCommand::Failed(e) => {
+                self.page = ImportPage::Finished;
+                self.summary = format!("Import failed: {e}");
+                self.skipped = String::new();
+                // Whatever was imported before the failure is kept.
+                sender.output(ImportDialogOutput::Imported).unwrap();
+            }
+        }
+    }
+}
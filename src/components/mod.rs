@@ -1,3 +1,7 @@
 mod entry_dialog;
+mod export_dialog;
+mod import_dialog;
 
 pub use entry_dialog::*;
+pub use export_dialog::*;
+pub use import_dialog::*;
@@ -2,8 +2,10 @@ pub mod note_editor_column;
 pub mod note_list_column;
 pub mod sidebar_column;
 
+use std::collections::HashMap;
+
 use adw::prelude::*;
-use gtk::glib;
+use gtk::{gdk, glib};
 use relm4::{
     gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
     SimpleComponent,
@@ -15,20 +17,30 @@ use sidebar_column::SidebarColumnModel;
 
 use crate::{
     app::AppContext,
+    components::{ExportDialogInput, ExportDialogModel},
     content_page::{
         note_editor_column::{NoteEditorColumnInit, NoteEditorColumnInput},
         note_list_column::{NoteListColumInit, NoteListColumnOutput},
         sidebar_column::{SidebarColumnInit, SidebarColumnOutput},
     },
+    export::ExportSelection,
+    note_window::{NoteWindowInit, NoteWindowModel, NoteWindowOutput},
+    print::PrintSession,
     properties,
 };
 
-use self::note_list_column::NoteListColumnInput;
+use self::{note_list_column::NoteListColumnInput, sidebar_column::SidebarColumnInput};
 
 pub struct ContentPageModel {
     note_list_column: Controller<NoteListColumnModel>,
     note_editor_column: Controller<NoteEditorColumnModel>,
     sidebar_column: Controller<SidebarColumnModel>,
+    note_windows: HashMap<String, Controller<NoteWindowModel>>,
+    export_dialog: Controller<ExportDialogModel>,
+    ctx: AppContext,
+    // Shared by the main editor and the note windows.
+    print_session: PrintSession,
+    navigate_forward: bool,
 }
 
 pub struct ContentPageInit {
@@ -37,14 +49,59 @@ pub struct ContentPageInit {
 
 #[derive(Debug)]
 pub enum ContentPageInput {
-    OpenFolder { folder_id: Option<String> },
+    OpenFolder {
+        folder_id: Option<String>,
+    },
+    OpenSearch {
+        query: String,
+    },
+    CreateNote,
+    ShowCreateFolderDialog,
+    ToggleSearch,
+    SyncRemote,
+    SyncFromScratch,
+    ToggleSidebar,
+    TogglePreview,
+    Print,
+    DeleteNote,
+    FocusNoteList,
+    FocusEditor,
+    SelectNextNote,
+    SelectPreviousNote,
+    EditNote {
+        id: String,
+    },
+    EditNewNote {
+        folder_id: Option<String>,
+    },
+    NoteDeleted {
+        id: String,
+    },
+    OpenNoteWindow {
+        id: String,
+    },
+    NoteWindowClosed {
+        id: String,
+    },
+    ShowExportDialog {
+        selection: ExportSelection,
+        name: String,
+    },
+    SwitchProfile {
+        profile: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+pub enum ContentPageOutput {
+    SwitchProfile { profile: Option<String> },
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for ContentPageModel {
     type Init = ContentPageInit;
     type Input = ContentPageInput;
-    type Output = ();
+    type Output = ContentPageOutput;
     type Widgets = ComponentWidgets;
 
     view! {
@@ -88,9 +145,11 @@ impl SimpleComponent for ContentPageModel {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
+        let print_session = PrintSession::default();
         let note_editor_column = note_editor_column::NoteEditorColumnModel::builder()
             .launch(NoteEditorColumnInit {
                 ctx: init.ctx.clone(),
+                print_session: print_session.clone(),
             })
             .detach();
 
@@ -98,26 +157,60 @@ impl SimpleComponent for ContentPageModel {
             .launch(NoteListColumInit {
                 ctx: init.ctx.clone(),
             })
-            .forward(note_editor_column.sender(), |msg| match msg {
-                // Is it ok to forward a subcomponent directly to another subcomponent?
-                NoteListColumnOutput::SelectNote { id } => NoteEditorColumnInput::OpenNote { id },
+            .forward(sender.input_sender(), |msg| match msg {
+                NoteListColumnOutput::SelectNote { id } => ContentPageInput::EditNote { id },
                 NoteListColumnOutput::CreateNote { folder_id } => {
-                    NoteEditorColumnInput::CreateNote { folder_id }
+                    ContentPageInput::EditNewNote { folder_id }
+                }
+                NoteListColumnOutput::DeletedNote { id } => ContentPageInput::NoteDeleted { id },
+                NoteListColumnOutput::OpenInNewWindow { id } => {
+                    ContentPageInput::OpenNoteWindow { id }
                 }
+                NoteListColumnOutput::Export { id, title } => ContentPageInput::ShowExportDialog {
+                    selection: ExportSelection::Note { id },
+                    name: title,
+                },
+                NoteListColumnOutput::ExportNotes { ids } => ContentPageInput::ShowExportDialog {
+                    name: format!("{} Notes", ids.len()),
+                    selection: ExportSelection::Notes { ids },
+                },
             });
 
         let sidebar_column = sidebar_column::SidebarColumnModel::builder()
-            .launch(SidebarColumnInit { ctx: init.ctx })
+            .launch(SidebarColumnInit {
+                ctx: init.ctx.clone(),
+            })
             .forward(sender.input_sender(), |msg| match msg {
                 SidebarColumnOutput::OpenFolder { folder_id } => {
                     ContentPageInput::OpenFolder { folder_id }
                 }
+                SidebarColumnOutput::OpenSearch { query } => ContentPageInput::OpenSearch { query },
+                SidebarColumnOutput::ExportFolder { folder_id, title } => {
+                    ContentPageInput::ShowExportDialog {
+                        selection: ExportSelection::Folder { id: folder_id },
+                        name: title,
+                    }
+                }
+                SidebarColumnOutput::SwitchProfile { profile } => {
+                    ContentPageInput::SwitchProfile { profile }
+                }
+                SidebarColumnOutput::OpenNote { id } => ContentPageInput::EditNote { id },
             });
 
+        let export_dialog = ExportDialogModel::builder()
+            .transient_for(root)
+            .launch(init.ctx.clone())
+            .detach();
+
         let model = ContentPageModel {
             note_editor_column,
             note_list_column,
             sidebar_column,
+            note_windows: HashMap::new(),
+            export_dialog,
+            ctx: init.ctx,
+            print_session,
+            navigate_forward: false,
         };
 
         let widgets = view_output!();
@@ -177,14 +270,126 @@ impl SimpleComponent for ContentPageModel {
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, input: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>) {
+        self.navigate_forward = false;
         match input {
             ContentPageInput::OpenFolder { folder_id } => {
+                self.navigate_forward = true;
                 self.note_list_column
                     .sender()
                     .send(NoteListColumnInput::RefreshNotes { folder_id })
                     .unwrap();
             }
+            ContentPageInput::OpenSearch { query } => {
+                self.navigate_forward = true;
+                self.note_list_column
+                    .emit(NoteListColumnInput::ShowSearch { query });
+            }
+            ContentPageInput::CreateNote => {
+                self.note_list_column.emit(NoteListColumnInput::CreateNote);
+            }
+            ContentPageInput::ShowCreateFolderDialog => {
+                self.sidebar_column
+                    .emit(SidebarColumnInput::ShowCreateFolderDialog);
+            }
+            ContentPageInput::ToggleSearch => {
+                self.note_list_column
+                    .emit(NoteListColumnInput::ToggleSearch);
+            }
+            ContentPageInput::SyncRemote => {
+                self.sidebar_column.emit(SidebarColumnInput::SyncRemote);
+            }
+            ContentPageInput::SyncFromScratch => {
+                self.sidebar_column
+                    .emit(SidebarColumnInput::SyncFromScratch);
+            }
+            ContentPageInput::ToggleSidebar => {
+                self.note_list_column
+                    .widgets()
+                    .change_siderbar_button
+                    .emit_clicked();
+            }
+            ContentPageInput::TogglePreview => {
+                self.note_editor_column
+                    .emit(NoteEditorColumnInput::TogglePreview);
+            }
+            ContentPageInput::Print => {
+                self.note_editor_column.emit(NoteEditorColumnInput::Print);
+            }
+            ContentPageInput::DeleteNote => {
+                self.note_list_column
+                    .emit(NoteListColumnInput::DeleteSelectedNote);
+            }
+            ContentPageInput::FocusNoteList => {
+                self.note_list_column.emit(NoteListColumnInput::FocusList);
+            }
+            ContentPageInput::FocusEditor => {
+                self.note_editor_column.widgets().body_view.grab_focus();
+            }
+            ContentPageInput::SelectNextNote => {
+                self.note_list_column
+                    .emit(NoteListColumnInput::SelectNextNote);
+            }
+            ContentPageInput::SelectPreviousNote => {
+                self.note_list_column
+                    .emit(NoteListColumnInput::SelectPreviousNote);
+            }
+            ContentPageInput::EditNote { id } => {
+                self.navigate_forward = true;
+                self.note_editor_column
+                    .emit(NoteEditorColumnInput::OpenNote { id });
+            }
+            ContentPageInput::EditNewNote { folder_id } => {
+                self.navigate_forward = true;
+                self.note_editor_column
+                    .emit(NoteEditorColumnInput::CreateNote { folder_id });
+            }
+            ContentPageInput::NoteDeleted { id } => {
+                if let Some(note_window) = self.note_windows.get(&id) {
+                    note_window.widget().close();
+                }
+                self.sidebar_column
+                    .emit(SidebarColumnInput::LocalChange { id: id.clone() });
+                self.note_editor_column
+                    .emit(NoteEditorColumnInput::CloseNote { id });
+            }
+            ContentPageInput::OpenNoteWindow { id } => {
+                if let Some(note_window) = self.note_windows.get(&id) {
+                    note_window.widget().present();
+                    return;
+                }
+                let note_window = NoteWindowModel::builder()
+                    .launch(NoteWindowInit {
+                        ctx: self.ctx.clone(),
+                        print_session: self.print_session.clone(),
+                        note_id: id.clone(),
+                    })
+                    .forward(sender.input_sender(), |msg| match msg {
+                        NoteWindowOutput::Closed { note_id } => {
+                            ContentPageInput::NoteWindowClosed { id: note_id }
+                        }
+                    });
+                self.note_windows.insert(id, note_window);
+            }
+            ContentPageInput::NoteWindowClosed { id } => {
+                self.note_windows.remove(&id);
+            }
+            ContentPageInput::ShowExportDialog { selection, name } => {
+                self.export_dialog
+                    .emit(ExportDialogInput::Show { selection, name });
+            }
+            ContentPageInput::SwitchProfile { profile } => {
+                sender
+                    .output(ContentPageOutput::SwitchProfile { profile })
+                    .unwrap();
+            }
+        }
+    }
+
+    fn shutdown(&mut self, _widgets: &mut Self::Widgets, _output: relm4::Sender<Self::Output>) {
+        // The note windows belong to the profile that is being closed.
+        for (_, note_window) in self.note_windows.drain() {
+            note_window.widget().close();
         }
     }
 
@@ -194,6 +399,78 @@ impl SimpleComponent for ContentPageModel {
            You can also implement your own view logic, which will be added to the view code that the view macro generates.
            Code inside pre_view() will run before the view update, while post_view() will run after.
         */
-        widgets.leaflet.navigate(adw::NavigationDirection::Forward);
+        if model.navigate_forward {
+            widgets.leaflet.navigate(adw::NavigationDirection::Forward);
+        }
+    }
+}
+
+const NOTE_DRAG_PREFIX: &str = "ruslin-note:";
+const FOLDER_DRAG_PREFIX: &str = "ruslin-folder:";
+
+// What a row carries while dragged, the sidebar folders take both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DraggedItem {
+    Note(String),
+    Folder(String),
+}
+
+impl DraggedItem {
+    // Drags are plain strings, so text dragged in from other apps has to be told apart.
+    fn to_drag_string(&self) -> String {
+        match self {
+            DraggedItem::Note(id) => format!("{NOTE_DRAG_PREFIX}{id}"),
+            DraggedItem::Folder(id) => format!("{FOLDER_DRAG_PREFIX}{id}"),
+        }
+    }
+
+    pub fn from_drag_string(value: &str) -> Option<DraggedItem> {
+        if let Some(id) = value.strip_prefix(NOTE_DRAG_PREFIX) {
+            Some(DraggedItem::Note(id.to_string()))
+        } else {
+            value
+                .strip_prefix(FOLDER_DRAG_PREFIX)
+                .map(|id| DraggedItem::Folder(id.to_string()))
+        }
+    }
+}
+
+fn add_row_drag_source(list_box: &gtk::ListBox, index: usize, item: DraggedItem) {
+    let Some(row) = list_box.row_at_index(index as i32) else {
+        return;
+    };
+    let drag_source = gtk::DragSource::new();
+    drag_source.set_actions(gdk::DragAction::MOVE);
+    drag_source.set_content(Some(&gdk::ContentProvider::for_value(
+        &item.to_drag_string().to_value(),
+    )));
+    drag_source.connect_drag_begin(|drag_source, _| {
+        let paintable = gtk::WidgetPaintable::new(Some(&drag_source.widget()));
+        drag_source.set_icon(Some(&paintable), 0, 0);
+    });
+    row.add_controller(&drag_source);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dragged_items_round_trip() {
+        for item in [
+            DraggedItem::Note("0123abcd".to_string()),
+            DraggedItem::Folder("4567ef01".to_string()),
+        ] {
+            assert_eq!(
+                DraggedItem::from_drag_string(&item.to_drag_string()),
+                Some(item)
+            );
+        }
+    }
+
+    #[test]
+    fn other_text_is_not_an_item() {
+        assert_eq!(DraggedItem::from_drag_string("0123abcd"), None);
+        assert_eq!(DraggedItem::from_drag_string("some dropped text"), None);
     }
 }
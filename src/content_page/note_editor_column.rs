@@ -1,19 +1,101 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
 use adw::prelude::*;
-use relm4::{gtk, prelude::*, ComponentParts, ComponentSender, SimpleComponent};
-use ruslin_data::{Note, UpdateSource};
+use gtk::{gdk, gio, glib};
+use relm4::{
+    actions::{ActionGroupName, RelmAction, RelmActionGroup},
+    factory::FactoryVecDeque,
+    gtk,
+    prelude::*,
+    ComponentParts, ComponentSender,
+};
+use ruslin_data::{AbbrNote, Note, UpdateSource};
 use sourceview5::{prelude::*, LanguageManager, StyleSchemeManager};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    app::NoteEvent,
+    backlinks::BacklinkIndex,
+    config::APP_ID,
+    icons,
+    markdown::{self, MarkdownBufferExt},
+    print::{self, PrintNote, PrintSession},
+    spell::{self, AddWordAction, ReplaceWordAction, SpellChecker, SpellingActionGroup},
+    AppContext,
+};
+
+static NEXT_EDITOR_ID: AtomicUsize = AtomicUsize::new(0);
+const SPELL_CHECK_DELAY: Duration = Duration::from_millis(300);
+
+struct BacklinkItemModel {
+    abbr_note: AbbrNote,
+}
+
+#[derive(Debug)]
+enum BacklinkItemInput {}
+
+#[derive(Debug)]
+enum BacklinkItemOutput {}
 
-use crate::AppContext;
+#[relm4::factory]
+impl FactoryComponent for BacklinkItemModel {
+    type Init = AbbrNote;
+    type Input = BacklinkItemInput;
+    type Output = BacklinkItemOutput;
+    type CommandOutput = ();
+    type Widgets = BacklinkItemWidgets;
+    type ParentInput = NoteEditorColumnInput;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        root = gtk::Box {
+            gtk::Label {
+                #[watch]
+                set_label: &self.abbr_note.title,
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self { abbr_note: init }
+    }
+}
 
 #[tracker::track]
 pub struct NoteEditorColumnModel {
     #[tracker::do_not_track]
     pub ctx: AppContext,
+    #[tracker::do_not_track]
+    editor_id: usize,
     pub current_note: Option<Note>,
+    pub show_preview: bool,
+    title: String,
+    #[tracker::do_not_track]
+    folder_title: Option<String>,
+    #[tracker::do_not_track]
+    auto_title: bool,
+    #[tracker::do_not_track]
+    backlinks: FactoryVecDeque<BacklinkItemModel>,
+    // Kept alive for its change notifications.
+    #[tracker::do_not_track]
+    settings: gio::Settings,
+    #[tracker::do_not_track]
+    print_session: PrintSession,
+    // Set when another editor or a sync changed the note shown, to keep the cursor in place.
+    #[tracker::do_not_track]
+    keep_cursor: bool,
+    #[tracker::do_not_track]
+    cursor_offset: Cell<Option<i32>>,
 }
 
 pub struct NoteEditorColumnInit {
     pub ctx: AppContext,
+    pub print_session: PrintSession,
 }
 
 #[derive(Debug)]
@@ -22,17 +104,49 @@ pub enum NoteEditorColumnInput {
     CreateNote { folder_id: Option<String> },
     UpdateTitle(String),
     UpdateBody(String),
+    TogglePreview,
+    Print,
+    CloseNote { id: String },
+    OpenLink(String),
+    OpenBacklink(usize),
 }
 
 #[derive(Debug)]
 pub enum NoteEditorColumnOutput {}
 
+#[derive(Debug)]
+pub enum NoteEditorColumnCommand {
+    LoadedBacklinks { id: String, notes: Vec<AbbrNote> },
+    BacklinksUnchanged,
+    NoteEvent(NoteEvent),
+}
+
+relm4::new_action_group!(EditorActionGroup, "editor");
+relm4::new_stateless_action!(BoldAction, EditorActionGroup, "bold");
+relm4::new_stateless_action!(ItalicAction, EditorActionGroup, "italic");
+relm4::new_stateless_action!(CodeAction, EditorActionGroup, "code");
+relm4::new_stateless_action!(LinkAction, EditorActionGroup, "link");
+relm4::new_stateless_action!(ToggleCheckboxAction, EditorActionGroup, "toggle-checkbox");
+relm4::new_stateless_action!(CycleHeadingAction, EditorActionGroup, "cycle-heading");
+relm4::new_stateless_action!(FormatTableAction, EditorActionGroup, "format-table");
+
+const EDITOR_SHORTCUTS: &[(&str, &str)] = &[
+    ("<Control>b", "editor.bold"),
+    ("<Control>i", "editor.italic"),
+    ("<Control>e", "editor.code"),
+    ("<Control>k", "editor.link"),
+    ("<Control>Return", "editor.toggle-checkbox"),
+    ("<Control>h", "editor.cycle-heading"),
+    ("<Control><Alt>t", "editor.format-table"),
+];
+
 #[relm4::component(pub)]
-impl SimpleComponent for NoteEditorColumnModel {
+impl Component for NoteEditorColumnModel {
     type Init = NoteEditorColumnInit;
     type Input = NoteEditorColumnInput;
     type Output = NoteEditorColumnOutput;
     type Widgets = NoteEditorColumnWidgets;
+    type CommandOutput = NoteEditorColumnCommand;
 
     view! {
         gtk::Box {
@@ -51,7 +165,28 @@ impl SimpleComponent for NoteEditorColumnModel {
 
                 #[wrap(Some)]
                 set_title_widget = &adw::WindowTitle {
-                    set_title: "Content",
+                    #[watch]
+                    set_title: model.header_title(),
+                    #[watch]
+                    set_subtitle: model.folder_title.as_deref().unwrap_or_default(),
+                },
+
+                #[name = "formatting_toolbar_button"]
+                pack_end = &gtk::ToggleButton {
+                    set_icon_name: icons::format_text_bold_symbolic(),
+                    set_tooltip_text: Some("Formatting Toolbar"),
+                },
+
+                pack_end = &gtk::ToggleButton {
+                    set_icon_name: icons::view_reveal_symbolic(),
+                    set_tooltip_text: Some("Toggle Preview"),
+                    #[watch]
+                    set_sensitive: model.current_note.is_some(),
+                    #[watch]
+                    set_active: model.show_preview,
+                    connect_clicked[sender] => move |_| {
+                        sender.input(NoteEditorColumnInput::TogglePreview);
+                    }
                 },
             },
 
@@ -61,30 +196,95 @@ impl SimpleComponent for NoteEditorColumnModel {
                 #[watch]
                 set_visible: model.current_note.is_some(),
 
-                gtk::TextView {
-                    set_height_request: 30,
+                #[name = "title_entry"]
+                gtk::Entry {
                     set_margin_all: 15,
                     set_hexpand: true,
+                    set_has_frame: false,
+                    set_placeholder_text: Some("Untitled"),
                     set_input_hints: gtk::InputHints::NO_SPELLCHECK,
-                    #[wrap(Some)]
-                    set_buffer: title_buf = &gtk::TextBuffer {
-                        #[track = "model.changed(NoteEditorColumnModel::current_note())"]
-                        set_text: model.current_note.as_ref().map(|n| n.get_title()).unwrap_or_default(),
-                        connect_end_user_action[sender] => move |buf| {
-                            let (start, end) = buf.bounds();
-                            sender.input(NoteEditorColumnInput::UpdateTitle(buf.text(&start, &end, true).to_string()));
-                        }
-                    },
                     add_css_class: "title-1",
+                    #[track = "model.changed(NoteEditorColumnModel::title())"]
+                    #[block_signal(title_changed_handler)]
+                    set_text: &model.title,
+                    connect_changed[sender] => move |entry| {
+                        sender.input(NoteEditorColumnInput::UpdateTitle(entry.text().to_string()));
+                    } @title_changed_handler,
+                    connect_activate[body_view] => move |_| {
+                        body_view.grab_focus();
+                    },
                 },
 
                 gtk::Separator {
 
                 },
 
+                #[name = "formatting_toolbar"]
+                gtk::Revealer {
+                    #[watch]
+                    set_sensitive: !model.show_preview,
+
+                    gtk::Box {
+                        set_spacing: 2,
+                        set_margin_start: 10,
+                        set_margin_end: 10,
+                        set_margin_top: 4,
+                        set_margin_bottom: 4,
+
+                        gtk::Button {
+                            set_icon_name: icons::format_text_bold_symbolic(),
+                            set_tooltip_text: Some("Bold"),
+                            set_action_name: Some("editor.bold"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Button {
+                            set_icon_name: icons::format_text_italic_symbolic(),
+                            set_tooltip_text: Some("Italic"),
+                            set_action_name: Some("editor.italic"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Button {
+                            set_label: "</>",
+                            set_tooltip_text: Some("Code"),
+                            set_action_name: Some("editor.code"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Button {
+                            set_icon_name: icons::insert_link_symbolic(),
+                            set_tooltip_text: Some("Link"),
+                            set_action_name: Some("editor.link"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Separator {
+                            set_orientation: gtk::Orientation::Vertical,
+                        },
+                        gtk::Button {
+                            set_label: "H",
+                            set_tooltip_text: Some("Cycle Heading Level"),
+                            set_action_name: Some("editor.cycle-heading"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Button {
+                            set_icon_name: icons::checkbox_checked_symbolic(),
+                            set_tooltip_text: Some("Toggle Checkbox"),
+                            set_action_name: Some("editor.toggle-checkbox"),
+                            add_css_class: "flat",
+                        },
+                        gtk::Button {
+                            set_icon_name: icons::view_grid_symbolic(),
+                            set_tooltip_text: Some("Format Table"),
+                            set_action_name: Some("editor.format-table"),
+                            add_css_class: "flat",
+                        },
+                    },
+                },
+
                 gtk::ScrolledWindow {
                     set_vexpand: true,
+                    #[watch]
+                    set_visible: !model.show_preview,
 
+                    #[name = "body_view"]
                     sourceview5::View {
                         set_vexpand: true,
                         set_editable: true,
@@ -113,6 +313,49 @@ impl SimpleComponent for NoteEditorColumnModel {
                         }
                     },
                 },
+
+                gtk::ScrolledWindow {
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: model.show_preview,
+
+                    #[name = "preview_label"]
+                    gtk::Label {
+                        set_margin_top: 10,
+                        set_margin_bottom: 10,
+                        set_margin_start: 15,
+                        set_margin_end: 15,
+                        set_xalign: 0.0,
+                        set_yalign: 0.0,
+                        set_wrap: true,
+                        set_wrap_mode: gtk::pango::WrapMode::WordChar,
+                        set_selectable: true,
+                        set_use_markup: true,
+                        connect_activate_link[sender] => move |_, uri| {
+                            sender.input(NoteEditorColumnInput::OpenLink(uri.to_string()));
+                            gtk::Inhibit(true)
+                        },
+                        #[track = "model.show_preview && (model.changed(NoteEditorColumnModel::show_preview()) || model.changed(NoteEditorColumnModel::current_note()))"]
+                        set_markup: &model.current_note.as_ref().map(|n| markdown::to_pango_markup(&n.body)).unwrap_or_default(),
+                    },
+                },
+
+                gtk::Expander {
+                    set_margin_all: 10,
+                    #[watch]
+                    set_visible: !model.backlinks.is_empty(),
+                    #[watch]
+                    set_label: Some(&format!("Backlinks ({})", model.backlinks.len())),
+
+                    #[local_ref]
+                    backlinks_list_box -> gtk::ListBox {
+                        set_selection_mode: gtk::SelectionMode::None,
+                        add_css_class: "boxed-list",
+                        connect_row_activated[sender] => move |_, row| {
+                            sender.input(NoteEditorColumnInput::OpenBacklink(row.index() as usize));
+                        }
+                    }
+                },
             }
         }
     }
@@ -122,60 +365,544 @@ impl SimpleComponent for NoteEditorColumnModel {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
+        let backlinks = FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
+        let mut note_events = init.ctx.note_events.subscribe();
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(async move {
+                    loop {
+                        let event = match note_events.recv().await {
+                            Ok(event) => event,
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        };
+                        if out.send(NoteEditorColumnCommand::NoteEvent(event)).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .drop_on_shutdown()
+        });
+
         let model = NoteEditorColumnModel {
             ctx: init.ctx,
+            editor_id: NEXT_EDITOR_ID.fetch_add(1, Ordering::Relaxed),
             current_note: None,
+            show_preview: false,
+            title: String::new(),
+            folder_title: None,
+            auto_title: false,
+            backlinks,
+            settings: gio::Settings::new(APP_ID),
+            print_session: init.print_session,
+            keep_cursor: false,
+            cursor_offset: Cell::new(None),
             tracker: 0,
         };
 
+        let backlinks_list_box = model.backlinks.widget();
         let widgets = view_output!();
 
+        let settings = &model.settings;
+        settings
+            .bind(
+                "show-formatting-toolbar",
+                &widgets.formatting_toolbar,
+                "reveal-child",
+            )
+            .build();
+        settings
+            .bind(
+                "show-formatting-toolbar",
+                &widgets.formatting_toolbar_button,
+                "active",
+            )
+            .build();
+
+        let body_buf = widgets.body_buf.clone();
+        let actions = RelmActionGroup::<EditorActionGroup>::new();
+        let bold_action: RelmAction<BoldAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.toggle_inline_format("**")),
+        );
+        let italic_action: RelmAction<ItalicAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.toggle_inline_format("*")),
+        );
+        let code_action: RelmAction<CodeAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.toggle_inline_format("`")),
+        );
+        let link_action: RelmAction<LinkAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.insert_link()),
+        );
+        let toggle_checkbox_action: RelmAction<ToggleCheckboxAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.toggle_checkbox()),
+        );
+        let cycle_heading_action: RelmAction<CycleHeadingAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.cycle_heading()),
+        );
+        let format_table_action: RelmAction<FormatTableAction> = RelmAction::new_stateless(
+            glib::clone!(@weak body_buf => move |_| body_buf.format_table()),
+        );
+        actions.add_action(&bold_action);
+        actions.add_action(&italic_action);
+        actions.add_action(&code_action);
+        actions.add_action(&link_action);
+        actions.add_action(&toggle_checkbox_action);
+        actions.add_action(&cycle_heading_action);
+        actions.add_action(&format_table_action);
+        root.insert_action_group(EditorActionGroup::NAME, Some(&actions.into_action_group()));
+
+        let shortcut_controller = gtk::ShortcutController::new();
+        shortcut_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        for (accelerator, action_name) in EDITOR_SHORTCUTS {
+            shortcut_controller.add_shortcut(&gtk::Shortcut::new(
+                gtk::ShortcutTrigger::parse_string(accelerator).as_ref(),
+                Some(&gtk::NamedAction::new(action_name)),
+            ));
+        }
+        widgets.body_view.add_controller(&shortcut_controller);
+
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(
+            glib::clone!(@weak body_buf => @default-return gtk::Inhibit(false), move |_, key, _, modifier| {
+                let is_enter = key == gdk::Key::Return || key == gdk::Key::KP_Enter;
+                let modified = modifier.intersects(
+                    gdk::ModifierType::CONTROL_MASK
+                        | gdk::ModifierType::SHIFT_MASK
+                        | gdk::ModifierType::ALT_MASK,
+                );
+                gtk::Inhibit(is_enter && !modified && body_buf.continue_list())
+            }),
+        );
+        widgets.body_view.add_controller(&key_controller);
+
+        let body_view = widgets.body_view.clone();
+        let link_click = gtk::GestureClick::new();
+        link_click.set_button(gdk::BUTTON_PRIMARY);
+        link_click.connect_pressed(
+            glib::clone!(@weak body_buf, @weak body_view, @strong sender => move |gesture, _, x, y| {
+                if !gesture.current_event_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                    return;
+                }
+                let (x, y) = body_view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+                let Some(iter) = body_view.iter_at_location(x, y) else {
+                    return;
+                };
+                let mut line_start = iter.clone();
+                line_start.set_line_offset(0);
+                let mut line_end = iter.clone();
+                if !line_end.ends_line() {
+                    line_end.forward_to_line_end();
+                }
+                let line = body_buf.text(&line_start, &line_end, false);
+                let offset = line
+                    .char_indices()
+                    .nth(iter.line_offset() as usize)
+                    .map(|(offset, _)| offset)
+                    .unwrap_or(line.len());
+                if let Some(url) = markdown::link_at(&line, offset) {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    sender.input(NoteEditorColumnInput::OpenLink(url));
+                }
+            }),
+        );
+        widgets.body_view.add_controller(&link_click);
+
+        let title_entry = widgets.title_entry.clone();
+        let spell_checker = Rc::new(RefCell::new(SpellChecker::new()));
+        check_spelling(&spell_checker.borrow(), &title_entry, &body_view);
+        for key in ["spell-check", "spell-language"] {
+            settings.connect_changed(
+                Some(key),
+                glib::clone!(@strong spell_checker, @weak title_entry, @weak body_view => move |_, _| {
+                    spell_checker.borrow_mut().reload();
+                    check_spelling(&spell_checker.borrow(), &title_entry, &body_view);
+                }),
+            );
+        }
+        title_entry.connect_changed(glib::clone!(@strong spell_checker => move |entry| {
+            spell::check_entry(&spell_checker.borrow(), entry);
+        }));
+        // The title's suggestions are looked up when its context menu opens, after this gesture.
+        let title_spelling_click = gtk::GestureClick::new();
+        title_spelling_click.set_button(gdk::BUTTON_SECONDARY);
+        title_spelling_click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        title_spelling_click.connect_pressed(
+            glib::clone!(@weak title_entry, @strong spell_checker => move |_, _, _, _| {
+                let menu = spell::entry_suggestions_menu(&spell_checker.borrow(), &title_entry);
+                title_entry.set_extra_menu(Some(&menu));
+            }),
+        );
+        title_entry.add_controller(&title_spelling_click);
+        // The whole body is checked again once typing pauses.
+        let spell_check_pending = Rc::new(Cell::new(false));
+        body_buf.connect_changed(
+            glib::clone!(@strong spell_checker, @strong spell_check_pending => move |buffer| {
+                if spell_check_pending.replace(true) {
+                    return;
+                }
+                glib::timeout_add_local_once(
+                    SPELL_CHECK_DELAY,
+                    glib::clone!(@weak buffer, @strong spell_checker, @strong spell_check_pending => move || {
+                        spell_check_pending.set(false);
+                        spell::check_buffer(&spell_checker.borrow(), buffer.upcast_ref());
+                    }),
+                );
+            }),
+        );
+
+        // Right-clicking a misspelled word selects it and offers its suggestions in the context
+        // menu, which opens after this gesture.
+        let spelling_click = gtk::GestureClick::new();
+        spelling_click.set_button(gdk::BUTTON_SECONDARY);
+        spelling_click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        spelling_click.connect_pressed(
+            glib::clone!(@weak body_buf, @weak body_view, @strong spell_checker => move |_, _, x, y| {
+                let (x, y) = body_view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+                let misspelled = body_view
+                    .iter_at_location(x, y)
+                    .and_then(|iter| spell::misspelled_word_at(body_buf.upcast_ref(), &iter));
+                match misspelled {
+                    Some((word, start, end)) => {
+                        body_buf.select_range(&start, &end);
+                        let menu = spell::suggestions_menu(&spell_checker.borrow(), &word);
+                        body_view.set_extra_menu(Some(&menu));
+                    }
+                    None => body_view.set_extra_menu(gio::MenuModel::NONE),
+                }
+            }),
+        );
+        widgets.body_view.add_controller(&spelling_click);
+
+        let body_spelling = RelmActionGroup::<SpellingActionGroup>::new();
+        let replace_action: RelmAction<ReplaceWordAction> = RelmAction::new_with_target_value(
+            glib::clone!(@weak body_buf => move |_, (word, replacement): (String, String)| {
+                let Some((mut start, mut end)) = body_buf.selection_bounds() else {
+                    return;
+                };
+                if body_buf.text(&start, &end, true) != word {
+                    return;
+                }
+                body_buf.begin_user_action();
+                body_buf.delete(&mut start, &mut end);
+                body_buf.insert(&mut start, &replacement);
+                body_buf.end_user_action();
+            }),
+        );
+        let add_word_action: RelmAction<AddWordAction> = RelmAction::new_with_target_value(
+            glib::clone!(@strong spell_checker, @weak title_entry, @weak body_view => move |_, word: String| {
+                spell_checker.borrow().add_word(&word);
+                check_spelling(&spell_checker.borrow(), &title_entry, &body_view);
+            }),
+        );
+        body_spelling.add_action(&replace_action);
+        body_spelling.add_action(&add_word_action);
+        body_view.insert_action_group(
+            SpellingActionGroup::NAME,
+            Some(&body_spelling.into_action_group()),
+        );
+
+        let title_spelling = RelmActionGroup::<SpellingActionGroup>::new();
+        let replace_action: RelmAction<ReplaceWordAction> = RelmAction::new_with_target_value(
+            glib::clone!(@strong spell_checker, @weak title_entry => move |_, (word, replacement): (String, String)| {
+                let title = title_entry.text();
+                let misspelled = spell_checker.borrow().misspelled_in_text(&title);
+                if let Some(range) = misspelled.into_iter().find(|range| title[range.clone()] == word) {
+                    let mut title = title.to_string();
+                    title.replace_range(range, &replacement);
+                    title_entry.set_text(&title);
+                }
+            }),
+        );
+        let add_word_action: RelmAction<AddWordAction> = RelmAction::new_with_target_value(
+            glib::clone!(@strong spell_checker, @weak title_entry, @weak body_view => move |_, word: String| {
+                spell_checker.borrow().add_word(&word);
+                check_spelling(&spell_checker.borrow(), &title_entry, &body_view);
+            }),
+        );
+        title_spelling.add_action(&replace_action);
+        title_spelling.add_action(&add_word_action);
+        title_entry.insert_action_group(
+            SpellingActionGroup::NAME,
+            Some(&title_spelling.into_action_group()),
+        );
+
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, input: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        self.reset();
+        self.keep_cursor = false;
         match input {
             NoteEditorColumnInput::OpenNote { id } => {
-                self.reset();
-                let note = self.ctx.data.db.load_note(&id).unwrap();
+                // The id can come from a link, the command line or D-Bus.
+                let note = match self.ctx.data.db.load_note(&id) {
+                    Ok(note) => note,
+                    Err(e) => {
+                        log::warn!("note {id} not found: {:?}", e);
+                        return;
+                    }
+                };
+                self.folder_title = self.load_folder_title(note.parent_id.as_deref());
+                self.auto_title = note.get_title().is_empty();
+                self.set_title(note.get_title().to_string());
                 self.set_current_note(Some(note));
+                self.backlinks.guard().clear();
+                self.load_backlinks(&sender, id, None);
             }
             NoteEditorColumnInput::CreateNote { folder_id } => {
-                self.reset();
+                self.folder_title = self.load_folder_title(folder_id.as_deref());
+                self.auto_title = true;
+                self.set_title(String::new());
                 self.set_current_note(Some(Note::new(folder_id, String::new(), String::new())));
+                self.backlinks.guard().clear();
             }
             NoteEditorColumnInput::UpdateTitle(title) => {
-                if self.changed(NoteEditorColumnModel::current_note()) {
-                    self.reset();
-                    return;
-                }
                 if let Some(note) = self.current_note.as_mut() {
                     if note.get_title() != title {
+                        // Once the user names the note it no longer follows the body.
+                        self.auto_title = false;
                         note.set_title(&title);
                         self.ctx
                             .data
                             .db
                             .replace_note(note, UpdateSource::LocalEdit)
                             .unwrap();
+                        self.title = title;
+                        self.notify_note_updated();
                     }
                 }
             }
             NoteEditorColumnInput::UpdateBody(body) => {
-                if self.changed(NoteEditorColumnModel::current_note()) {
-                    self.reset();
-                    return;
-                }
                 if let Some(note) = self.current_note.as_mut() {
                     if note.body != body {
+                        let generated_title = self
+                            .auto_title
+                            .then(|| markdown::title_from_body(&body))
+                            .filter(|title| title != note.get_title());
+                        if let Some(title) = &generated_title {
+                            note.set_title(title);
+                        }
                         note.body = body;
                         self.ctx
                             .data
                             .db
                             .replace_note(note, UpdateSource::LocalEdit)
                             .unwrap();
+                        if let Some(title) = generated_title {
+                            self.set_title(title);
+                        }
+                        self.notify_note_updated();
+                    }
+                }
+            }
+            NoteEditorColumnInput::TogglePreview => {
+                if self.current_note.is_some() {
+                    self.set_show_preview(!self.show_preview);
+                }
+            }
+            NoteEditorColumnInput::Print => {
+                let Some(note) = &self.current_note else {
+                    return;
+                };
+                let mut metadata = Vec::new();
+                if let Some(folder_title) = &self.folder_title {
+                    metadata.push(("Folder".to_string(), folder_title.clone()));
+                }
+                metadata.push((
+                    "Printed".to_string(),
+                    chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+                ));
+                let window = root
+                    .root()
+                    .and_then(|root| root.downcast::<gtk::Window>().ok());
+                print::print(
+                    vec![PrintNote {
+                        title: self.header_title().to_string(),
+                        body: note.body.clone(),
+                        metadata,
+                    }],
+                    &self.print_session,
+                    window.as_ref(),
+                );
+            }
+            NoteEditorColumnInput::CloseNote { id } => {
+                if self.current_note.as_ref().map(|n| n.id == id) == Some(true) {
+                    self.folder_title = None;
+                    self.set_title(String::new());
+                    self.set_current_note(None);
+                    self.backlinks.guard().clear();
+                }
+            }
+            NoteEditorColumnInput::OpenLink(url) => match markdown::note_id_from_url(&url) {
+                // Resources share the `:/id` form, so make sure it really is a note.
+                Some(id) => match self.ctx.data.db.load_note(id) {
+                    Ok(_) => sender.input(NoteEditorColumnInput::OpenNote { id: id.to_string() }),
+                    Err(e) => log::warn!("cannot open linked note {id}: {:?}", e),
+                },
+                None => gtk::show_uri(None::<&gtk::Window>, &url, gdk::CURRENT_TIME),
+            },
+            NoteEditorColumnInput::OpenBacklink(index) => {
+                if let Some(backlink) = self.backlinks.get(index) {
+                    sender.input(NoteEditorColumnInput::OpenNote {
+                        id: backlink.abbr_note.id.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        self.reset();
+        self.keep_cursor = false;
+        match message {
+            NoteEditorColumnCommand::LoadedBacklinks { id, notes } => {
+                if self.current_note.as_ref().map(|n| n.id == id) != Some(true) {
+                    return;
+                }
+                let mut backlinks_guard = self.backlinks.guard();
+                backlinks_guard.clear();
+                for note in notes {
+                    backlinks_guard.push_back(note);
+                }
+            }
+            NoteEditorColumnCommand::BacklinksUnchanged => {}
+            NoteEditorColumnCommand::NoteEvent(event) => {
+                // Another editor or an external capture saved the note shown here, so pick up its changes.
+                let id = match event {
+                    NoteEvent::Updated { editor_id, .. } if editor_id == self.editor_id => return,
+                    NoteEvent::Updated { id, .. } | NoteEvent::Changed { id } => id,
+                    NoteEvent::FavouritesChanged => return,
+                };
+                self.ctx.backlinks.note_changed(&id);
+                let Some(current_id) = self.current_note.as_ref().map(|n| n.id.clone()) else {
+                    return;
+                };
+                if current_id != id {
+                    // Only a note that links here, or did before the change, matters.
+                    let was_backlink = (0..self.backlinks.len())
+                        .filter_map(|index| self.backlinks.get(index))
+                        .any(|backlink| backlink.abbr_note.id == id);
+                    let changed_id = (!was_backlink).then_some(id);
+                    self.load_backlinks(&sender, current_id, changed_id);
+                    return;
+                }
+                match self.ctx.data.db.load_note(&id) {
+                    Ok(note) => {
+                        self.auto_title = note.get_title().is_empty();
+                        self.set_title(note.get_title().to_string());
+                        let same_body = self
+                            .current_note
+                            .as_ref()
+                            .map_or(false, |current| current.body == note.body);
+                        if same_body {
+                            // Leaves the buffer, its cursor and its undo history alone.
+                            self.current_note = Some(note);
+                        } else {
+                            self.keep_cursor = true;
+                            self.set_current_note(Some(note));
+                        }
+                    }
+                    Err(e) => log::error!("reload note {id} failed: {:?}", e),
+                }
+            }
+        }
+    }
+
+    fn pre_view() {
+        if model.keep_cursor {
+            model.cursor_offset.set(Some(body_buf.cursor_position()));
+        }
+    }
+
+    fn post_view() {
+        if let Some(offset) = model.cursor_offset.take() {
+            body_buf.place_cursor(&body_buf.iter_at_offset(offset));
+        }
+    }
+}
+
+impl NoteEditorColumnModel {
+    // With `changed_id`, only when that note links here now.
+    fn load_backlinks(
+        &self,
+        sender: &ComponentSender<Self>,
+        id: String,
+        changed_id: Option<String>,
+    ) {
+        let data = self.ctx.data.clone();
+        let backlinks = self.ctx.backlinks.clone();
+        sender.spawn_oneshot_command(move || {
+            if let Some(changed_id) = changed_id {
+                match BacklinkIndex::links_to(&data, &changed_id, &id) {
+                    Ok(true) => {}
+                    Ok(false) => return NoteEditorColumnCommand::BacklinksUnchanged,
+                    // Deleted, and it wasn't a backlink either.
+                    Err(e) => {
+                        log::debug!("note {changed_id} not read for backlinks: {:?}", e);
+                        return NoteEditorColumnCommand::BacklinksUnchanged;
                     }
                 }
             }
+            let notes = match backlinks.backlinks(&data, &id) {
+                Ok(notes) => notes,
+                Err(e) => {
+                    log::error!("load backlinks failed: {:?}", e);
+                    Vec::new()
+                }
+            };
+            NoteEditorColumnCommand::LoadedBacklinks { id, notes }
+        });
+    }
+
+    fn notify_note_updated(&self) {
+        if let Some(note) = &self.current_note {
+            self.ctx.backlinks.note_changed(&note.id);
+            // Sending only fails when no other editor is listening.
+            let _ = self.ctx.note_events.send(NoteEvent::Updated {
+                id: note.id.clone(),
+                editor_id: self.editor_id,
+            });
+        }
+    }
+
+    fn header_title(&self) -> &str {
+        match &self.current_note {
+            Some(_) if self.title.is_empty() => "Untitled",
+            Some(_) => self.title.as_str(),
+            None => "Ruslin",
+        }
+    }
+
+    fn load_folder_title(&self, folder_id: Option<&str>) -> Option<String> {
+        let folder_id = folder_id?;
+        match self.ctx.data.db.load_folders() {
+            Ok(folders) => folders
+                .into_iter()
+                .find(|folder| folder.id == folder_id)
+                .map(|folder| folder.title),
+            Err(e) => {
+                log::error!("load folders failed: {:?}", e);
+                None
+            }
         }
     }
 }
+
+// Also tells input methods whether to check spelling themselves.
+fn check_spelling(checker: &SpellChecker, title_entry: &gtk::Entry, body_view: &sourceview5::View) {
+    let hints = if checker.is_enabled() {
+        gtk::InputHints::SPELLCHECK
+    } else {
+        gtk::InputHints::NO_SPELLCHECK
+    };
+    title_entry.set_input_hints(hints);
+    body_view.set_input_hints(hints);
+    spell::check_entry(checker, title_entry);
+    spell::check_buffer(checker, &body_view.buffer());
+}
@@ -1,13 +1,37 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use adw::prelude::*;
+use gtk::{gdk, gio, glib};
 use relm4::{
-    factory::FactoryVecDeque, gtk, prelude::*, ComponentParts, ComponentSender, SimpleComponent,
+    actions::{ActionGroupName, ActionName, RelmAction, RelmActionGroup},
+    factory::FactoryVecDeque,
+    gtk,
+    prelude::*,
+    Component, ComponentParts, ComponentSender,
+};
+use ruslin_data::{AbbrNote, DatabaseError, Note, RuslinData, UpdateSource};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    app::NoteEvent,
+    bulk_change::{self, BulkChangeError},
+    capture, favourites, folder_order, icons, markdown,
+    search::{self, Query},
+    AppContext,
 };
-use ruslin_data::AbbrNote;
 
-use crate::{icons, AppContext};
+// Edits come in per keystroke, a shown saved search is rerun once they pause this long.
+const SEARCH_REFRESH_DELAY: Duration = Duration::from_millis(500);
+// Searching reads every note, so it waits until typing pauses.
+const SEARCH_DELAY: Duration = Duration::from_millis(250);
 
 struct NoteItemModel {
     abbr_note: AbbrNote,
+    pinned: bool,
 }
 
 #[derive(Debug)]
@@ -18,7 +42,8 @@ enum NoteItemOutput {}
 
 #[relm4::factory]
 impl FactoryComponent for NoteItemModel {
-    type Init = AbbrNote;
+    // The note and whether it is pinned.
+    type Init = (AbbrNote, bool);
     type Input = NoteItemInput;
     type Output = NoteItemOutput;
     type CommandOutput = ();
@@ -32,6 +57,13 @@ impl FactoryComponent for NoteItemModel {
             set_spacing: 5,
             gtk::Box {
                 set_margin_top: 5,
+                set_spacing: 6,
+
+                gtk::Image {
+                    set_icon_name: Some(icons::view_pin_symbolic()),
+                    #[watch]
+                    set_visible: self.pinned,
+                },
 
                 gtk::Label {
                     #[watch]
@@ -52,7 +84,8 @@ impl FactoryComponent for NoteItemModel {
     }
 
     fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
-        Self { abbr_note: init }
+        let (abbr_note, pinned) = init;
+        Self { abbr_note, pinned }
     }
 }
 
@@ -60,6 +93,28 @@ pub struct NoteListColumnModel {
     ctx: AppContext,
     notes: FactoryVecDeque<NoteItemModel>,
     folder_id: Option<String>,
+    // A saved search shown instead of a folder.
+    query: Option<Query>,
+    search_refresh_pending: bool,
+    is_searching: bool,
+    search_text: String,
+    // Bumped on every keystroke, only the search of the last one runs.
+    search_generation: u32,
+    // Bumped on every reload, notes loaded for an older one are dropped.
+    load_generation: u32,
+    context_note_index: Option<usize>,
+    // Shows and toggles whether the context menu's note is pinned.
+    pin_action: gio::SimpleAction,
+    // Taps toggle rows instead of opening them, for touch screens without Ctrl and Shift.
+    selection_mode: bool,
+    selected_count: usize,
+    // The folders the selected notes can be moved to.
+    move_menu: gio::Menu,
+    // What the toast's Undo button takes back.
+    undo: Option<UndoChange>,
+    toast_overlay: adw::ToastOverlay,
+    // Told once per query, not on every keystroke.
+    unsupported_search: Option<String>,
 }
 
 pub struct NoteListColumInit {
@@ -69,22 +124,103 @@ pub struct NoteListColumInit {
 #[derive(Debug)]
 pub enum NoteListColumnInput {
     RefreshNotes { folder_id: Option<String> },
+    ShowSearch { query: String },
+    NoteUpdated,
+    RefreshSearch,
     SelectNote(usize),
     CreateNote,
+    ToggleSearch,
+    SetSearchMode(bool),
+    Search(String),
+    DeleteSelectedNote,
+    ConfirmDeleteSelected,
+    // The delete toast went away, the notes it hid are deleted unless undone.
+    CommitDelete { ids: Vec<String> },
+    SelectionChanged,
+    SetSelectionMode(bool),
+    MoveSelected { folder_id: String },
+    ExportSelected,
+    Undo,
+    FocusList,
+    SelectNextNote,
+    SelectPreviousNote,
+    SetContextNote(usize),
+    CopyMarkdownLink,
+    OpenInNewWindow,
+    Export,
+    SetContextNotePinned(bool),
+    NoteChanged,
+    DropFiles(Vec<PathBuf>),
 }
 
 #[derive(Debug)]
 pub enum NoteListColumnOutput {
     SelectNote { id: String },
     CreateNote { folder_id: Option<String> },
+    DeletedNote { id: String },
+    OpenInNewWindow { id: String },
+    Export { id: String, title: String },
+    ExportNotes { ids: Vec<String> },
+}
+
+#[derive(Debug)]
+pub enum NoteListColumnCommand {
+    NoteEvent(NoteEvent),
+    RunSearch {
+        generation: u32,
+    },
+    NotesLoaded {
+        generation: u32,
+        notes: Result<Vec<AbbrNote>, DatabaseError>,
+    },
+}
+
+relm4::new_action_group!(NoteListActionGroup, "note-list");
+relm4::new_stateless_action!(
+    CopyMarkdownLinkAction,
+    NoteListActionGroup,
+    "copy-markdown-link"
+);
+relm4::new_stateless_action!(
+    OpenInNewWindowAction,
+    NoteListActionGroup,
+    "open-in-new-window"
+);
+relm4::new_stateless_action!(ExportNoteAction, NoteListActionGroup, "export");
+relm4::new_stateful_action!(PinNoteAction, NoteListActionGroup, "pin", (), bool);
+relm4::new_stateless_action!(UndoAction, NoteListActionGroup, "undo");
+
+// The id of the folder to move the selected notes to.
+struct MoveSelectedAction;
+
+impl ActionName for MoveSelectedAction {
+    type Group = NoteListActionGroup;
+    type Target = String;
+    type State = ();
+
+    const NAME: &'static str = "move-selected";
 }
 
 #[relm4::component(pub)]
-impl SimpleComponent for NoteListColumnModel {
+impl Component for NoteListColumnModel {
     type Init = NoteListColumInit;
     type Input = NoteListColumnInput;
     type Output = NoteListColumnOutput;
     type Widgets = ComponentWidgets;
+    type CommandOutput = NoteListColumnCommand;
+
+    menu! {
+        note_context_menu: {
+            section! {
+                "Open in New Window" => OpenInNewWindowAction,
+                "Pinned" => PinNoteAction,
+            },
+            section! {
+                "Copy Markdown Link" => CopyMarkdownLinkAction,
+                "Export…" => ExportNoteAction,
+            }
+        }
+    }
 
     view! {
         gtk::Box {
@@ -107,19 +243,100 @@ impl SimpleComponent for NoteListColumnModel {
                     connect_clicked[sender] => move |_| {
                         sender.input(NoteListColumnInput::CreateNote);
                     }
+                },
+
+                #[name = "selection_mode_button"]
+                pack_end = &gtk::ToggleButton {
+                    set_icon_name: icons::selection_mode_symbolic(),
+                    set_tooltip_text: Some("Select Notes"),
+                    #[watch]
+                    set_active: model.selection_mode,
+                    connect_toggled[sender] => move |button| {
+                        sender.input(NoteListColumnInput::SetSelectionMode(button.is_active()));
+                    }
+                },
+
+                pack_end = &gtk::ToggleButton {
+                    set_icon_name: icons::system_search_symbolic(),
+                    #[watch]
+                    set_active: model.is_searching,
+                    connect_clicked[sender] => move |_| {
+                        sender.input(NoteListColumnInput::ToggleSearch);
+                    }
                 }
             },
 
+            #[name = "search_bar"]
+            gtk::SearchBar {
+                #[watch]
+                set_search_mode: model.is_searching,
+                connect_search_mode_enabled_notify[sender] => move |search_bar| {
+                    sender.input(NoteListColumnInput::SetSearchMode(search_bar.is_search_mode()));
+                },
+
+                #[wrap(Some)]
+                set_child: search_entry = &gtk::SearchEntry {
+                    set_hexpand: true,
+                    connect_search_changed[sender] => move |entry| {
+                        sender.input(NoteListColumnInput::Search(entry.text().to_string()));
+                    },
+                },
+            },
+
             #[local_ref]
-            note_list_box -> gtk::ListBox {
-                set_selection_mode: gtk::SelectionMode::Single,
-                add_css_class: "navigation-sidebar",
+            toast_overlay -> adw::ToastOverlay {
+                set_vexpand: true,
 
-                connect_row_selected[sender] => move |_, row| {
-                    if let Some(row) = row {
-                        sender.input(NoteListColumnInput::SelectNote(row.index() as usize))
+                #[wrap(Some)]
+                set_child = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+
+                    #[local_ref]
+                    note_list_box -> gtk::ListBox {
+                        set_selection_mode: gtk::SelectionMode::Multiple,
+                        add_css_class: "navigation-sidebar",
+
+                        connect_selected_rows_changed[sender] => move |_| {
+                            sender.input(NoteListColumnInput::SelectionChanged);
+                        }
                     }
                 }
+            },
+
+            gtk::ActionBar {
+                #[watch]
+                set_revealed: model.selection_mode || model.selected_count > 1,
+
+                pack_start = &gtk::Label {
+                    #[watch]
+                    set_label: &format!("{} selected", model.selected_count),
+                },
+
+                pack_end = &gtk::Button {
+                    set_label: "Delete",
+                    add_css_class: "destructive-action",
+                    #[watch]
+                    set_sensitive: model.selected_count > 0,
+                    connect_clicked[sender] => move |_| {
+                        sender.input(NoteListColumnInput::DeleteSelectedNote);
+                    }
+                },
+
+                pack_end = &gtk::Button {
+                    set_label: "Export…",
+                    #[watch]
+                    set_sensitive: model.selected_count > 0,
+                    connect_clicked[sender] => move |_| {
+                        sender.input(NoteListColumnInput::ExportSelected);
+                    }
+                },
+
+                pack_end = &gtk::MenuButton {
+                    set_label: "Move To",
+                    set_menu_model: Some(&model.move_menu),
+                    #[watch]
+                    set_sensitive: model.selected_count > 0,
+                },
             }
         }
     }
@@ -130,23 +347,186 @@ impl SimpleComponent for NoteListColumnModel {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let notes = FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
+        let mut note_events = init.ctx.note_events.subscribe();
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(async move {
+                    loop {
+                        let event = match note_events.recv().await {
+                            Ok(event) => event,
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        };
+                        if out.send(NoteListColumnCommand::NoteEvent(event)).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .drop_on_shutdown()
+        });
         let model = NoteListColumnModel {
             ctx: init.ctx,
             notes,
             folder_id: None,
+            query: None,
+            search_refresh_pending: false,
+            is_searching: false,
+            search_text: String::new(),
+            search_generation: 0,
+            load_generation: 0,
+            context_note_index: None,
+            pin_action: gio::SimpleAction::new_stateful(
+                PinNoteAction::NAME,
+                None,
+                &false.to_variant(),
+            ),
+            selection_mode: false,
+            selected_count: 0,
+            move_menu: gio::Menu::new(),
+            undo: None,
+            toast_overlay: adw::ToastOverlay::new(),
+            unsupported_search: None,
         };
 
         let note_list_box = model.notes.widget();
+        let toast_overlay = &model.toast_overlay;
 
         let widgets = view_output!();
 
+        widgets.search_bar.connect_entry(&widgets.search_entry);
+
+        let note_list_box = note_list_box.clone();
+        let context_menu = gtk::PopoverMenu::from_model(Some(&note_context_menu));
+        context_menu.set_has_arrow(false);
+        context_menu.set_halign(gtk::Align::Start);
+        context_menu.set_parent(&note_list_box);
+        let context_click = gtk::GestureClick::new();
+        context_click.set_button(gdk::BUTTON_SECONDARY);
+        context_click.connect_pressed(
+            glib::clone!(@weak note_list_box, @strong sender => move |_, _, x, y| {
+                if let Some(row) = note_list_box.row_at_y(y as i32) {
+                    sender.input(NoteListColumnInput::SetContextNote(row.index() as usize));
+                    context_menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                    context_menu.popup();
+                }
+            }),
+        );
+        note_list_box.add_controller(&context_click);
+
+        // Claims the tap before the list box selects only the tapped row.
+        let selection_click = gtk::GestureClick::new();
+        selection_click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        selection_click.connect_pressed(glib::clone!(
+            @weak note_list_box,
+            @weak widgets.selection_mode_button as selection_mode_button => move |gesture, _, _, y| {
+                if !selection_mode_button.is_active() {
+                    return;
+                }
+                if let Some(row) = note_list_box.row_at_y(y as i32) {
+                    if row.is_selected() {
+                        note_list_box.unselect_row(&row);
+                    } else {
+                        note_list_box.select_row(Some(&row));
+                    }
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                }
+            }
+        ));
+        note_list_box.add_controller(&selection_click);
+
+        let file_drop_target =
+            gtk::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+        file_drop_target.connect_drop(
+            glib::clone!(@strong sender => @default-return false, move |_, value, _, _| {
+                let Ok(files) = value.get::<gdk::FileList>() else {
+                    return false;
+                };
+                let paths = files.files().iter().filter_map(|file| file.path()).collect();
+                sender.input(NoteListColumnInput::DropFiles(paths));
+                true
+            }),
+        );
+        note_list_box.add_controller(&file_drop_target);
+
+        let actions = RelmActionGroup::<NoteListActionGroup>::new();
+        let copy_markdown_link_action: RelmAction<CopyMarkdownLinkAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(NoteListColumnInput::CopyMarkdownLink);
+            })
+        };
+        let open_in_new_window_action: RelmAction<OpenInNewWindowAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(NoteListColumnInput::OpenInNewWindow);
+            })
+        };
+        let export_action: RelmAction<ExportNoteAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(NoteListColumnInput::Export);
+            })
+        };
+        actions.add_action(&copy_markdown_link_action);
+        actions.add_action(&open_in_new_window_action);
+        let undo_action: RelmAction<UndoAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(NoteListColumnInput::Undo);
+            })
+        };
+        let move_selected_action: RelmAction<MoveSelectedAction> = {
+            let sender = sender.clone();
+            RelmAction::new_with_target_value(move |_, folder_id: String| {
+                sender.input(NoteListColumnInput::MoveSelected { folder_id });
+            })
+        };
+        actions.add_action(&export_action);
+        actions.add_action(&undo_action);
+        actions.add_action(&move_selected_action);
+        let actions = actions.into_action_group();
+        // Added directly, its state changes with the note the menu is opened on.
+        model.pin_action.connect_activate({
+            let sender = sender.clone();
+            move |action, _| {
+                let pinned = action.state().and_then(|state| state.get::<bool>());
+                sender.input(NoteListColumnInput::SetContextNotePinned(
+                    !pinned.unwrap_or_default(),
+                ));
+            }
+        });
+        actions.add_action(&model.pin_action);
+        root.insert_action_group(NoteListActionGroup::NAME, Some(&actions));
+
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>) {
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
         match input {
             NoteListColumnInput::RefreshNotes { folder_id } => {
-                self.reload_notes(folder_id);
+                self.query = None;
+                self.reload_notes(folder_id, &sender);
+            }
+            NoteListColumnInput::ShowSearch { query } => {
+                self.query = Some(Query::parse(&query));
+                self.reload_notes(None, &sender);
+            }
+            NoteListColumnInput::NoteUpdated => {
+                if self.query.is_none() || self.search_refresh_pending {
+                    return;
+                }
+                self.search_refresh_pending = true;
+                let input_sender = sender.input_sender().clone();
+                glib::timeout_add_local_once(SEARCH_REFRESH_DELAY, move || {
+                    // Fails only once the list is gone.
+                    let _ = input_sender.send(NoteListColumnInput::RefreshSearch);
+                });
+            }
+            NoteListColumnInput::RefreshSearch => {
+                self.search_refresh_pending = false;
+                if self.query.is_some() {
+                    self.reload_notes(self.folder_id.clone(), &sender);
+                }
             }
             NoteListColumnInput::SelectNote(index) => {
                 sender
@@ -162,23 +542,544 @@ impl SimpleComponent for NoteListColumnModel {
                     })
                     .unwrap();
             }
+            NoteListColumnInput::ToggleSearch => {
+                self.is_searching = !self.is_searching;
+            }
+            NoteListColumnInput::SetSearchMode(is_searching) => {
+                self.is_searching = is_searching;
+                if !is_searching && !self.search_text.is_empty() {
+                    self.search_text.clear();
+                    self.reload_notes(self.folder_id.clone(), &sender);
+                }
+            }
+            NoteListColumnInput::Search(text) => {
+                self.search_text = text;
+                self.search_generation = self.search_generation.wrapping_add(1);
+                let generation = self.search_generation;
+                sender.oneshot_command(async move {
+                    tokio::time::sleep(SEARCH_DELAY).await;
+                    NoteListColumnCommand::RunSearch { generation }
+                });
+            }
+            NoteListColumnInput::DeleteSelectedNote => {
+                let notes = self.selected_notes();
+                let (heading, body) = match notes.as_slice() {
+                    [] => return,
+                    [note] => (
+                        "Delete Note?".to_string(),
+                        format!("“{}” will be deleted.", note.title),
+                    ),
+                    notes => (
+                        format!("Delete {} Notes?", notes.len()),
+                        "The selected notes will be deleted.".to_string(),
+                    ),
+                };
+                let window = self
+                    .notes
+                    .widget()
+                    .root()
+                    .and_then(|root| root.downcast::<gtk::Window>().ok());
+                let dialog = adw::MessageDialog::new(window.as_ref(), Some(&heading), Some(&body));
+                dialog.add_response("cancel", "_Cancel");
+                dialog.add_response("delete", "_Delete");
+                dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+                dialog.set_default_response(Some("cancel"));
+                dialog.connect_response(None, move |_, response| {
+                    if response == "delete" {
+                        sender.input(NoteListColumnInput::ConfirmDeleteSelected);
+                    }
+                });
+                dialog.present();
+            }
+            NoteListColumnInput::ConfirmDeleteSelected => {
+                self.delete_hidden_notes(&sender);
+                let ids: Vec<String> = self
+                    .selected_notes()
+                    .iter()
+                    .map(|note| note.id.clone())
+                    .collect();
+                if ids.is_empty() {
+                    return;
+                }
+                for id in &ids {
+                    // Fails only once the content page is gone.
+                    let _ = sender.output(NoteListColumnOutput::DeletedNote { id: id.clone() });
+                }
+                let toast = adw::Toast::new(&format!("{} deleted", note_count(ids.len())));
+                toast.set_button_label(Some("Undo"));
+                toast.set_action_name(Some(&UndoAction::action_name()));
+                toast.connect_dismissed(glib::clone!(@strong sender, @strong ids => move |_| {
+                    sender.input(NoteListColumnInput::CommitDelete { ids: ids.clone() });
+                }));
+                self.undo = Some(UndoChange::Delete(ids));
+                self.reload_notes(self.folder_id.clone(), &sender);
+                self.toast_overlay.add_toast(&toast);
+            }
+            NoteListColumnInput::CommitDelete { ids } => {
+                // Undone, or already deleted before a later change.
+                if matches!(&self.undo, Some(UndoChange::Delete(hidden)) if *hidden == ids) {
+                    self.delete_hidden_notes(&sender);
+                }
+            }
+            NoteListColumnInput::SelectionChanged => {
+                let selected_rows = self.notes.widget().selected_rows();
+                self.selected_count = selected_rows.len();
+                match selected_rows.as_slice() {
+                    [row] if !self.selection_mode => {
+                        sender.input(NoteListColumnInput::SelectNote(row.index() as usize));
+                    }
+                    [] => {}
+                    _ => self.reload_move_menu(),
+                }
+            }
+            NoteListColumnInput::SetSelectionMode(selection_mode) => {
+                self.selection_mode = selection_mode;
+                if selection_mode {
+                    self.reload_move_menu();
+                }
+            }
+            NoteListColumnInput::MoveSelected { folder_id } => {
+                self.delete_hidden_notes(&sender);
+                let ids: Vec<String> = self
+                    .selected_notes()
+                    .iter()
+                    .map(|note| note.id.clone())
+                    .collect();
+                let data = &self.ctx.data;
+                let result = bulk_change::apply_all(
+                    &ids,
+                    |id| {
+                        let from = data.db.load_note(id)?.parent_id;
+                        set_note_parent(data, id, Some(folder_id.clone()))?;
+                        Ok(MovedNote {
+                            id: id.clone(),
+                            from,
+                        })
+                    },
+                    |moved| set_note_parent(data, &moved.id, moved.from.clone()),
+                );
+                self.finish_move(result, &sender);
+            }
+            NoteListColumnInput::ExportSelected => {
+                let notes = self.selected_notes();
+                let output = match notes.as_slice() {
+                    [] => return,
+                    [note] => NoteListColumnOutput::Export {
+                        id: note.id.clone(),
+                        title: note.title.clone(),
+                    },
+                    notes => NoteListColumnOutput::ExportNotes {
+                        ids: notes.iter().map(|note| note.id.clone()).collect(),
+                    },
+                };
+                sender.output(output).unwrap();
+            }
+            NoteListColumnInput::Undo => match self.undo.take() {
+                Some(UndoChange::Move(moved)) => {
+                    let not_restored = bulk_change::undo_all(&moved, |moved| {
+                        set_note_parent(&self.ctx.data, &moved.id, moved.from.clone())
+                    });
+                    for moved in moved {
+                        capture::notify_changed(&self.ctx, moved.id);
+                    }
+                    if not_restored > 0 {
+                        self.toast_overlay.add_toast(&adw::Toast::new(&format!(
+                            "Undo failed for {}",
+                            note_count(not_restored)
+                        )));
+                    }
+                }
+                // Nothing was written, the notes only have to be shown again.
+                Some(UndoChange::Delete(_)) => {
+                    self.reload_notes(self.folder_id.clone(), &sender);
+                }
+                None => {}
+            },
+            NoteListColumnInput::FocusList => {
+                let note_list_box = self.notes.widget();
+                match note_list_box.selected_row() {
+                    Some(row) => row.grab_focus(),
+                    None => note_list_box.grab_focus(),
+                };
+            }
+            NoteListColumnInput::SelectNextNote => {
+                self.select_note_by_offset(1);
+            }
+            NoteListColumnInput::SelectPreviousNote => {
+                self.select_note_by_offset(-1);
+            }
+            NoteListColumnInput::SetContextNote(index) => {
+                self.context_note_index = Some(index);
+                let pinned = self.notes.get(index).map_or(false, |note| note.pinned);
+                self.pin_action.set_state(&pinned.to_variant());
+            }
+            NoteListColumnInput::SetContextNotePinned(pinned) => {
+                let Some(note) = self
+                    .context_note_index
+                    .and_then(|index| self.notes.get(index))
+                else {
+                    return;
+                };
+                if let Err(e) = favourites::set_note_pinned(&self.ctx, &note.abbr_note.id, pinned) {
+                    log::warn!("save pinned notes failed: {e}");
+                }
+            }
+            NoteListColumnInput::CopyMarkdownLink => {
+                let Some(note) = self
+                    .context_note_index
+                    .and_then(|index| self.notes.get(index))
+                else {
+                    return;
+                };
+                let link = markdown::note_link(&note.abbr_note.title, &note.abbr_note.id);
+                if let Some(display) = gdk::Display::default() {
+                    display.clipboard().set_text(&link);
+                }
+            }
+            NoteListColumnInput::OpenInNewWindow => {
+                if let Some(note) = self
+                    .context_note_index
+                    .and_then(|index| self.notes.get(index))
+                {
+                    sender
+                        .output(NoteListColumnOutput::OpenInNewWindow {
+                            id: note.abbr_note.id.clone(),
+                        })
+                        .unwrap();
+                }
+            }
+            NoteListColumnInput::Export => {
+                if let Some(note) = self
+                    .context_note_index
+                    .and_then(|index| self.notes.get(index))
+                {
+                    sender
+                        .output(NoteListColumnOutput::Export {
+                            id: note.abbr_note.id.clone(),
+                            title: note.abbr_note.title.clone(),
+                        })
+                        .unwrap();
+                }
+            }
+            NoteListColumnInput::NoteChanged => {
+                self.reload_notes(self.folder_id.clone(), &sender);
+            }
+            NoteListColumnInput::DropFiles(paths) => {
+                let skipped: Vec<String> = paths
+                    .iter()
+                    .filter_map(|path| self.create_note_from_file(path).err())
+                    .collect();
+                match skipped.as_slice() {
+                    [] => {}
+                    [reason] => self.toast_overlay.add_toast(&adw::Toast::new(reason)),
+                    _ => self.toast_overlay.add_toast(&adw::Toast::new(&format!(
+                        "{} of the dropped files were not added",
+                        skipped.len()
+                    ))),
+                }
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            NoteListColumnCommand::NoteEvent(
+                NoteEvent::Changed { .. } | NoteEvent::FavouritesChanged,
+            ) => {
+                sender.input(NoteListColumnInput::NoteChanged);
+            }
+            NoteListColumnCommand::NoteEvent(NoteEvent::Updated { .. }) => {
+                sender.input(NoteListColumnInput::NoteUpdated);
+            }
+            NoteListColumnCommand::RunSearch { generation } => {
+                if generation == self.search_generation {
+                    self.reload_notes(self.folder_id.clone(), &sender);
+                }
+            }
+            NoteListColumnCommand::NotesLoaded { generation, notes } => {
+                if generation != self.load_generation {
+                    return;
+                }
+                match notes {
+                    Ok(notes) => self.show_notes(notes),
+                    Err(e) => {
+                        log::warn!("load notes failed: {e:?}");
+                        self.toast_overlay
+                            .add_toast(&adw::Toast::new("Couldn't load the notes"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn shutdown(&mut self, _widgets: &mut Self::Widgets, _output: relm4::Sender<Self::Output>) {
+        // Notes deleted while the toast still shows are deleted for good when the window closes.
+        if let Some(UndoChange::Delete(ids)) = self.undo.take() {
+            delete_notes(&self.ctx.data, &ids);
         }
     }
 }
 
 impl NoteListColumnModel {
-    fn reload_notes(&mut self, folder_id: Option<String>) {
+    // The folder is shown right away, its notes once they are loaded.
+    fn reload_notes(&mut self, folder_id: Option<String>, sender: &ComponentSender<Self>) {
+        self.folder_id = folder_id.clone();
+        self.load_generation = self.load_generation.wrapping_add(1);
+        let generation = self.load_generation;
+        let data = self.ctx.data.clone();
+        let resources_dir = self.ctx.resources_dir.clone();
+        let query = self.query.clone();
+        let search_query = Query::parse(&self.search_text);
+        let unsupported = query
+            .as_ref()
+            .and_then(Query::unsupported)
+            .or_else(|| search_query.unsupported());
+        if unsupported.is_some() && unsupported != self.unsupported_search {
+            self.toast_overlay
+                .add_toast(&adw::Toast::new(unsupported.as_deref().unwrap_or_default()));
+        }
+        self.unsupported_search = unsupported;
+        sender.spawn_oneshot_command(move || {
+            let notes = load_notes(
+                &data,
+                &resources_dir,
+                folder_id.as_deref(),
+                query.as_ref(),
+                &search_query,
+            );
+            NoteListColumnCommand::NotesLoaded { generation, notes }
+        });
+    }
+
+    fn show_notes(&mut self, notes: Vec<AbbrNote>) {
+        let pinned = favourites::load(&self.ctx).notes;
+        let hidden: &[String] = match &self.undo {
+            Some(UndoChange::Delete(ids)) => ids.as_slice(),
+            _ => &[],
+        };
+        let mut notes: Vec<(AbbrNote, bool)> = notes
+            .into_iter()
+            .filter(|note| !hidden.contains(&note.id))
+            .map(|note| {
+                let is_pinned = pinned.contains(&note.id);
+                (note, is_pinned)
+            })
+            .collect();
+        // Stable, so pinned notes keep their order among themselves.
+        notes.sort_by_key(|(_, is_pinned)| !is_pinned);
+        let ids: Vec<String> = notes.iter().map(|(note, _)| note.id.clone()).collect();
         let mut notes_guard = self.notes.guard();
         notes_guard.clear();
-        for note in self
-            .ctx
+        for note in notes {
+            notes_guard.push_back(note);
+        }
+        drop(notes_guard);
+        for (index, id) in ids.into_iter().enumerate() {
+            super::add_row_drag_source(self.notes.widget(), index, super::DraggedItem::Note(id));
+        }
+    }
+
+    // Text files become notes in the folder shown. ruslin-data can't store resources, so other
+    // files are refused like attachments on import. The error says why, for the toast.
+    fn create_note_from_file(&self, path: &Path) -> Result<(), String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        if !matches!(extension.as_str(), "md" | "markdown" | "txt") {
+            return Err(format!(
+                "{name} was not added, only text files can be dropped"
+            ));
+        }
+        let body = fs::read_to_string(path).map_err(|e| {
+            log::warn!("read dropped file {} failed: {e}", path.display());
+            format!("Couldn't read {name}")
+        })?;
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let note = Note::new(self.folder_id.clone(), title, body);
+        self.ctx
             .data
             .db
-            .load_abbr_notes(folder_id.as_deref())
-            .unwrap()
-        {
-            notes_guard.push_back(note);
+            .replace_note(&note, UpdateSource::LocalEdit)
+            .map_err(|e| {
+                log::warn!("create note from {} failed: {e:?}", path.display());
+                format!("Couldn't add {name}")
+            })?;
+        capture::notify_changed(&self.ctx, note.id);
+        Ok(())
+    }
+
+    fn selected_notes(&self) -> Vec<&AbbrNote> {
+        self.notes
+            .widget()
+            .selected_rows()
+            .iter()
+            .filter_map(|row| self.notes.get(row.index() as usize))
+            .map(|item| &item.abbr_note)
+            .collect()
+    }
+
+    // Says how the move went in a toast, offering undo when every note moved.
+    fn finish_move(
+        &mut self,
+        result: Result<Vec<MovedNote>, BulkChangeError<DatabaseError>>,
+        sender: &ComponentSender<Self>,
+    ) {
+        self.reload_notes(self.folder_id.clone(), sender);
+        let moved = match result {
+            Ok(moved) if !moved.is_empty() => moved,
+            Ok(_) => return,
+            Err(BulkChangeError {
+                error,
+                not_restored,
+            }) => {
+                log::warn!("move failed, {not_restored} notes not moved back: {error:?}");
+                let message = match not_restored {
+                    0 => "No notes moved, an error occurred".to_string(),
+                    count => format!(
+                        "An error occurred, {} couldn't be moved back",
+                        note_count(count)
+                    ),
+                };
+                self.toast_overlay.add_toast(&adw::Toast::new(&message));
+                return;
+            }
+        };
+        for moved in &moved {
+            capture::notify_changed(&self.ctx, moved.id.clone());
+        }
+        let toast = adw::Toast::new(&format!("{} moved", note_count(moved.len())));
+        toast.set_button_label(Some("Undo"));
+        toast.set_action_name(Some(&UndoAction::action_name()));
+        self.undo = Some(UndoChange::Move(moved));
+        self.toast_overlay.add_toast(&toast);
+    }
+
+    // Deletes the notes the last delete hid. Runs before every other change, which also drops the
+    // undo of an older move, as that would move notes the new change touched.
+    fn delete_hidden_notes(&mut self, sender: &ComponentSender<Self>) {
+        let Some(UndoChange::Delete(ids)) = self.undo.take() else {
+            return;
+        };
+        let not_deleted = delete_notes(&self.ctx.data, &ids);
+        if not_deleted > 0 {
+            self.reload_notes(self.folder_id.clone(), sender);
+            self.toast_overlay.add_toast(&adw::Toast::new(&format!(
+                "{} couldn't be deleted",
+                note_count(not_deleted)
+            )));
+        }
+    }
+
+    fn reload_move_menu(&self) {
+        self.move_menu.remove_all();
+        let folders = match self.ctx.data.db.load_folders() {
+            Ok(folders) => folders,
+            Err(e) => {
+                log::warn!("load folders failed: {e:?}");
+                return;
+            }
+        };
+        for (folder, depth) in folder_order::tree(folders, &folder_order::load(&self.ctx)) {
+            let label = format!("{}{}", "    ".repeat(depth), folder.title);
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(
+                Some(&MoveSelectedAction::action_name()),
+                Some(&folder.id.to_variant()),
+            );
+            self.move_menu.append_item(&item);
         }
-        self.folder_id = folder_id;
+    }
+
+    fn select_note_by_offset(&self, offset: i32) {
+        let note_list_box = self.notes.widget();
+        let index = match note_list_box.selected_row() {
+            Some(row) => row.index() + offset,
+            None => 0,
+        };
+        if index < 0 {
+            return;
+        }
+        if let Some(row) = note_list_box.row_at_index(index) {
+            note_list_box.unselect_all();
+            note_list_box.select_row(Some(&row));
+            row.grab_focus();
+        }
+    }
+}
+
+// The notes of the folder or saved search, narrowed down by the search entry.
+fn load_notes(
+    data: &RuslinData,
+    resources_dir: &Path,
+    folder_id: Option<&str>,
+    query: Option<&Query>,
+    search_query: &Query,
+) -> Result<Vec<AbbrNote>, DatabaseError> {
+    let notes = match query {
+        Some(query) => search::query_notes(data, resources_dir, query)?,
+        None => data.db.load_abbr_notes(folder_id)?,
+    };
+    if search_query.is_empty() {
+        return Ok(notes);
+    }
+    search::filter_notes(data, resources_dir, notes, search_query)
+}
+
+// Undo of a move only puts the folder back, edits made since are kept.
+#[derive(Debug)]
+struct MovedNote {
+    id: String,
+    from: Option<String>,
+}
+
+// Deletes only happen once the toast is gone, until then the notes are hidden.
+#[derive(Debug)]
+enum UndoChange {
+    Move(Vec<MovedNote>),
+    Delete(Vec<String>),
+}
+
+fn set_note_parent(
+    data: &RuslinData,
+    id: &str,
+    parent_id: Option<String>,
+) -> Result<(), DatabaseError> {
+    let mut note = data.db.load_note(id)?;
+    note.parent_id = parent_id;
+    data.db.replace_note(&note, UpdateSource::LocalEdit)?;
+    Ok(())
+}
+
+// Returns how many couldn't be deleted.
+fn delete_notes(data: &RuslinData, ids: &[String]) -> usize {
+    ids.iter()
+        .filter(|id| match data.db.delete_note(id) {
+            Ok(_) => false,
+            Err(e) => {
+                log::warn!("delete note {id} failed: {e:?}");
+                true
+            }
+        })
+        .count()
+}
+
+fn note_count(count: usize) -> String {
+    match count {
+        1 => "1 note".to_string(),
+        count => format!("{count} notes"),
     }
 }
@@ -1,6 +1,9 @@
+use std::time::Duration;
+
 use adw::prelude::*;
+use gtk::{gdk, gio, glib};
 use relm4::{
-    actions::{RelmAction, RelmActionGroup},
+    actions::{ActionGroupName, ActionName, RelmAction, RelmActionGroup},
     factory::FactoryVecDeque,
     gtk,
     prelude::*,
@@ -8,16 +11,31 @@ use relm4::{
 };
 use ruslin_data::{
     sync::{SyncError, SyncInfo},
-    DatabaseError, Folder,
+    DatabaseError, Folder, UpdateSource,
 };
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
-    components::{EntryDialogInit, EntryDialogInput, EntryDialogModel, EntryDialogOutput},
-    icons, AppContext,
+    app::NoteEvent,
+    cancel::{self, CancelFlag},
+    capture,
+    components::{
+        EntryDialogInit, EntryDialogInput, EntryDialogModel, EntryDialogOutput, ImportDialogInput,
+        ImportDialogModel, ImportDialogOutput,
+    },
+    content_page::DraggedItem,
+    data_dir, favourites,
+    folder_order::{self, DropPosition},
+    icons, pending_changes, saved_searches, sync_check, AppContext,
 };
 
+// Failed syncs are retried after this, doubling up to `MAX_RETRY_DELAY`.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
 struct FolderItemModel {
     folder: Folder,
+    depth: usize,
 }
 
 #[derive(Debug)]
@@ -30,7 +48,7 @@ enum FolderItemOutput {}
 
 #[relm4::factory]
 impl FactoryComponent for FolderItemModel {
-    type Init = Folder;
+    type Init = (Folder, usize);
     type Input = FolderItemInput;
     type Output = FolderItemOutput;
     type CommandOutput = ();
@@ -40,6 +58,8 @@ impl FactoryComponent for FolderItemModel {
 
     view! {
         root = gtk::Box {
+            // Subfolders are indented under their parent.
+            set_margin_start: 12 * self.depth as i32,
             #[name(label)]
             gtk::Label {
                 #[watch]
@@ -50,7 +70,88 @@ impl FactoryComponent for FolderItemModel {
     }
 
     fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
-        Self { folder: init }
+        let (folder, depth) = init;
+        Self { folder, depth }
+    }
+}
+
+#[derive(Debug)]
+enum Favourite {
+    Note { id: String, title: String },
+    Folder { id: String, title: String },
+}
+
+struct FavouriteItemModel {
+    favourite: Favourite,
+}
+
+#[relm4::factory]
+impl FactoryComponent for FavouriteItemModel {
+    type Init = Favourite;
+    type Input = ();
+    type Output = ();
+    type CommandOutput = ();
+    type Widgets = FavouriteItemWidgets;
+    type ParentInput = SidebarColumnInput;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        root = gtk::Box {
+            set_spacing: 6,
+
+            gtk::Image {
+                set_icon_name: Some(match &self.favourite {
+                    Favourite::Note { .. } => icons::view_pin_symbolic(),
+                    Favourite::Folder { .. } => icons::starred_symbolic(),
+                }),
+            },
+
+            gtk::Label {
+                set_label: match &self.favourite {
+                    Favourite::Note { title, .. } | Favourite::Folder { title, .. } => title,
+                },
+                set_ellipsize: gtk::pango::EllipsizeMode::End,
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self { favourite: init }
+    }
+}
+
+struct SavedSearchItemModel {
+    query: String,
+}
+
+#[relm4::factory]
+impl FactoryComponent for SavedSearchItemModel {
+    type Init = String;
+    type Input = ();
+    type Output = ();
+    type CommandOutput = ();
+    type Widgets = SavedSearchItemWidgets;
+    type ParentInput = SidebarColumnInput;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        root = gtk::Box {
+            set_spacing: 6,
+
+            gtk::Image {
+                set_icon_name: Some(icons::system_search_symbolic()),
+            },
+
+            gtk::Label {
+                set_label: &self.query,
+                set_ellipsize: gtk::pango::EllipsizeMode::End,
+                add_css_class: "heading",
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self { query: init }
     }
 }
 
@@ -59,10 +160,59 @@ pub struct SidebarColumnModel {
     #[tracker::do_not_track]
     folders: FactoryVecDeque<FolderItemModel>,
     #[tracker::do_not_track]
+    favourites: FactoryVecDeque<FavouriteItemModel>,
+    #[tracker::do_not_track]
+    favourite_ids: favourites::Favourites,
+    // Shows and toggles whether the context menu's folder is a favourite.
+    #[tracker::do_not_track]
+    favourite_folder_action: gio::SimpleAction,
+    #[tracker::do_not_track]
+    saved_searches: FactoryVecDeque<SavedSearchItemModel>,
+    #[tracker::do_not_track]
     add_note_dialog: Controller<EntryDialogModel>,
     #[tracker::do_not_track]
+    add_saved_search_dialog: Controller<EntryDialogModel>,
+    #[tracker::do_not_track]
+    edit_saved_search_dialog: Controller<EntryDialogModel>,
+    // The query the edit dialog was opened on.
+    #[tracker::do_not_track]
+    editing_saved_search: Option<String>,
+    #[tracker::do_not_track]
+    import_dialog: Controller<ImportDialogModel>,
+    #[tracker::do_not_track]
+    new_profile_dialog: Controller<EntryDialogModel>,
+    #[tracker::do_not_track]
     ctx: AppContext,
+    #[tracker::do_not_track]
+    context_folder_index: Option<usize>,
+    #[tracker::do_not_track]
+    context_saved_search_index: Option<usize>,
+    #[tracker::do_not_track]
+    network_monitor: gio::NetworkMonitor,
+    #[tracker::do_not_track]
+    network_changed_handler: Option<glib::SignalHandlerId>,
+    // When the running sync started, saved once it finishes so the pending count starts over.
+    #[tracker::do_not_track]
+    sync_started: Option<String>,
+    // A sync was asked for while offline, it runs once the network is back.
+    #[tracker::do_not_track]
+    sync_queued: bool,
+    #[tracker::do_not_track]
+    retry_delay: Option<Duration>,
+    // Bumped whenever a sync starts, so a retry scheduled before it doesn't start another one.
+    #[tracker::do_not_track]
+    retry_generation: u32,
+    // Set to stop the running sync before its next item.
+    #[tracker::do_not_track]
+    sync_cancel: Option<CancelFlag>,
     is_syncing: bool,
+    is_cancelling_sync: bool,
+    is_online: bool,
+    // The server answered but the sync still failed, retrying won't help until the settings change.
+    sync_failed: bool,
+    pending_count: usize,
+    has_favourites: bool,
+    has_saved_searches: bool,
 }
 
 pub struct SidebarColumnInit {
@@ -73,10 +223,46 @@ pub struct SidebarColumnInit {
 pub enum SidebarColumnInput {
     SelectFolderIndex(u32),
     SelectAllNotes,
+    SelectSavedSearchIndex(u32),
     ShowCreateFolderDialog,
+    ShowAddSavedSearchDialog,
+    AddSavedSearch {
+        query: String,
+    },
+    SetContextSavedSearch(usize),
+    ShowEditContextSavedSearch,
+    EditSavedSearch {
+        query: String,
+    },
+    RemoveContextSavedSearch,
+    ShowImportDialog,
     SyncRemote,
-    InsertFolder { title: String },
+    // Downloads every item on the server again.
+    SyncFromScratch,
+    InsertFolder {
+        title: String,
+    },
     ReloadFolders,
+    SetContextFolder(usize),
+    // A note is moved into the folder, a folder next to it or into it.
+    DropOnFolder {
+        item: DraggedItem,
+        index: usize,
+        position: DropPosition,
+    },
+    SetContextFolderFavourite(bool),
+    ReloadFavourites,
+    ActivateFavourite(usize),
+    ExportContextFolder,
+    ShowNewProfileDialog,
+    SwitchProfile {
+        profile: Option<String>,
+    },
+    LocalChange {
+        id: String,
+    },
+    NetworkChanged(bool),
+    CancelSync,
 }
 
 #[derive(Debug)]
@@ -84,13 +270,25 @@ pub enum SidebarColumnCommand {
     SyncSuccess(SyncInfo),
     ReloadFolders(Vec<Folder>),
     AddedFolder,
-    ToastSyncError(SyncError),
+    SyncFailed {
+        error: SyncError,
+        server_reachable: bool,
+    },
     ToastError(DatabaseError),
+    RetrySync {
+        generation: u32,
+    },
+    SyncCancelled,
+    PendingCount(usize),
 }
 
 #[derive(Debug)]
 pub enum SidebarColumnOutput {
     OpenFolder { folder_id: Option<String> },
+    OpenSearch { query: String },
+    ExportFolder { folder_id: String, title: String },
+    SwitchProfile { profile: Option<String> },
+    OpenNote { id: String },
 }
 
 relm4::new_action_group!(pub(super) WindowActionGroup, "win");
@@ -98,6 +296,41 @@ relm4::new_stateless_action!(PreferencesAction, WindowActionGroup, "preferences"
 relm4::new_stateless_action!(pub(super) ShortcutsAction, WindowActionGroup, "show-help-overlay");
 relm4::new_stateless_action!(AboutAction, WindowActionGroup, "about");
 relm4::new_stateless_action!(AddFolderAction, WindowActionGroup, "add-folder");
+relm4::new_stateless_action!(AddSavedSearchAction, WindowActionGroup, "add-saved-search");
+relm4::new_stateless_action!(ImportAction, WindowActionGroup, "import");
+relm4::new_stateless_action!(PrintAction, WindowActionGroup, "print");
+
+relm4::new_action_group!(SidebarActionGroup, "sidebar");
+relm4::new_stateless_action!(ExportFolderAction, SidebarActionGroup, "export-folder");
+relm4::new_stateful_action!(
+    FavouriteFolderAction,
+    SidebarActionGroup,
+    "favourite-folder",
+    (),
+    bool
+);
+relm4::new_stateless_action!(NewProfileAction, SidebarActionGroup, "new-profile");
+relm4::new_stateless_action!(
+    EditSavedSearchAction,
+    SidebarActionGroup,
+    "edit-saved-search"
+);
+relm4::new_stateless_action!(
+    RemoveSavedSearchAction,
+    SidebarActionGroup,
+    "remove-saved-search"
+);
+
+// The profile name, empty for the default profile.
+struct SwitchProfileAction;
+
+impl ActionName for SwitchProfileAction {
+    type Group = SidebarActionGroup;
+    type Target = String;
+    type State = ();
+
+    const NAME: &'static str = "switch-profile";
+}
 
 #[relm4::component(pub)]
 impl Component for SidebarColumnModel {
@@ -109,6 +342,10 @@ impl Component for SidebarColumnModel {
 
     menu! {
         primary_menu: {
+            section! {
+                "_Import…" => ImportAction,
+                "_Print…" => PrintAction,
+            },
             section! {
                 "_Preferences" => PreferencesAction,
                 "_Keyboard" => ShortcutsAction,
@@ -118,6 +355,19 @@ impl Component for SidebarColumnModel {
         add_menu: {
             section! {
                 "Add Folder" => AddFolderAction,
+                "Add Saved Search" => AddSavedSearchAction,
+            }
+        },
+        folder_context_menu: {
+            section! {
+                "Favourite" => FavouriteFolderAction,
+                "Export…" => ExportFolderAction,
+            }
+        },
+        saved_search_context_menu: {
+            section! {
+                "Edit…" => EditSavedSearchAction,
+                "Remove" => RemoveSavedSearchAction,
             }
         }
     }
@@ -148,7 +398,11 @@ impl Component for SidebarColumnModel {
                 },
 
                 #[wrap(Some)]
-                set_title_widget = &adw::WindowTitle {
+                set_title_widget = &gtk::MenuButton {
+                    set_label: model.ctx.profile.as_deref().unwrap_or("Default"),
+                    set_tooltip_text: Some("Switch Profile"),
+                    set_menu_model: Some(&profile_menu()),
+                    add_css_class: "flat",
                 },
 
                 set_show_end_title_buttons: false,
@@ -164,6 +418,31 @@ impl Component for SidebarColumnModel {
                 }
             },
 
+            gtk::Label {
+                set_label: "Favourites",
+                set_tooltip_text: Some("Pinned notes and favourite folders are kept on this device, they aren't synced"),
+                set_xalign: 0.0,
+                set_margin_start: 12,
+                set_margin_top: 6,
+                add_css_class: "dim-label",
+                add_css_class: "caption-heading",
+                #[track = "model.changed(SidebarColumnModel::has_favourites())"]
+                set_visible: model.has_favourites,
+            },
+
+            #[local_ref]
+            favourite_list_box -> gtk::ListBox {
+                set_selection_mode: gtk::SelectionMode::None,
+                set_activate_on_single_click: true,
+                add_css_class: "navigation-sidebar",
+                #[track = "model.changed(SidebarColumnModel::has_favourites())"]
+                set_visible: model.has_favourites,
+
+                connect_row_activated[sender] => move |_, row| {
+                    sender.input(SidebarColumnInput::ActivateFavourite(row.index() as usize));
+                }
+            },
+
             #[name = "all_notes_list_box"]
             gtk::ListBox {
                 set_selection_mode: gtk::SelectionMode::Single,
@@ -176,26 +455,116 @@ impl Component for SidebarColumnModel {
                     },
                 },
 
-                connect_row_selected[sender, folder_list_box] => move |_, row| {
+                connect_row_selected[sender, folder_list_box, saved_search_list_box] => move |_, row| {
                     if row.is_some() {
                         folder_list_box.unselect_all();
+                        saved_search_list_box.unselect_all();
                         sender.input(SidebarColumnInput::SelectAllNotes);
                     }
                 }
             },
 
-            #[local_ref]
-            folder_list_box -> gtk::ListBox {
-                set_selection_mode: gtk::SelectionMode::Single,
-                add_css_class: "navigation-sidebar",
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_vexpand: true,
+
+                #[local_ref]
+                folder_list_box -> gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::Single,
+                    add_css_class: "navigation-sidebar",
 
-                connect_row_selected[sender, all_notes_list_box] => move |_, row| {
-                    if let Some(row) = row {
-                        all_notes_list_box.unselect_all();
-                        sender.input(SidebarColumnInput::SelectFolderIndex(row.index() as u32));
+                    connect_row_selected[sender, all_notes_list_box, saved_search_list_box] => move |_, row| {
+                        if let Some(row) = row {
+                            all_notes_list_box.unselect_all();
+                            saved_search_list_box.unselect_all();
+                            sender.input(SidebarColumnInput::SelectFolderIndex(row.index() as u32));
+                        }
                     }
-                }
-            }
+                },
+
+                gtk::Label {
+                    set_label: "Saved Searches",
+                    set_tooltip_text: Some("Saved searches are kept on this device for the open profile, they aren't synced"),
+                    set_xalign: 0.0,
+                    set_margin_start: 12,
+                    set_margin_top: 6,
+                    add_css_class: "dim-label",
+                    add_css_class: "caption-heading",
+                    #[track = "model.changed(SidebarColumnModel::has_saved_searches())"]
+                    set_visible: model.has_saved_searches,
+                },
+
+                #[local_ref]
+                saved_search_list_box -> gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::Single,
+                    add_css_class: "navigation-sidebar",
+                    #[track = "model.changed(SidebarColumnModel::has_saved_searches())"]
+                    set_visible: model.has_saved_searches,
+
+                    connect_row_selected[sender, all_notes_list_box, folder_list_box] => move |_, row| {
+                        if let Some(row) = row {
+                            all_notes_list_box.unselect_all();
+                            folder_list_box.unselect_all();
+                            sender.input(SidebarColumnInput::SelectSavedSearchIndex(row.index() as u32));
+                        }
+                    }
+                },
+            },
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 6,
+                set_margin_all: 12,
+                #[track = "model.changed(SidebarColumnModel::is_syncing())"]
+                set_visible: model.is_syncing,
+
+                #[name = "sync_progress_bar"]
+                gtk::ProgressBar {
+                    set_pulse_step: 0.1,
+                },
+
+                gtk::Box {
+                    set_spacing: 6,
+
+                    gtk::Label {
+                        #[track = "model.changed(SidebarColumnModel::is_cancelling_sync() | SidebarColumnModel::pending_count())"]
+                        set_label: &sync_progress(model.is_cancelling_sync, model.pending_count),
+                        set_hexpand: true,
+                        set_xalign: 0.0,
+                        add_css_class: "dim-label",
+                    },
+
+                    gtk::Button {
+                        set_label: "Cancel",
+                        #[track = "model.changed(SidebarColumnModel::is_cancelling_sync())"]
+                        set_sensitive: !model.is_cancelling_sync,
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SidebarColumnInput::CancelSync);
+                        },
+                    },
+                },
+            },
+
+            gtk::Box {
+                set_spacing: 6,
+                set_margin_all: 12,
+                #[track = "model.changed(SidebarColumnModel::is_online() | SidebarColumnModel::sync_failed() | SidebarColumnModel::pending_count())"]
+                set_visible: !model.is_online || model.sync_failed || model.pending_count > 0,
+
+                gtk::Image {
+                    set_icon_name: Some(icons::network_offline_symbolic()),
+                    #[track = "model.changed(SidebarColumnModel::is_online())"]
+                    set_visible: !model.is_online,
+                },
+
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    add_css_class: "dim-label",
+                    #[track = "model.changed(SidebarColumnModel::is_online() | SidebarColumnModel::sync_failed() | SidebarColumnModel::pending_count())"]
+                    set_label: &sync_status(model.is_online, model.sync_failed, model.pending_count),
+                },
+            },
         }
     }
 
@@ -206,6 +575,10 @@ impl Component for SidebarColumnModel {
     ) -> ComponentParts<Self> {
         let folders: FactoryVecDeque<FolderItemModel> =
             FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
+        let favourites: FactoryVecDeque<FavouriteItemModel> =
+            FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
+        let saved_searches: FactoryVecDeque<SavedSearchItemModel> =
+            FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
         let add_note_dialog = EntryDialogModel::builder()
             .transient_for(&root)
             .launch(EntryDialogInit {
@@ -215,23 +588,263 @@ impl Component for SidebarColumnModel {
             .forward(sender.input_sender(), |msg| match msg {
                 EntryDialogOutput::Text(title) => SidebarColumnInput::InsertFolder { title },
             });
-        let model = SidebarColumnModel {
+        let add_saved_search_dialog = EntryDialogModel::builder()
+            .transient_for(&root)
+            .launch(EntryDialogInit {
+                title: "Add Saved Search".to_string(),
+                button_label: "Save".to_string(),
+            })
+            .forward(sender.input_sender(), |msg| match msg {
+                EntryDialogOutput::Text(query) => SidebarColumnInput::AddSavedSearch { query },
+            });
+        let edit_saved_search_dialog = EntryDialogModel::builder()
+            .transient_for(&root)
+            .launch(EntryDialogInit {
+                title: "Edit Saved Search".to_string(),
+                button_label: "Save".to_string(),
+            })
+            .forward(sender.input_sender(), |msg| match msg {
+                EntryDialogOutput::Text(query) => SidebarColumnInput::EditSavedSearch { query },
+            });
+        let import_dialog = ImportDialogModel::builder()
+            .transient_for(&root)
+            .launch(init.ctx.clone())
+            .forward(sender.input_sender(), |msg| match msg {
+                ImportDialogOutput::Imported => SidebarColumnInput::ReloadFolders,
+            });
+        let new_profile_dialog = EntryDialogModel::builder()
+            .transient_for(&root)
+            .launch(EntryDialogInit {
+                title: "Create Profile".to_string(),
+                button_label: "Create".to_string(),
+            })
+            .forward(sender.input_sender(), |msg| match msg {
+                EntryDialogOutput::Text(name) => SidebarColumnInput::SwitchProfile {
+                    profile: Some(name),
+                },
+            });
+        let mut note_events = init.ctx.note_events.subscribe();
+        let input_sender = sender.input_sender().clone();
+        relm4::spawn(async move {
+            loop {
+                let input = match note_events.recv().await {
+                    Ok(NoteEvent::Updated { id, .. } | NoteEvent::Changed { id }) => {
+                        SidebarColumnInput::LocalChange { id }
+                    }
+                    Ok(NoteEvent::FavouritesChanged) => SidebarColumnInput::ReloadFavourites,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                if input_sender.send(input).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let network_monitor = gio::NetworkMonitor::default();
+        let network_changed_handler = network_monitor.connect_network_changed({
+            let sender = sender.clone();
+            move |_, available| sender.input(SidebarColumnInput::NetworkChanged(available))
+        });
+
+        let mut model = SidebarColumnModel {
             folders,
+            favourites,
+            favourite_ids: favourites::Favourites::default(),
+            favourite_folder_action: gio::SimpleAction::new_stateful(
+                FavouriteFolderAction::NAME,
+                None,
+                &false.to_variant(),
+            ),
+            has_favourites: false,
+            saved_searches,
+            has_saved_searches: false,
             add_note_dialog,
+            add_saved_search_dialog,
+            edit_saved_search_dialog,
+            editing_saved_search: None,
+            import_dialog,
+            new_profile_dialog,
             ctx: init.ctx,
+            context_folder_index: None,
+            context_saved_search_index: None,
+            is_online: network_monitor.is_network_available(),
+            network_monitor,
+            network_changed_handler: Some(network_changed_handler),
+            sync_started: None,
+            sync_queued: false,
+            retry_delay: None,
+            retry_generation: 0,
+            sync_cancel: None,
+            is_cancelling_sync: false,
+            sync_failed: false,
+            pending_count: 0,
             is_syncing: false,
             tracker: 0,
         };
+        model.reload_saved_searches();
+        model.refresh_pending_count(&sender);
         sender.input(SidebarColumnInput::ReloadFolders);
 
         let folder_list_box = model.folders.widget();
+        let favourite_list_box = model.favourites.widget();
+        let saved_search_list_box = model.saved_searches.widget();
         let widgets = view_output!();
 
+        // ruslin-data doesn't report how far a sync got, so the bar only shows that it's running.
+        let sync_progress_bar = widgets.sync_progress_bar.clone();
+        glib::timeout_add_local(
+            Duration::from_millis(100),
+            glib::clone!(@weak sync_progress_bar => @default-return glib::Continue(false), move || {
+                if sync_progress_bar.is_mapped() {
+                    sync_progress_bar.pulse();
+                }
+                glib::Continue(true)
+            }),
+        );
+
+        let folder_list_box = folder_list_box.clone();
+        let context_menu = gtk::PopoverMenu::from_model(Some(&folder_context_menu));
+        context_menu.set_has_arrow(false);
+        context_menu.set_halign(gtk::Align::Start);
+        context_menu.set_parent(&folder_list_box);
+        let context_click = gtk::GestureClick::new();
+        context_click.set_button(gdk::BUTTON_SECONDARY);
+        context_click.connect_pressed(
+            glib::clone!(@weak folder_list_box, @strong sender => move |_, _, x, y| {
+                if let Some(row) = folder_list_box.row_at_y(y as i32) {
+                    sender.input(SidebarColumnInput::SetContextFolder(row.index() as usize));
+                    context_menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                    context_menu.popup();
+                }
+            }),
+        );
+        folder_list_box.add_controller(&context_click);
+
+        let drop_target = gtk::DropTarget::new(glib::Type::STRING, gdk::DragAction::MOVE);
+        drop_target.connect_motion(
+            glib::clone!(@weak folder_list_box => @default-return gdk::DragAction::empty(), move |_, _, y| {
+                match folder_list_box.row_at_y(y as i32) {
+                    Some(row) => {
+                        folder_list_box.drag_highlight_row(&row);
+                        gdk::DragAction::MOVE
+                    }
+                    None => {
+                        folder_list_box.drag_unhighlight_row();
+                        gdk::DragAction::empty()
+                    }
+                }
+            }),
+        );
+        drop_target.connect_leave(glib::clone!(@weak folder_list_box => move |_| {
+            folder_list_box.drag_unhighlight_row();
+        }));
+        drop_target.connect_drop(
+            glib::clone!(@weak folder_list_box, @strong sender => @default-return false, move |_, value, _, y| {
+                folder_list_box.drag_unhighlight_row();
+                let (Some(item), Some(row)) = (
+                    value.get::<String>().ok().and_then(|value| DraggedItem::from_drag_string(&value)),
+                    folder_list_box.row_at_y(y as i32),
+                ) else {
+                    return false;
+                };
+                let position = DropPosition::at(y - row.allocation().y() as f64, row.height() as f64);
+                sender.input(SidebarColumnInput::DropOnFolder {
+                    item,
+                    index: row.index() as usize,
+                    position,
+                });
+                true
+            }),
+        );
+        folder_list_box.add_controller(&drop_target);
+
+        let saved_search_list_box = saved_search_list_box.clone();
+        let saved_search_menu = gtk::PopoverMenu::from_model(Some(&saved_search_context_menu));
+        saved_search_menu.set_has_arrow(false);
+        saved_search_menu.set_halign(gtk::Align::Start);
+        saved_search_menu.set_parent(&saved_search_list_box);
+        let saved_search_click = gtk::GestureClick::new();
+        saved_search_click.set_button(gdk::BUTTON_SECONDARY);
+        saved_search_click.connect_pressed(
+            glib::clone!(@weak saved_search_list_box, @strong sender => move |_, _, x, y| {
+                if let Some(row) = saved_search_list_box.row_at_y(y as i32) {
+                    sender.input(SidebarColumnInput::SetContextSavedSearch(row.index() as usize));
+                    saved_search_menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                    saved_search_menu.popup();
+                }
+            }),
+        );
+        saved_search_list_box.add_controller(&saved_search_click);
+
+        let sidebar_actions = RelmActionGroup::<SidebarActionGroup>::new();
+        let export_folder_action: RelmAction<ExportFolderAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(SidebarColumnInput::ExportContextFolder);
+            })
+        };
+        let new_profile_action: RelmAction<NewProfileAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(SidebarColumnInput::ShowNewProfileDialog);
+            })
+        };
+        let switch_profile_action: RelmAction<SwitchProfileAction> = {
+            let sender = sender.clone();
+            RelmAction::new_with_target_value(move |_, profile: String| {
+                let profile = Some(profile).filter(|profile| !profile.is_empty());
+                sender.input(SidebarColumnInput::SwitchProfile { profile });
+            })
+        };
+        let edit_saved_search_action: RelmAction<EditSavedSearchAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(SidebarColumnInput::ShowEditContextSavedSearch);
+            })
+        };
+        let remove_saved_search_action: RelmAction<RemoveSavedSearchAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.input(SidebarColumnInput::RemoveContextSavedSearch);
+            })
+        };
+        sidebar_actions.add_action(&export_folder_action);
+        sidebar_actions.add_action(&edit_saved_search_action);
+        sidebar_actions.add_action(&remove_saved_search_action);
+        sidebar_actions.add_action(&new_profile_action);
+        sidebar_actions.add_action(&switch_profile_action);
+        let sidebar_actions = sidebar_actions.into_action_group();
+        // Added directly, its state changes with the folder the menu is opened on.
+        model.favourite_folder_action.connect_activate({
+            let sender = sender.clone();
+            move |action, _| {
+                let favourite = action.state().and_then(|state| state.get::<bool>());
+                sender.input(SidebarColumnInput::SetContextFolderFavourite(
+                    !favourite.unwrap_or_default(),
+                ));
+            }
+        });
+        sidebar_actions.add_action(&model.favourite_folder_action);
+        widgets
+            .main_view
+            .insert_action_group(SidebarActionGroup::NAME, Some(&sidebar_actions));
+
         let add_group = RelmActionGroup::<WindowActionGroup>::new();
-        let add_folder_action: RelmAction<AddFolderAction> = RelmAction::new_stateless(move |_| {
-            sender.input(SidebarColumnInput::ShowCreateFolderDialog);
+        let add_folder_action: RelmAction<AddFolderAction> =
+            RelmAction::new_stateless(glib::clone!(@strong sender => move |_| {
+                sender.input(SidebarColumnInput::ShowCreateFolderDialog);
+            }));
+        let add_saved_search_action: RelmAction<AddSavedSearchAction> =
+            RelmAction::new_stateless(glib::clone!(@strong sender => move |_| {
+                sender.input(SidebarColumnInput::ShowAddSavedSearchDialog);
+            }));
+        let import_action: RelmAction<ImportAction> = RelmAction::new_stateless(move |_| {
+            sender.input(SidebarColumnInput::ShowImportDialog);
         });
         add_group.add_action(&add_folder_action);
+        add_group.add_action(&add_saved_search_action);
+        add_group.add_action(&import_action);
         let add_actions = add_group.into_action_group();
         widgets
             .main_view
@@ -256,18 +869,76 @@ impl Component for SidebarColumnModel {
                     })
                     .unwrap();
             }
+            SidebarColumnInput::SelectSavedSearchIndex(index) => {
+                if let Some(item) = self.saved_searches.get(index as usize) {
+                    sender
+                        .output(SidebarColumnOutput::OpenSearch {
+                            query: item.query.clone(),
+                        })
+                        .unwrap();
+                }
+            }
             SidebarColumnInput::ShowCreateFolderDialog => {
                 self.add_note_dialog.emit(EntryDialogInput::Show);
             }
-            SidebarColumnInput::SyncRemote => {
-                self.set_is_syncing(true);
-                let data = self.ctx.data.clone();
-                sender.oneshot_command(async move {
-                    match data.synchronize(false).await {
-                        Ok(info) => SidebarColumnCommand::SyncSuccess(info),
-                        Err(e) => SidebarColumnCommand::ToastSyncError(e),
+            SidebarColumnInput::ShowAddSavedSearchDialog => {
+                self.add_saved_search_dialog.emit(EntryDialogInput::Show);
+            }
+            SidebarColumnInput::AddSavedSearch { query } => {
+                let query = query.trim();
+                if query.is_empty() {
+                    return;
+                }
+                if let Err(e) = saved_searches::add(&self.ctx, query) {
+                    log::warn!("save saved searches failed: {e}");
+                }
+                self.reload_saved_searches();
+            }
+            SidebarColumnInput::SetContextSavedSearch(index) => {
+                self.context_saved_search_index = Some(index);
+            }
+            SidebarColumnInput::ShowEditContextSavedSearch => {
+                if let Some(item) = self
+                    .context_saved_search_index
+                    .and_then(|index| self.saved_searches.get(index))
+                {
+                    self.editing_saved_search = Some(item.query.clone());
+                    self.edit_saved_search_dialog
+                        .emit(EntryDialogInput::ShowWithText(item.query.clone()));
+                }
+            }
+            SidebarColumnInput::EditSavedSearch { query } => {
+                let query = query.trim();
+                let Some(old) = self.editing_saved_search.take() else {
+                    return;
+                };
+                if query.is_empty() {
+                    return;
+                }
+                if let Err(e) = saved_searches::replace(&self.ctx, &old, query) {
+                    log::warn!("save saved searches failed: {e}");
+                }
+                self.reload_saved_searches();
+            }
+            SidebarColumnInput::RemoveContextSavedSearch => {
+                if let Some(item) = self
+                    .context_saved_search_index
+                    .and_then(|index| self.saved_searches.get(index))
+                {
+                    if let Err(e) = saved_searches::remove(&self.ctx, &item.query) {
+                        log::warn!("save saved searches failed: {e}");
                     }
-                });
+                }
+                self.reload_saved_searches();
+            }
+            SidebarColumnInput::ShowImportDialog => {
+                self.import_dialog.emit(ImportDialogInput::Show);
+            }
+            SidebarColumnInput::SyncRemote => {
+                self.start_sync(false, &sender);
+            }
+            SidebarColumnInput::SyncFromScratch => {
+                self.start_sync(true, &sender);
             }
             SidebarColumnInput::InsertFolder { title } => {
                 let data = self.ctx.data.clone();
@@ -283,6 +954,109 @@ impl Component for SidebarColumnModel {
                     Err(e) => SidebarColumnCommand::ToastError(e),
                 })
             }
+            SidebarColumnInput::SetContextFolder(index) => {
+                self.context_folder_index = Some(index);
+                let favourite = self.folders.get(index).map_or(false, |item| {
+                    self.favourite_ids.folders.contains(&item.folder.id)
+                });
+                self.favourite_folder_action
+                    .set_state(&favourite.to_variant());
+            }
+            SidebarColumnInput::DropOnFolder {
+                item,
+                index,
+                position,
+            } => {
+                let Some(target_id) = self.folders.get(index).map(|item| item.folder.id.clone())
+                else {
+                    return;
+                };
+                match item {
+                    DraggedItem::Note(id) => self.move_note(&id, target_id),
+                    DraggedItem::Folder(id) => self.move_folder(&id, &target_id, position, &sender),
+                }
+            }
+            SidebarColumnInput::SetContextFolderFavourite(favourite) => {
+                if let Some(item) = self
+                    .context_folder_index
+                    .and_then(|index| self.folders.get(index))
+                {
+                    if let Err(e) =
+                        favourites::set_folder_favourite(&self.ctx, &item.folder.id, favourite)
+                    {
+                        log::warn!("save favourite folders failed: {e}");
+                    }
+                }
+            }
+            SidebarColumnInput::ReloadFavourites => {
+                self.reload_favourites();
+            }
+            SidebarColumnInput::ActivateFavourite(index) => {
+                match self.favourites.get(index).map(|item| &item.favourite) {
+                    Some(Favourite::Note { id, .. }) => {
+                        sender
+                            .output(SidebarColumnOutput::OpenNote { id: id.clone() })
+                            .unwrap();
+                    }
+                    Some(Favourite::Folder { id, .. }) => {
+                        let folder_index = (0..self.folders.len())
+                            .find(|&index| &self.folders.get(index).unwrap().folder.id == id);
+                        let folder_list_box = self.folders.widget();
+                        if let Some(row) = folder_index
+                            .and_then(|index| folder_list_box.row_at_index(index as i32))
+                        {
+                            folder_list_box.select_row(Some(&row));
+                        }
+                    }
+                    None => {}
+                }
+            }
+            SidebarColumnInput::ExportContextFolder => {
+                if let Some(item) = self
+                    .context_folder_index
+                    .and_then(|index| self.folders.get(index))
+                {
+                    sender
+                        .output(SidebarColumnOutput::ExportFolder {
+                            folder_id: item.folder.id.clone(),
+                            title: item.folder.title.clone(),
+                        })
+                        .unwrap();
+                }
+            }
+            SidebarColumnInput::ShowNewProfileDialog => {
+                self.new_profile_dialog.emit(EntryDialogInput::Show);
+            }
+            SidebarColumnInput::SwitchProfile { profile } => {
+                // The app checks the name, and says what is wrong with it.
+                sender
+                    .output(SidebarColumnOutput::SwitchProfile { profile })
+                    .unwrap();
+            }
+            SidebarColumnInput::LocalChange { id } => {
+                if self.favourite_ids.notes.contains(&id) {
+                    self.reload_favourites();
+                }
+                self.refresh_pending_count(&sender);
+            }
+            SidebarColumnInput::CancelSync => {
+                if let Some(cancel) = &self.sync_cancel {
+                    cancel.cancel();
+                    self.set_is_cancelling_sync(true);
+                }
+            }
+            SidebarColumnInput::NetworkChanged(available) => {
+                if available == self.is_online {
+                    return;
+                }
+                self.set_is_online(available);
+                let has_pending = self.sync_queued || self.pending_count > 0;
+                if sync_on_reconnect(available, has_pending, self.sync_failed)
+                    && self.ctx.data.sync_exists()
+                {
+                    sender.input(SidebarColumnInput::SyncRemote);
+                }
+            }
         }
     }
 
@@ -296,25 +1070,411 @@ impl Component for SidebarColumnModel {
         match message {
             SidebarColumnCommand::SyncSuccess(_) => {
                 self.set_is_syncing(false);
+                self.sync_cancel = None;
+                if let Some(started) = self.sync_started.take() {
+                    if let Err(e) =
+                        pending_changes::save_last_sync(self.ctx.profile.as_deref(), &started)
+                    {
+                        log::warn!("save last sync time failed: {e}");
+                    }
+                }
+                self.retry_delay = None;
+                self.set_sync_failed(false);
+                self.refresh_pending_count(&sender);
                 sender.input(SidebarColumnInput::ReloadFolders);
             }
             SidebarColumnCommand::ReloadFolders(folders) => {
+                let folders = folder_order::tree(folders, &folder_order::load(&self.ctx));
+                let folder_ids: Vec<String> = folders
+                    .iter()
+                    .map(|(folder, _)| folder.id.clone())
+                    .collect();
                 let mut folders_guard = self.folders.guard();
                 folders_guard.clear();
                 for folder in folders.into_iter() {
                     folders_guard.push_back(folder);
                 }
+                drop(folders_guard);
+                for (index, id) in folder_ids.into_iter().enumerate() {
+                    super::add_row_drag_source(
+                        self.folders.widget(),
+                        index,
+                        DraggedItem::Folder(id),
+                    );
+                }
+                self.reload_favourites();
             }
             SidebarColumnCommand::AddedFolder => {
+                self.refresh_pending_count(&sender);
                 sender.input(SidebarColumnInput::ReloadFolders);
             }
             SidebarColumnCommand::ToastError(e) => {
                 todo!("{e}")
             }
-            SidebarColumnCommand::ToastSyncError(_e) => {
+            SidebarColumnCommand::SyncFailed {
+                error,
+                server_reachable,
+            } => {
                 self.set_is_syncing(false);
-                // todo!("{e}")
+                self.sync_cancel = None;
+                self.sync_started = None;
+                log::warn!("sync failed: {error:?}");
+                let network_available = self.network_monitor.is_network_available();
+                match after_sync_failure(network_available, server_reachable, self.retry_delay) {
+                    AfterSyncFailure::WaitForNetwork => {
+                        self.set_is_online(false);
+                        self.sync_queued = true;
+                    }
+                    AfterSyncFailure::Retry(delay) => {
+                        self.retry_delay = Some(delay);
+                        let generation = self.retry_generation;
+                        sender.oneshot_command(async move {
+                            tokio::time::sleep(delay).await;
+                            SidebarColumnCommand::RetrySync { generation }
+                        });
+                    }
+                    AfterSyncFailure::Stop => {
+                        self.retry_delay = None;
+                        self.set_sync_failed(true);
+                    }
+                }
+            }
+            SidebarColumnCommand::RetrySync { generation } => {
+                if generation == self.retry_generation {
+                    sender.input(SidebarColumnInput::SyncRemote);
+                }
+            }
+            SidebarColumnCommand::SyncCancelled => {
+                self.set_is_syncing(false);
+                self.sync_cancel = None;
+                self.sync_started = None;
+                sender.input(SidebarColumnInput::ReloadFolders);
+            }
+            SidebarColumnCommand::PendingCount(count) => {
+                self.set_pending_count(count);
+            }
+        }
+    }
+
+    fn shutdown(&mut self, _widgets: &mut Self::Widgets, _output: relm4::Sender<Self::Output>) {
+        // Also reached when quitting, so a running sync doesn't outlive the window.
+        if let Some(cancel) = self.sync_cancel.take() {
+            cancel.cancel();
+        }
+        if let Some(handler) = self.network_changed_handler.take() {
+            self.network_monitor.disconnect(handler);
+        }
+    }
+}
+
+impl SidebarColumnModel {
+    // Folder titles come from the loaded folders, so this runs after every folder reload.
+    fn reload_favourites(&mut self) {
+        // Both lists have to be read, a failed read isn't taken for deleted items.
+        let db = &self.ctx.data.db;
+        if let (Ok(notes), Ok(folders)) = (db.load_abbr_notes(None), db.load_folders()) {
+            let note_ids = notes.into_iter().map(|note| note.id).collect();
+            let folder_ids = folders.into_iter().map(|folder| folder.id).collect();
+            if let Err(e) = favourites::forget_missing(&self.ctx, &note_ids, &folder_ids) {
+                log::warn!("forget deleted favourites failed: {e}");
+            }
+        }
+        self.favourite_ids = favourites::load(&self.ctx);
+        let mut favourites_guard = self.favourites.guard();
+        favourites_guard.clear();
+        for id in &self.favourite_ids.folders {
+            let folder = (0..self.folders.len())
+                .map(|index| &self.folders.get(index).unwrap().folder)
+                .find(|folder| &folder.id == id);
+            if let Some(folder) = folder {
+                favourites_guard.push_back(Favourite::Folder {
+                    id: folder.id.clone(),
+                    title: folder.title.clone(),
+                });
+            }
+        }
+        for id in &self.favourite_ids.notes {
+            if let Ok(note) = self.ctx.data.db.load_note(id) {
+                favourites_guard.push_back(Favourite::Note {
+                    id: note.id.clone(),
+                    title: note.get_title().to_string(),
+                });
+            }
+        }
+        let has_favourites = !favourites_guard.is_empty();
+        drop(favourites_guard);
+        self.set_has_favourites(has_favourites);
+    }
+
+    // Reparents the folder through the db, so the move syncs, and keeps its place among the
+    // shown folders.
+    fn move_folder(
+        &self,
+        id: &str,
+        target_id: &str,
+        position: DropPosition,
+        sender: &ComponentSender<Self>,
+    ) {
+        let folders = match self.ctx.data.db.load_folders() {
+            Ok(folders) => folders,
+            Err(e) => {
+                log::warn!("load folders failed: {e:?}");
+                return;
+            }
+        };
+        let shown: Vec<String> = (0..self.folders.len())
+            .map(|index| self.folders.get(index).unwrap().folder.id.clone())
+            .collect();
+        let Some((parent_id, order)) =
+            folder_order::drop_folder(&folders, &shown, id, target_id, position)
+        else {
+            log::info!("folder {id} not moved into itself");
+            return;
+        };
+        if let Some(mut folder) = folders
+            .into_iter()
+            .find(|folder| folder.id == id && folder.parent_id != parent_id)
+        {
+            folder.parent_id = parent_id;
+            if let Err(e) = self.ctx.data.db.replace_folder(&folder) {
+                log::warn!("move folder failed: {e:?}");
+                return;
+            }
+            self.refresh_pending_count(sender);
+        }
+        if let Err(e) = folder_order::save(&self.ctx, &order) {
+            log::warn!("save folder order failed: {e}");
+        }
+        sender.input(SidebarColumnInput::ReloadFolders);
+    }
+
+    fn move_note(&self, id: &str, folder_id: String) {
+        let mut note = match self.ctx.data.db.load_note(id) {
+            Ok(note) => note,
+            Err(e) => {
+                log::warn!("load dropped note failed: {e:?}");
+                return;
+            }
+        };
+        if note.parent_id.as_ref() == Some(&folder_id) {
+            return;
+        }
+        note.parent_id = Some(folder_id);
+        if let Err(e) = self
+            .ctx
+            .data
+            .db
+            .replace_note(&note, UpdateSource::LocalEdit)
+        {
+            log::warn!("move note failed: {e:?}");
+            return;
+        }
+        capture::notify_changed(&self.ctx, note.id);
+    }
+
+    fn reload_saved_searches(&mut self) {
+        let mut saved_searches_guard = self.saved_searches.guard();
+        saved_searches_guard.clear();
+        for query in saved_searches::load(&self.ctx) {
+            saved_searches_guard.push_back(query);
+        }
+        let has_saved_searches = !saved_searches_guard.is_empty();
+        drop(saved_searches_guard);
+        self.set_has_saved_searches(has_saved_searches);
+    }
+
+    fn start_sync(&mut self, from_scratch: bool, sender: &ComponentSender<Self>) {
+        if self.is_syncing {
+            return;
+        }
+        if !self.is_online {
+            self.sync_queued = true;
+            return;
+        }
+        self.sync_queued = false;
+        self.retry_generation = self.retry_generation.wrapping_add(1);
+        self.set_sync_failed(false);
+        self.set_is_syncing(true);
+        self.sync_started = Some(pending_changes::now());
+        let data = self.ctx.data.clone();
+        let server = sync_check::load_server(self.ctx.profile.as_deref());
+        let cancel = CancelFlag::default();
+        self.sync_cancel = Some(cancel.clone());
+        self.set_is_cancelling_sync(false);
+        sender.oneshot_command(async move {
+            // What was synced before the flag was seen is kept.
+            match cancel::until_cancelled(&cancel, data.synchronize(from_scratch)).await {
+                None => SidebarColumnCommand::SyncCancelled,
+                Some(Ok(info)) => SidebarColumnCommand::SyncSuccess(info),
+                Some(Err(error)) => SidebarColumnCommand::SyncFailed {
+                    error,
+                    server_reachable: server_reachable(server).await,
+                },
+            }
+        });
+    }
+
+    // Counted from the database, so the count survives restarts and changes made elsewhere.
+    fn refresh_pending_count(&self, sender: &ComponentSender<Self>) {
+        let ctx = self.ctx.clone();
+        sender.spawn_oneshot_command(move || match pending_changes::count(&ctx) {
+            Ok(count) => SidebarColumnCommand::PendingCount(count),
+            Err(e) => SidebarColumnCommand::ToastError(e),
+        });
+    }
+}
+
+// Lists the default profile first, the others as found on disk.
+fn profile_menu() -> gio::Menu {
+    let profiles = gio::Menu::new();
+    let default_item = gio::MenuItem::new(Some("Default"), None);
+    default_item.set_action_and_target_value(
+        Some(&SwitchProfileAction::action_name()),
+        Some(&"".to_variant()),
+    );
+    profiles.append_item(&default_item);
+    for profile in data_dir::profiles() {
+        let item = gio::MenuItem::new(Some(&profile), None);
+        item.set_action_and_target_value(
+            Some(&SwitchProfileAction::action_name()),
+            Some(&profile.to_variant()),
+        );
+        profiles.append_item(&item);
+    }
+
+    let menu = gio::Menu::new();
+    menu.append_section(None, &profiles);
+    let new_profile = gio::Menu::new();
+    new_profile.append(Some("New Profile…"), Some(&NewProfileAction::action_name()));
+    menu.append_section(None, &new_profile);
+    menu
+}
+
+// ruslin-data's sync errors don't say whether they came from the network, so a failed sync asks
+// the server itself. One that answers refused the sync, which retrying won't change.
+async fn server_reachable(server: Option<(String, String)>) -> bool {
+    match server {
+        Some((host, _)) => sync_check::ping(&reqwest::Client::new(), &host)
+            .await
+            .is_ok(),
+        None => false,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AfterSyncFailure {
+    WaitForNetwork,
+    Retry(Duration),
+    Stop,
+}
+
+fn after_sync_failure(
+    network_available: bool,
+    server_reachable: bool,
+    retry_delay: Option<Duration>,
+) -> AfterSyncFailure {
+    if !network_available {
+        AfterSyncFailure::WaitForNetwork
+    } else if server_reachable {
+        AfterSyncFailure::Stop
+    } else {
+        AfterSyncFailure::Retry(
+            retry_delay.map_or(FIRST_RETRY_DELAY, |delay| (delay * 2).min(MAX_RETRY_DELAY)),
+        )
+    }
+}
+
+// A sync the server refused waits for the user to sync again.
+fn sync_on_reconnect(available: bool, has_pending: bool, sync_failed: bool) -> bool {
+    available && has_pending && !sync_failed
+}
+
+// ruslin-data doesn't report how far a sync got, the local changes it has to upload are known.
+fn sync_progress(is_cancelling: bool, pending_count: usize) -> String {
+    match (is_cancelling, pending_count) {
+        (true, _) => "Cancelling after the current item…".to_string(),
+        (false, 0) => "Syncing…".to_string(),
+        (false, 1) => "Syncing 1 local change…".to_string(),
+        (false, count) => format!("Syncing {count} local changes…"),
+    }
+}
+
+fn sync_status(is_online: bool, sync_failed: bool, pending_count: usize) -> String {
+    let pending = match pending_count {
+        0 => None,
+        1 => Some("1 change waiting to sync".to_string()),
+        count => Some(format!("{count} changes waiting to sync")),
+    };
+    let state = match (is_online, sync_failed) {
+        (false, _) => Some("Offline"),
+        (true, true) => Some("Sync failed, check the sync settings"),
+        (true, false) => None,
+    };
+    match (state, pending) {
+        (Some(state), Some(pending)) => format!("{state}, {pending}"),
+        (Some(state), None) => state.to_string(),
+        (None, pending) => pending.unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_names_the_state_and_the_pending_changes() {
+        assert_eq!(sync_status(true, false, 0), "");
+        assert_eq!(sync_status(true, false, 1), "1 change waiting to sync");
+        assert_eq!(sync_status(false, false, 0), "Offline");
+        assert_eq!(
+            sync_status(false, true, 3),
+            "Offline, 3 changes waiting to sync"
+        );
+        assert_eq!(
+            sync_status(true, true, 2),
+            "Sync failed, check the sync settings, 2 changes waiting to sync"
+        );
+    }
+
+    #[test]
+    fn progress_names_the_changes_to_upload() {
+        assert_eq!(sync_progress(false, 0), "Syncing…");
+        assert_eq!(sync_progress(false, 1), "Syncing 1 local change…");
+        assert_eq!(sync_progress(false, 12), "Syncing 12 local changes…");
+        assert_eq!(
+            sync_progress(true, 12),
+            "Cancelling after the current item…"
+        );
+    }
+
+    #[test]
+    fn failures_without_network_wait_for_it() {
+        assert_eq!(
+            after_sync_failure(false, false, Some(FIRST_RETRY_DELAY)),
+            AfterSyncFailure::WaitForNetwork
+        );
+    }
+
+    #[test]
+    fn refused_syncs_are_not_retried() {
+        assert_eq!(after_sync_failure(true, true, None), AfterSyncFailure::Stop);
+        assert!(!sync_on_reconnect(true, true, true));
+        assert!(sync_on_reconnect(true, true, false));
+        assert!(!sync_on_reconnect(true, false, false));
+        assert!(!sync_on_reconnect(false, true, false));
+    }
+
+    #[test]
+    fn unreachable_servers_are_retried_with_growing_delays() {
+        let mut delay = None;
+        let mut delays = Vec::new();
+        for _ in 0..8 {
+            match after_sync_failure(true, false, delay) {
+                AfterSyncFailure::Retry(next) => delay = Some(next),
+                other => panic!("expected a retry, got {other:?}"),
             }
+            delays.push(delay.unwrap().as_secs());
         }
+        assert_eq!(delays, [30, 60, 120, 240, 480, 600, 600, 600]);
     }
 }
@@ -0,0 +1,302 @@
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+pub const DATA_DIR_ENV: &str = "RUSLIN_DATA_DIR";
+
+// What ruslin-data keeps in its data directory. Older versions put these straight into
+// `~/.local/share`, they are moved into the app's own directory on the first start.
+const LEGACY_DATABASE: &str = "database.sqlite";
+const LEGACY_SYNC_CONFIG: &str = "sync_config.json";
+const LEGACY_RESOURCES: &str = "resources";
+// SQLite keeps uncommitted and not yet checkpointed changes next to the database, they belong
+// to it and move with it.
+const SQLITE_SIDECARS: [&str; 3] = ["-wal", "-shm", "-journal"];
+// Left in the new directory once the legacy database was found there, so entries that failed
+// to move are retried on the next start. Removed once nothing is left to move.
+const MIGRATION_MARKER: &str = ".legacy-data-migration";
+
+pub struct DataDir {
+    pub path: PathBuf,
+    // `None` is the default profile.
+    pub profile: Option<String>,
+    // Set when `--profile` or `RUSLIN_DATA_DIR` chose the directory for this run.
+    pub is_override: bool,
+}
+
+// `RUSLIN_DATA_DIR`, then `--profile`, then the profile that was open last.
+//
+// The profile has to be known before the GApplication exists, so `--profile` is read from the
+// arguments here, `cli` only registers it for `--help` and to keep GOption from rejecting it.
+pub fn resolve(last_profile: Option<String>) -> io::Result<DataDir> {
+    if let Some(path) = env::var_os(DATA_DIR_ENV).filter(|path| !path.is_empty()) {
+        let path = PathBuf::from(path);
+        fs::create_dir_all(&path)?;
+        return Ok(DataDir {
+            path,
+            profile: None,
+            is_override: true,
+        });
+    }
+    match profile_arg() {
+        Some(profile) => Ok(DataDir {
+            is_override: true,
+            ..open_profile(Some(&profile))?
+        }),
+        None => open_profile(last_profile.as_deref()),
+    }
+}
+
+// `~/.local/share/ruslin` (or the Flatpak equivalent), a profile lives in `profiles/<name>` below it.
+pub fn open_profile(profile: Option<&str>) -> io::Result<DataDir> {
+    let base = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user data directory"))?;
+    let app_dir = base.join("ruslin");
+    let path = match profile {
+        Some(profile) => {
+            if !is_valid_profile_name(profile) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid profile name “{profile}”"),
+                ));
+            }
+            app_dir.join("profiles").join(profile)
+        }
+        None => app_dir.clone(),
+    };
+    fs::create_dir_all(&path)?;
+    if profile.is_none() {
+        migrate_legacy_data(&base, &app_dir)?;
+    }
+    Ok(DataDir {
+        path,
+        profile: profile.map(str::to_string),
+        is_override: false,
+    })
+}
+
+pub fn is_valid_profile_name(profile: &str) -> bool {
+    !profile.trim().is_empty() && !profile.contains(['/', '\\']) && !profile.starts_with('.')
+}
+
+// The named profiles, sorted, without the default one.
+pub fn profiles() -> Vec<String> {
+    let Some(base) = dirs::data_dir() else {
+        return Vec::new();
+    };
+    let mut profiles: Vec<String> = fs::read_dir(base.join("ruslin").join("profiles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    profiles.sort();
+    profiles
+}
+
+fn profile_arg() -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if let Some(profile) = arg.strip_prefix("--profile=") {
+            return Some(profile.to_string());
+        }
+        if arg == "--profile" {
+            return args.next();
+        }
+    }
+    None
+}
+
+// The names are generic, so each entry is only moved once it looks like ruslin's. The
+// database goes first, the rest only follows a database that came from the old directory.
+fn migrate_legacy_data(old_dir: &Path, new_dir: &Path) -> io::Result<()> {
+    let marker = new_dir.join(MIGRATION_MARKER);
+    if !marker.exists() {
+        if new_dir.join(LEGACY_DATABASE).exists()
+            || !is_ruslin_database(&old_dir.join(LEGACY_DATABASE))
+        {
+            return Ok(());
+        }
+        fs::write(&marker, "")?;
+    }
+    // Without its database ruslin-data would start over with an empty one, so this has to work.
+    move_database(old_dir, new_dir)?;
+    for (entry, is_ours) in [
+        (LEGACY_SYNC_CONFIG, is_json_object as fn(&Path) -> bool),
+        (LEGACY_RESOURCES, is_resource_dir),
+    ] {
+        if let Err(e) = move_entry(old_dir, new_dir, entry, is_ours) {
+            log::warn!("moving {entry} failed, retrying on the next start: {e}");
+        }
+    }
+    let left = legacy_entries()
+        .into_iter()
+        .any(|entry| old_dir.join(&entry).exists() && !new_dir.join(&entry).exists());
+    if !left {
+        fs::remove_file(&marker)?;
+    }
+    Ok(())
+}
+
+fn legacy_entries() -> Vec<String> {
+    let mut entries: Vec<String> = SQLITE_SIDECARS
+        .iter()
+        .map(|suffix| format!("{LEGACY_DATABASE}{suffix}"))
+        .collect();
+    entries.extend([LEGACY_DATABASE, LEGACY_SYNC_CONFIG, LEGACY_RESOURCES].map(str::to_string));
+    entries
+}
+
+// The sidecars go first, a database that moved without them would lose their changes. A
+// failed move leaves the database behind, so the next start tries again.
+fn move_database(old_dir: &Path, new_dir: &Path) -> io::Result<()> {
+    let old_database = old_dir.join(LEGACY_DATABASE);
+    if !old_database.exists()
+        || new_dir.join(LEGACY_DATABASE).exists()
+        || !is_ruslin_database(&old_database)
+    {
+        return Ok(());
+    }
+    for suffix in SQLITE_SIDECARS {
+        move_entry(
+            old_dir,
+            new_dir,
+            &format!("{LEGACY_DATABASE}{suffix}"),
+            |_| true,
+        )?;
+    }
+    move_entry(old_dir, new_dir, LEGACY_DATABASE, is_ruslin_database)
+}
+
+fn move_entry(
+    old_dir: &Path,
+    new_dir: &Path,
+    entry: &str,
+    is_ours: fn(&Path) -> bool,
+) -> io::Result<()> {
+    let old_path = old_dir.join(entry);
+    let new_path = new_dir.join(entry);
+    if !old_path.exists() || new_path.exists() {
+        return Ok(());
+    }
+    if !is_ours(&old_path) {
+        log::info!("leaving {}, it isn't ruslin's", old_path.display());
+        return Ok(());
+    }
+    log::info!("moving {} to {}", old_path.display(), new_path.display());
+    fs::rename(&old_path, &new_path)
+}
+
+// The schema is stored at the start of the file, there is no need to read all of it.
+fn is_ruslin_database(path: &Path) -> bool {
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(1 << 20).read_to_end(&mut bytes))
+        .map_or(false, |_| looks_like_ruslin_database(&bytes))
+}
+
+// An SQLite file whose schema has the Joplin tables ruslin-data creates.
+fn looks_like_ruslin_database(bytes: &[u8]) -> bool {
+    let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|window| window == needle);
+    bytes.starts_with(b"SQLite format 3\0")
+        && contains(b"CREATE TABLE")
+        && contains(b"notes")
+        && contains(b"folders")
+}
+
+fn is_json_object(path: &Path) -> bool {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .map_or(false, |value| value.is_object())
+}
+
+// Resource files are named by their item id.
+fn is_resource_dir(path: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(path) else {
+        return false;
+    };
+    entries.flatten().all(|entry| {
+        entry
+            .path()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map_or(false, is_item_id)
+    })
+}
+
+fn is_item_id(name: &str) -> bool {
+    name.len() == 32 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_ruslin_databases() {
+        let mut database = b"SQLite format 3\0".to_vec();
+        database
+            .extend_from_slice(b"...CREATE TABLE notes (id TEXT)...CREATE TABLE folders (id TEXT)");
+        assert!(looks_like_ruslin_database(&database));
+        assert!(!looks_like_ruslin_database(
+            b"SQLite format 3\0 CREATE TABLE moz_places (url TEXT)"
+        ));
+        assert!(!looks_like_ruslin_database(b"notes folders CREATE TABLE"));
+    }
+
+    #[test]
+    fn recognizes_item_ids() {
+        assert!(is_item_id("0123456789abcdef0123456789abcdef"));
+        assert!(!is_item_id("wallpaper"));
+        assert!(!is_item_id("0123456789abcdef0123456789abcdeg"));
+    }
+
+    #[test]
+    fn moves_each_entry_once_it_is_ours() {
+        let root = env::temp_dir().join(format!("ruslin-migration-{}", std::process::id()));
+        let (old_dir, new_dir) = (root.join("old"), root.join("new"));
+        fs::create_dir_all(old_dir.join(LEGACY_RESOURCES)).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        let mut database = b"SQLite format 3\0".to_vec();
+        database.extend_from_slice(b"CREATE TABLE notes; CREATE TABLE folders;");
+        fs::write(old_dir.join(LEGACY_DATABASE), &database).unwrap();
+        fs::write(old_dir.join(format!("{LEGACY_DATABASE}-wal")), "").unwrap();
+        fs::write(old_dir.join(LEGACY_SYNC_CONFIG), "not json").unwrap();
+        fs::write(
+            old_dir
+                .join(LEGACY_RESOURCES)
+                .join("0123456789abcdef0123456789abcdef.png"),
+            "",
+        )
+        .unwrap();
+
+        migrate_legacy_data(&old_dir, &new_dir).unwrap();
+        assert!(new_dir.join(LEGACY_DATABASE).exists());
+        assert!(new_dir.join(format!("{LEGACY_DATABASE}-wal")).exists());
+        assert!(!old_dir.join(format!("{LEGACY_DATABASE}-wal")).exists());
+        assert!(new_dir.join(LEGACY_RESOURCES).exists());
+        assert!(old_dir.join(LEGACY_SYNC_CONFIG).exists());
+        assert!(new_dir.join(MIGRATION_MARKER).exists());
+
+        // Fixed up later, it moves on the next start and the migration is done.
+        fs::write(old_dir.join(LEGACY_SYNC_CONFIG), "{}").unwrap();
+        migrate_legacy_data(&old_dir, &new_dir).unwrap();
+        assert!(new_dir.join(LEGACY_SYNC_CONFIG).exists());
+        assert!(!new_dir.join(MIGRATION_MARKER).exists());
+
+        // A database that came back in the old place isn't taken again.
+        fs::write(old_dir.join(LEGACY_DATABASE), &database).unwrap();
+        migrate_legacy_data(&old_dir, &new_dir).unwrap();
+        assert!(old_dir.join(LEGACY_DATABASE).exists());
+        assert!(!new_dir.join(MIGRATION_MARKER).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
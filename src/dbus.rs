@@ -0,0 +1,45 @@
+use gtk::{gio, glib};
+use relm4::gtk;
+
+pub const FAILED: &str = "org.dianqk.ruslin.Error.Failed";
+pub const NOT_FOUND: &str = "org.dianqk.ruslin.Error.NotFound";
+
+// The database work runs on a worker thread, the reply is sent from the main thread once done.
+pub fn reply_in_background<F>(invocation: gio::DBusMethodInvocation, reply: F)
+where
+    F: FnOnce() -> Result<Option<glib::Variant>, (&'static str, String)> + Send + 'static,
+{
+    relm4::spawn_local(async move {
+        match relm4::spawn_blocking(reply).await {
+            Ok(Ok(value)) => invocation.return_value(value.as_ref()),
+            Ok(Err((name, message))) => invocation.return_dbus_error(name, &message),
+            Err(e) => invocation.return_dbus_error(FAILED, &e.to_string()),
+        }
+    });
+}
+
+pub fn invalid_args(invocation: gio::DBusMethodInvocation, method: &str) {
+    invocation.return_dbus_error(
+        "org.freedesktop.DBus.Error.InvalidArgs",
+        &format!("invalid arguments for {method}"),
+    );
+}
+
+pub fn unknown_method(invocation: gio::DBusMethodInvocation, method: &str) {
+    invocation.return_dbus_error(
+        "org.freedesktop.DBus.Error.UnknownMethod",
+        &format!("no method {method}"),
+    );
+}
+
+// Neither interface declares properties, so GDBus answers property calls itself. These only
+// keep a call that gets through from taking the application down.
+pub fn get_property(property: &str) -> glib::Variant {
+    log::warn!("unknown D-Bus property {property}");
+    glib::ToVariant::to_variant(&())
+}
+
+pub fn set_property(property: &str) -> bool {
+    log::warn!("unknown D-Bus property {property}");
+    false
+}
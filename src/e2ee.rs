@@ -0,0 +1,60 @@
+use serde_json::{json, Value};
+
+// Joplin keeps the sync target settings in info.json at the root of the target,
+// each setting wrapped as {"value": ..., "updatedTime": ...}.
+pub fn e2ee_enabled(info: &Value) -> bool {
+    info["e2ee"]["value"].as_bool().unwrap_or(false)
+}
+
+// Signs in to Joplin Server and reads info.json. A target that has never been
+// synced has no info.json and isn't encrypted.
+pub async fn target_is_encrypted(
+    host: &str,
+    email: &str,
+    password: &str,
+) -> Result<bool, reqwest::Error> {
+    let host = host.trim_end_matches('/');
+    let client = reqwest::Client::new();
+    let session: Value = client
+        .post(format!("{host}/api/sessions"))
+        .json(&json!({ "email": email, "password": password }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let session_id = session["id"].as_str().unwrap_or_default();
+    let response = client
+        .get(format!("{host}/api/items/root:/info.json:/content"))
+        .header("X-API-AUTH", session_id)
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    let info: Value = response.error_for_status()?.json().await?;
+    Ok(e2ee_enabled(&info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_e2ee_setting() {
+        let info = json!({
+            "version": 3,
+            "e2ee": { "value": true, "updatedTime": 1670000000000u64 },
+            "masterKeys": [],
+        });
+        assert!(e2ee_enabled(&info));
+        let info = json!({ "version": 3, "e2ee": { "value": false, "updatedTime": 0 } });
+        assert!(!e2ee_enabled(&info));
+    }
+
+    #[test]
+    fn missing_setting_is_unencrypted() {
+        assert!(!e2ee_enabled(&json!({ "version": 3 })));
+        assert!(!e2ee_enabled(&json!({})));
+    }
+}
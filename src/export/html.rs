@@ -0,0 +1,86 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pulldown_cmark::{escape::escape_html, html};
+
+use super::{
+    file_name, mime_type, unique_name, url_path, ExportItems, ExportReport, ExportResult, Resources,
+};
+use crate::markdown as md;
+
+const STYLE: &str = "body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+img { max-width: 100%; }
+pre, code { background: #f4f4f4; border-radius: 4px; }
+pre { padding: 0.5em; overflow-x: auto; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+blockquote { margin-left: 0; padding-left: 1em; border-left: 4px solid #ccc; color: #555; }";
+
+// A single note is written to `dest`, several notes become files in the `dest` directory.
+// Resources are embedded as data URLs so every page stands on its own.
+pub(super) fn export(
+    items: &ExportItems,
+    resources: &Resources,
+    dest: &Path,
+) -> ExportResult<ExportReport> {
+    let mut report = ExportReport::default();
+    let single = items.notes.len() == 1;
+    let mut used_names = Vec::new();
+    let note_names: HashMap<&str, String> = items
+        .notes
+        .iter()
+        .map(|export_note| {
+            let note = &export_note.note;
+            let name = match dest.file_name() {
+                Some(name) if single => name.to_string_lossy().to_string(),
+                _ => unique_name(&mut used_names, file_name(&note.get_title()), "html"),
+            };
+            (note.id.as_str(), name)
+        })
+        .collect();
+    if !single {
+        fs::create_dir_all(dest)?;
+    }
+
+    let mut data_urls: HashMap<&str, String> = HashMap::new();
+    for export_note in items.notes.iter() {
+        let note = &export_note.note;
+        for (id, resource) in resources.linked_by(&note.body) {
+            if !data_urls.contains_key(id) {
+                let bytes = fs::read(resource)?;
+                let data_url = format!(
+                    "data:{};base64,{}",
+                    mime_type(resource),
+                    STANDARD.encode(bytes)
+                );
+                data_urls.insert(id, data_url);
+                report.resources += 1;
+            }
+        }
+        let body = md::replace_item_links(&note.body, |id| match note_names.get(id) {
+            Some(name) => Some(url_path(name)),
+            None => data_urls.get(id).cloned(),
+        });
+
+        let title = note.get_title();
+        let mut page =
+            String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+        escape_html(&mut page, &title)?;
+        page.push_str("</title>\n<style>\n");
+        page.push_str(STYLE);
+        page.push_str("\n</style>\n</head>\n<body>\n<h1>");
+        escape_html(&mut page, &title)?;
+        page.push_str("</h1>\n");
+        html::push_html(&mut page, md::parser(&body));
+        page.push_str("</body>\n</html>\n");
+
+        let path = if single {
+            dest.to_path_buf()
+        } else {
+            dest.join(&note_names[note.id.as_str()])
+        };
+        fs::write(path, page)?;
+        report.notes += 1;
+    }
+    Ok(report)
+}
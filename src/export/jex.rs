@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use pulldown_cmark::{Event, Tag};
+
+use super::{mime_type, ExportItems, ExportReport, ExportResult, Resources};
+use crate::markdown;
+
+const TYPE_NOTE: u8 = 1;
+const TYPE_FOLDER: u8 = 2;
+const TYPE_RESOURCE: u8 = 4;
+
+// A JEX file is a tar archive with one item per file, in Joplin's serialization format.
+pub(super) fn export(
+    items: &ExportItems,
+    resources: &Resources,
+    dest: &Path,
+) -> ExportResult<ExportReport> {
+    let mut report = ExportReport::default();
+    let mut builder = tar::Builder::new(File::create(dest)?);
+    let now = format_time(Utc::now());
+
+    for folder in items.folders.iter() {
+        let created = joplin_time(&folder.created_time.format_ymd_hms());
+        let updated = joplin_time(&folder.updated_time.format_ymd_hms());
+        let item = serialize(
+            &folder.title,
+            None,
+            &[
+                ("id", folder.id.as_str()),
+                ("created_time", created.as_str()),
+                ("updated_time", updated.as_str()),
+                ("user_created_time", created.as_str()),
+                ("user_updated_time", updated.as_str()),
+                ("parent_id", folder.parent_id.as_deref().unwrap_or_default()),
+            ],
+            TYPE_FOLDER,
+        );
+        append(&mut builder, &format!("{}.md", folder.id), item.as_bytes())?;
+    }
+
+    let mut exported_resources = Vec::new();
+    for export_note in items.notes.iter() {
+        let note = &export_note.note;
+        let created = joplin_time(&note.user_created_time.format_ymd_hms());
+        let updated = joplin_time(&note.user_updated_time.format_ymd_hms());
+        let item = serialize(
+            &note.get_title(),
+            Some(note.body.as_str()),
+            &[
+                ("id", note.id.as_str()),
+                (
+                    "parent_id",
+                    export_note.folder_id.as_deref().unwrap_or_default(),
+                ),
+                ("created_time", created.as_str()),
+                ("updated_time", updated.as_str()),
+                ("user_created_time", created.as_str()),
+                ("user_updated_time", updated.as_str()),
+                ("markup_language", "1"),
+            ],
+            TYPE_NOTE,
+        );
+        append(&mut builder, &format!("{}.md", note.id), item.as_bytes())?;
+        report.notes += 1;
+
+        let link_texts = link_texts(&note.body);
+        for (id, resource) in resources.linked_by(&note.body) {
+            if exported_resources.contains(&id) {
+                continue;
+            }
+            let file_name = resource
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // Joplin names attachment links after the original file.
+            let original_name = link_texts
+                .get(id)
+                .filter(|text| !text.is_empty())
+                .cloned()
+                .unwrap_or_else(|| file_name.clone());
+            let extension = resource
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let modified = fs::metadata(resource)
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| format_time(modified.into()))
+                .unwrap_or_else(|_| now.clone());
+            let item = serialize(
+                &original_name,
+                None,
+                &[
+                    ("id", id),
+                    ("mime", mime_type(resource)),
+                    ("filename", original_name.as_str()),
+                    ("created_time", modified.as_str()),
+                    ("updated_time", modified.as_str()),
+                    ("file_extension", extension.as_str()),
+                ],
+                TYPE_RESOURCE,
+            );
+            append(&mut builder, &format!("{id}.md"), item.as_bytes())?;
+            append(
+                &mut builder,
+                &format!("resources/{file_name}"),
+                &fs::read(resource)?,
+            )?;
+            exported_resources.push(id);
+            report.resources += 1;
+        }
+    }
+    builder.finish()?;
+    Ok(report)
+}
+
+fn format_time(time: chrono::DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+// ruslin-data formats times in local time.
+fn joplin_time(local: &str) -> String {
+    NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(|time| format_time(time.with_timezone(&Utc)))
+        .unwrap_or_else(|| format_time(Utc::now()))
+}
+
+// The text of the first link or image pointing at each item.
+fn link_texts(body: &str) -> HashMap<String, String> {
+    let mut texts = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for event in markdown::parser(body) {
+        match event {
+            Event::Start(Tag::Link(_, url, _) | Tag::Image(_, url, _)) => {
+                current =
+                    markdown::note_id_from_url(&url).map(|id| (id.to_string(), String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, link_text)) = current.as_mut() {
+                    link_text.push_str(&text);
+                }
+            }
+            Event::End(Tag::Link(..) | Tag::Image(..)) => {
+                if let Some((id, text)) = current.take() {
+                    texts.entry(id).or_insert(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    texts
+}
+
+fn serialize(title: &str, body: Option<&str>, props: &[(&str, &str)], type_: u8) -> String {
+    let mut item = format!("{title}\n\n");
+    if let Some(body) = body {
+        item.push_str(body);
+        item.push_str("\n\n");
+    }
+    for (key, value) in props {
+        item.push_str(&format!("{key}: {value}\n"));
+    }
+    item.push_str(&format!("type_: {type_}"));
+    item
+}
+
+fn append<W: Write>(builder: &mut tar::Builder<W>, path: &str, data: &[u8]) -> ExportResult<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::jex::Item;
+
+    #[test]
+    fn serializes_like_joplin() {
+        let item = serialize(
+            "Groceries",
+            Some("- milk"),
+            &[("id", "3c4d"), ("parent_id", "1a2b")],
+            TYPE_NOTE,
+        );
+        assert_eq!(
+            item,
+            "Groceries\n\n- milk\n\nid: 3c4d\nparent_id: 1a2b\ntype_: 1"
+        );
+    }
+
+    #[test]
+    fn notes_round_trip_through_the_importer() {
+        let body = "# Plan\n\nDue: tomorrow\n\n[Link](:/0123456789abcdef0123456789abcdef)";
+        let item = Item::parse(&serialize(
+            "Plan",
+            Some(body),
+            &[
+                ("id", "3c4d"),
+                ("parent_id", "1a2b"),
+                ("markup_language", "1"),
+            ],
+            TYPE_NOTE,
+        ));
+        assert_eq!(item.title, "Plan");
+        assert_eq!(item.body, body);
+        assert_eq!(item.prop("id"), "3c4d");
+        assert_eq!(item.prop("parent_id"), "1a2b");
+        assert_eq!(item.prop("type_"), "1");
+    }
+
+    #[test]
+    fn folders_round_trip_through_the_importer() {
+        let item = Item::parse(&serialize(
+            "Work",
+            None,
+            &[("id", "1a2b"), ("parent_id", "")],
+            TYPE_FOLDER,
+        ));
+        assert_eq!(item.title, "Work");
+        assert_eq!(item.body, "");
+        assert_eq!(item.prop("id"), "1a2b");
+        assert_eq!(item.prop("parent_id"), "");
+        assert_eq!(item.prop("type_"), "2");
+    }
+}
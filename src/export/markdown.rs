@@ -0,0 +1,84 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{
+    file_name, folder_dirs, unique_name, url_path, ExportItems, ExportReport, ExportResult,
+    Resources,
+};
+use crate::markdown as md;
+
+const RESOURCES_DIR: &str = "_resources";
+
+// Each folder becomes a directory, links point at the exported files instead of item ids.
+pub(super) fn export(
+    items: &ExportItems,
+    resources: &Resources,
+    dest: &Path,
+) -> ExportResult<ExportReport> {
+    let mut report = ExportReport::default();
+    let folders: Vec<(&str, Option<&str>, &str)> = items
+        .folders
+        .iter()
+        .map(|folder| {
+            (
+                folder.id.as_str(),
+                folder.parent_id.as_deref(),
+                folder.title.as_str(),
+            )
+        })
+        .collect();
+    let folder_dirs = folder_dirs(&folders);
+    // Notes without a known folder share one more top-level directory.
+    let mut top_level: Vec<String> = folder_dirs
+        .values()
+        .filter(|dir| !dir.contains('/'))
+        .cloned()
+        .collect();
+    let mut loose_dir = None;
+    let mut used_names: HashMap<String, Vec<String>> = HashMap::new();
+    let mut note_paths: HashMap<&str, String> = HashMap::new();
+    for export_note in items.notes.iter() {
+        let dir = match export_note
+            .folder_id
+            .as_deref()
+            .and_then(|id| folder_dirs.get(id))
+        {
+            Some(dir) => dir.clone(),
+            None => loose_dir
+                .get_or_insert_with(|| unique_name(&mut top_level, "Notes".to_string(), ""))
+                .clone(),
+        };
+        let used_names = used_names.entry(dir.clone()).or_default();
+        let name = unique_name(used_names, file_name(&export_note.note.get_title()), "md");
+        note_paths.insert(export_note.note.id.as_str(), format!("{dir}/{name}"));
+    }
+
+    let mut copied = Vec::new();
+    for export_note in items.notes.iter() {
+        let note = &export_note.note;
+        let path = dest.join(&note_paths[note.id.as_str()]);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        for (id, resource) in resources.linked_by(&note.body) {
+            if !copied.contains(&id) {
+                let resources_dest = dest.join(RESOURCES_DIR);
+                fs::create_dir_all(&resources_dest)?;
+                fs::copy(resource, resources_dest.join(resource.file_name().unwrap()))?;
+                copied.push(id);
+                report.resources += 1;
+            }
+        }
+        let body = md::replace_item_links(&note.body, |id| {
+            if let Some(note_path) = note_paths.get(id) {
+                Some(format!("../{}", url_path(note_path)))
+            } else {
+                let resource = resources.get(id)?;
+                let name = resource.file_name()?.to_string_lossy();
+                Some(format!("../{RESOURCES_DIR}/{}", url_path(&name)))
+            }
+        });
+        fs::write(path, body)?;
+        report.notes += 1;
+    }
+    Ok(report)
+}
@@ -0,0 +1,389 @@
+mod html;
+mod jex;
+mod markdown;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ruslin_data::{DatabaseError, Folder, Note, RuslinData};
+
+use crate::{markdown as md, print::PrintNote};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Pdf,
+    Jex,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            ExportFormat::Markdown => None,
+            ExportFormat::Html => Some("html"),
+            ExportFormat::Pdf => Some("pdf"),
+            ExportFormat::Jex => Some("jex"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportSelection {
+    Note { id: String },
+    Notes { ids: Vec<String> },
+    Folder { id: String },
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Database(DatabaseError),
+    // PDF needs a print operation on the GTK thread, see `print::export_pdf`.
+    NeedsPrintOperation,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{e}"),
+            ExportError::Database(e) => write!(f, "{e}"),
+            ExportError::NeedsPrintOperation => {
+                write!(f, "PDF can only be exported through printing")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<DatabaseError> for ExportError {
+    fn from(e: DatabaseError) -> Self {
+        ExportError::Database(e)
+    }
+}
+
+pub type ExportResult<T> = Result<T, ExportError>;
+
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub notes: usize,
+    pub resources: usize,
+}
+
+pub struct ExportNote {
+    pub folder_id: Option<String>,
+    pub note: Note,
+}
+
+pub struct ExportItems {
+    pub folders: Vec<Folder>,
+    pub notes: Vec<ExportNote>,
+}
+
+impl ExportItems {
+    pub fn load(data: &RuslinData, selection: &ExportSelection) -> ExportResult<ExportItems> {
+        let folders = data.db.load_folders()?;
+        let notes = match selection {
+            ExportSelection::Note { id } => {
+                let note = data.db.load_note(id)?;
+                vec![ExportNote {
+                    folder_id: note.parent_id.clone(),
+                    note,
+                }]
+            }
+            ExportSelection::Notes { ids } => ids
+                .iter()
+                .map(|id| {
+                    let note = data.db.load_note(id)?;
+                    Ok(ExportNote {
+                        folder_id: note.parent_id.clone(),
+                        note,
+                    })
+                })
+                .collect::<ExportResult<Vec<_>>>()?,
+            ExportSelection::Folder { id } => {
+                let mut notes = Vec::new();
+                for folder_id in folder_tree(&folders, id) {
+                    for abbr_note in data.db.load_abbr_notes(Some(&folder_id))? {
+                        notes.push(ExportNote {
+                            folder_id: Some(folder_id.clone()),
+                            note: data.db.load_note(&abbr_note.id)?,
+                        });
+                    }
+                }
+                notes
+            }
+        };
+        // The folders of the notes and their parents, so the hierarchy can be rebuilt.
+        let mut needed: HashSet<&str> = HashSet::new();
+        for note in notes.iter() {
+            let mut folder_id = note.folder_id.as_deref();
+            while let Some(id) = folder_id.filter(|id| needed.insert(id)) {
+                folder_id = folders
+                    .iter()
+                    .find(|folder| folder.id == id)
+                    .and_then(|folder| folder.parent_id.as_deref());
+            }
+        }
+        let needed: HashSet<String> = needed.into_iter().map(str::to_string).collect();
+        let folders = folders
+            .into_iter()
+            .filter(|folder| needed.contains(&folder.id))
+            .collect();
+        Ok(ExportItems { folders, notes })
+    }
+
+    pub fn print_notes(&self) -> Vec<PrintNote> {
+        self.notes
+            .iter()
+            .map(|export_note| PrintNote {
+                title: export_note.note.get_title().to_string(),
+                body: export_note.note.body.clone(),
+                metadata: vec![(
+                    "Folder".to_string(),
+                    self.folder_title(export_note.folder_id.as_deref())
+                        .to_string(),
+                )],
+            })
+            .collect()
+    }
+
+    fn folder_title(&self, folder_id: Option<&str>) -> &str {
+        self.folders
+            .iter()
+            .find(|folder| Some(folder.id.as_str()) == folder_id)
+            .map(|folder| folder.title.as_str())
+            .unwrap_or("Notes")
+    }
+}
+
+pub fn export(
+    data: Arc<RuslinData>,
+    resources_dir: &Path,
+    selection: &ExportSelection,
+    format: ExportFormat,
+    dest: &Path,
+) -> ExportResult<ExportReport> {
+    let items = ExportItems::load(&data, selection)?;
+    let resources = Resources::new(resources_dir);
+    match format {
+        ExportFormat::Markdown => markdown::export(&items, &resources, dest),
+        ExportFormat::Html => html::export(&items, &resources, dest),
+        ExportFormat::Jex => jex::export(&items, &resources, dest),
+        ExportFormat::Pdf => Err(ExportError::NeedsPrintOperation),
+    }
+}
+
+// The folder and its subfolders, at any depth.
+fn folder_tree(folders: &[Folder], id: &str) -> Vec<String> {
+    let mut tree = vec![id.to_string()];
+    let mut index = 0;
+    while let Some(parent_id) = tree.get(index).cloned() {
+        tree.extend(
+            folders
+                .iter()
+                .filter(|folder| folder.parent_id.as_deref() == Some(parent_id.as_str()))
+                .filter(|folder| !tree.contains(&folder.id))
+                .map(|folder| folder.id.clone())
+                .collect::<Vec<_>>(),
+        );
+        index += 1;
+    }
+    tree
+}
+
+// Resources are stored by the data layer as `<resources_dir>/<id>.<extension>`.
+struct Resources {
+    files: HashMap<String, PathBuf>,
+}
+
+impl Resources {
+    fn new(resources_dir: &Path) -> Resources {
+        let files = fs::read_dir(resources_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let id = path.file_stem()?.to_str()?.to_string();
+                Some((id, path))
+            })
+            .collect();
+        Resources { files }
+    }
+
+    fn get(&self, id: &str) -> Option<&Path> {
+        self.files.get(id).map(PathBuf::as_path)
+    }
+
+    fn linked_by<'a>(&'a self, body: &str) -> Vec<(&'a str, &'a Path)> {
+        let mut linked = Vec::new();
+        md::replace_item_links(body, |id| {
+            if let Some((id, path)) = self.files.get_key_value(id) {
+                if !linked
+                    .iter()
+                    .any(|(linked_id, _)| *linked_id == id.as_str())
+                {
+                    linked.push((id.as_str(), path.as_path()));
+                }
+            }
+            None
+        });
+        linked
+    }
+}
+
+pub(crate) fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+// Keeps file names portable, titles may contain anything.
+fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.chars().take(100).collect()
+    }
+}
+
+// The directory of each folder, relative to the export root. Subfolders go inside their parent's
+// directory, folders whose parent isn't exported go at the top.
+fn folder_dirs<'a>(folders: &[(&'a str, Option<&'a str>, &'a str)]) -> HashMap<&'a str, String> {
+    let by_id: HashMap<&str, (Option<&str>, &str)> = folders
+        .iter()
+        .map(|(id, parent_id, title)| (*id, (*parent_id, *title)))
+        .collect();
+    let mut dirs: HashMap<&str, String> = HashMap::new();
+    let mut used: HashMap<Option<&str>, Vec<String>> = HashMap::new();
+    for (id, _, _) in folders {
+        // The folder and its ancestors without a directory yet, a cycle ends the chain.
+        let mut chain = Vec::new();
+        let mut current = Some(*id);
+        while let Some(folder_id) = current
+            .filter(|id| by_id.contains_key(id) && !dirs.contains_key(id) && !chain.contains(id))
+        {
+            chain.push(folder_id);
+            current = by_id[folder_id].0;
+        }
+        for folder_id in chain.into_iter().rev() {
+            let (parent_id, title) = by_id[folder_id];
+            let parent = parent_id.and_then(|parent_id| {
+                dirs.get(parent_id)
+                    .map(|parent_dir| (parent_id, parent_dir.clone()))
+            });
+            let siblings = used.entry(parent.as_ref().map(|(id, _)| *id)).or_default();
+            let name = unique_name(siblings, file_name(title), "");
+            let dir = match parent {
+                Some((_, parent_dir)) => format!("{parent_dir}/{name}"),
+                None => name,
+            };
+            dirs.insert(folder_id, dir);
+        }
+    }
+    dirs
+}
+
+fn unique_name(used: &mut Vec<String>, name: String, extension: &str) -> String {
+    let with_extension = |name: String| {
+        if extension.is_empty() {
+            name
+        } else {
+            format!("{name}.{extension}")
+        }
+    };
+    let mut candidate = with_extension(name.clone());
+    let mut n = 1;
+    while used.contains(&candidate) {
+        candidate = with_extension(format!("{name} ({n})"));
+        n += 1;
+    }
+    used.push(candidate.clone());
+    candidate
+}
+
+fn url_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subfolders_go_inside_their_parents() {
+        let dirs = folder_dirs(&[
+            ("c", Some("b"), "Drafts"),
+            ("a", None, "Work"),
+            ("b", Some("a"), "Reports"),
+        ]);
+        assert_eq!(dirs["a"], "Work");
+        assert_eq!(dirs["b"], "Work/Reports");
+        assert_eq!(dirs["c"], "Work/Reports/Drafts");
+    }
+
+    #[test]
+    fn numbers_siblings_with_the_same_name() {
+        let dirs = folder_dirs(&[
+            ("a", None, "Work"),
+            ("b", None, "Work"),
+            ("c", Some("a"), "Notes"),
+            ("d", Some("b"), "Notes"),
+            ("e", Some("a"), "Notes"),
+            ("f", None, "a/b"),
+        ]);
+        assert_eq!(dirs["a"], "Work");
+        assert_eq!(dirs["b"], "Work (1)");
+        assert_eq!(dirs["c"], "Work/Notes");
+        // Only siblings are numbered.
+        assert_eq!(dirs["d"], "Work (1)/Notes");
+        assert_eq!(dirs["e"], "Work/Notes (1)");
+        assert_eq!(dirs["f"], "a_b");
+    }
+
+    #[test]
+    fn unknown_parents_and_cycles_end_at_the_top() {
+        let dirs = folder_dirs(&[
+            ("a", Some("missing"), "Orphan"),
+            ("b", Some("c"), "Loop B"),
+            ("c", Some("b"), "Loop C"),
+        ]);
+        assert_eq!(dirs["a"], "Orphan");
+        assert_eq!(dirs["c"], "Loop C");
+        assert_eq!(dirs["b"], "Loop C/Loop B");
+    }
+}
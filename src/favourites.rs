@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+
+use gtk::{gio, glib, prelude::*};
+use relm4::gtk;
+
+use crate::{app::NoteEvent, config::APP_ID, AppContext};
+
+// Pinned notes and favourite folders, by profile. ruslin-data has no tags or spare note
+// fields to keep them in, so they stay on this machine and aren't synced.
+#[derive(Debug, Default, Clone)]
+pub struct Favourites {
+    pub notes: Vec<String>,
+    pub folders: Vec<String>,
+}
+
+pub fn load(ctx: &AppContext) -> Favourites {
+    let settings = gio::Settings::new(APP_ID);
+    let favourites: HashMap<String, (Vec<String>, Vec<String>)> = settings.get("favourites");
+    favourites
+        .get(ctx.profile.as_deref().unwrap_or_default())
+        .map(|(notes, folders)| Favourites {
+            notes: notes.clone(),
+            folders: folders.clone(),
+        })
+        .unwrap_or_default()
+}
+
+pub fn set_note_pinned(ctx: &AppContext, id: &str, pinned: bool) -> Result<(), glib::BoolError> {
+    update(ctx, |favourites| toggle(&mut favourites.notes, id, pinned))
+}
+
+pub fn set_folder_favourite(
+    ctx: &AppContext,
+    id: &str,
+    favourite: bool,
+) -> Result<(), glib::BoolError> {
+    update(ctx, |favourites| {
+        toggle(&mut favourites.folders, id, favourite)
+    })
+}
+
+// Drops the ids of notes and folders that were deleted, here or by a sync. Only writes when
+// something was dropped, writing announces a change.
+pub fn forget_missing(
+    ctx: &AppContext,
+    note_ids: &HashSet<String>,
+    folder_ids: &HashSet<String>,
+) -> Result<(), glib::BoolError> {
+    let mut favourites = load(ctx);
+    if retain_existing(&mut favourites, note_ids, folder_ids) {
+        update(ctx, |stored| *stored = favourites)?;
+    }
+    Ok(())
+}
+
+fn retain_existing(
+    favourites: &mut Favourites,
+    note_ids: &HashSet<String>,
+    folder_ids: &HashSet<String>,
+) -> bool {
+    let count = favourites.notes.len() + favourites.folders.len();
+    favourites.notes.retain(|id| note_ids.contains(id));
+    favourites.folders.retain(|id| folder_ids.contains(id));
+    favourites.notes.len() + favourites.folders.len() != count
+}
+
+fn toggle(ids: &mut Vec<String>, id: &str, on: bool) {
+    ids.retain(|other| other != id);
+    if on {
+        ids.push(id.to_string());
+    }
+}
+
+fn update(ctx: &AppContext, f: impl FnOnce(&mut Favourites)) -> Result<(), glib::BoolError> {
+    let mut favourites = load(ctx);
+    f(&mut favourites);
+    let settings = gio::Settings::new(APP_ID);
+    let mut all: HashMap<String, (Vec<String>, Vec<String>)> = settings.get("favourites");
+    all.insert(
+        ctx.profile.clone().unwrap_or_default(),
+        (favourites.notes, favourites.folders),
+    );
+    settings.set("favourites", &all)?;
+    // Sending only fails when nothing is listening.
+    let _ = ctx.note_events.send(NoteEvent::FavouritesChanged);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn toggling_keeps_each_id_once_in_pin_order() {
+        let mut notes = Vec::new();
+        toggle(&mut notes, "a", true);
+        toggle(&mut notes, "b", true);
+        toggle(&mut notes, "a", true);
+        assert_eq!(notes, ids(&["b", "a"]));
+        toggle(&mut notes, "b", false);
+        toggle(&mut notes, "c", false);
+        assert_eq!(notes, ids(&["a"]));
+    }
+
+    #[test]
+    fn deleted_notes_and_folders_are_forgotten() {
+        let mut favourites = Favourites {
+            notes: ids(&["a", "gone", "b"]),
+            folders: ids(&["f", "deleted"]),
+        };
+        let note_ids = HashSet::from(["a".to_string(), "b".to_string()]);
+        let folder_ids = HashSet::from(["f".to_string()]);
+        assert!(retain_existing(&mut favourites, &note_ids, &folder_ids));
+        assert_eq!(favourites.notes, ids(&["a", "b"]));
+        assert_eq!(favourites.folders, ids(&["f"]));
+        assert!(!retain_existing(&mut favourites, &note_ids, &folder_ids));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use gtk::{gio, glib, prelude::*};
+use relm4::gtk;
+use ruslin_data::Folder;
+
+use crate::{config::APP_ID, AppContext};
+
+// Joplin folders have no position, the order they are dragged into is kept on this machine.
+// Their parent is a folder field and synced.
+pub fn load(ctx: &AppContext) -> Vec<String> {
+    let settings = gio::Settings::new(APP_ID);
+    let orders: HashMap<String, Vec<String>> = settings.get("folder-order");
+    orders
+        .get(ctx.profile.as_deref().unwrap_or_default())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn save(ctx: &AppContext, folder_ids: &[String]) -> Result<(), glib::BoolError> {
+    let settings = gio::Settings::new(APP_ID);
+    let mut orders: HashMap<String, Vec<String>> = settings.get("folder-order");
+    orders.insert(ctx.profile.clone().unwrap_or_default(), folder_ids.to_vec());
+    settings.set("folder-order", &orders)
+}
+
+// Where a folder dropped on a row goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPosition {
+    Before,
+    Into,
+    After,
+}
+
+impl DropPosition {
+    // The outer quarters of the row put the folder next to it, the middle inside it.
+    pub fn at(y: f64, row_height: f64) -> DropPosition {
+        if y < row_height / 4.0 {
+            DropPosition::Before
+        } else if y > row_height * 3.0 / 4.0 {
+            DropPosition::After
+        } else {
+            DropPosition::Into
+        }
+    }
+}
+
+// Each folder after its parent with its depth, siblings in the saved order. Folders that were
+// never moved keep their loaded order after the ordered ones. A folder whose parent is missing,
+// or that is part of a parent cycle, is shown at the top level.
+pub fn tree(folders: Vec<Folder>, order: &[String]) -> Vec<(Folder, usize)> {
+    let mut folders = folders;
+    folders.sort_by_key(|folder| {
+        order
+            .iter()
+            .position(|id| id == &folder.id)
+            .unwrap_or(usize::MAX)
+    });
+    let index_of: HashMap<&str, usize> = folders
+        .iter()
+        .enumerate()
+        .map(|(index, folder)| (folder.id.as_str(), index))
+        .collect();
+    let mut children = vec![Vec::new(); folders.len()];
+    let mut roots = Vec::new();
+    for (index, folder) in folders.iter().enumerate() {
+        match folder
+            .parent_id
+            .as_deref()
+            .and_then(|parent_id| index_of.get(parent_id))
+        {
+            Some(&parent) if parent != index => children[parent].push(index),
+            _ => roots.push(index),
+        }
+    }
+    let mut visited = vec![false; folders.len()];
+    let mut indexes = Vec::with_capacity(folders.len());
+    for root in roots.into_iter().chain(0..folders.len()) {
+        visit(root, 0, &children, &mut visited, &mut indexes);
+    }
+    let mut folders: Vec<Option<Folder>> = folders.into_iter().map(Some).collect();
+    indexes
+        .into_iter()
+        .filter_map(|(index, depth)| Some((folders[index].take()?, depth)))
+        .collect()
+}
+
+fn visit(
+    index: usize,
+    depth: usize,
+    children: &[Vec<usize>],
+    visited: &mut [bool],
+    indexes: &mut Vec<(usize, usize)>,
+) {
+    if visited[index] {
+        return;
+    }
+    visited[index] = true;
+    indexes.push((index, depth));
+    for &child in &children[index] {
+        visit(child, depth + 1, children, visited, indexes);
+    }
+}
+
+// The new parent of the dragged folder and the new order of the shown folders. `None` when the
+// folder would end up inside itself.
+pub fn drop_folder(
+    folders: &[Folder],
+    order: &[String],
+    dragged: &str,
+    target: &str,
+    position: DropPosition,
+) -> Option<(Option<String>, Vec<String>)> {
+    let parent_of = |id: &str| {
+        folders
+            .iter()
+            .find(|folder| folder.id == id)
+            .and_then(|folder| folder.parent_id.clone())
+    };
+    let parent_id = match position {
+        DropPosition::Into => Some(target.to_string()),
+        DropPosition::Before | DropPosition::After => parent_of(target),
+    };
+    // Walks up from the new parent, giving up after as many steps as there are folders.
+    let mut ancestor = parent_id.clone();
+    for _ in 0..=folders.len() {
+        match ancestor {
+            Some(id) if id == dragged => return None,
+            Some(id) => ancestor = parent_of(&id),
+            None => break,
+        }
+    }
+    let mut order: Vec<String> = order.iter().filter(|id| *id != dragged).cloned().collect();
+    let index = match (position, order.iter().position(|id| id == target)) {
+        (DropPosition::Before, Some(index)) => index,
+        (DropPosition::After, Some(index)) => index + 1,
+        _ => order.len(),
+    };
+    order.insert(index, dragged.to_string());
+    Some((parent_id, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(id: &str, parent_id: Option<&str>) -> Folder {
+        let mut folder = Folder::new(id.to_uppercase(), parent_id.map(str::to_string));
+        folder.id = id.to_string();
+        folder
+    }
+
+    fn ids(tree: &[(Folder, usize)]) -> Vec<(&str, usize)> {
+        tree.iter()
+            .map(|(folder, depth)| (folder.id.as_str(), *depth))
+            .collect()
+    }
+
+    fn order(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn children_follow_their_parent_in_order() {
+        let folders = vec![
+            folder("c", Some("a")),
+            folder("a", None),
+            folder("b", None),
+            folder("d", Some("a")),
+            folder("e", Some("missing")),
+        ];
+        let tree = tree(folders, &order(&["b", "a", "d", "c"]));
+        assert_eq!(
+            ids(&tree),
+            [("b", 0), ("a", 0), ("d", 1), ("c", 1), ("e", 0)]
+        );
+    }
+
+    #[test]
+    fn parent_cycles_are_shown_once() {
+        let folders = vec![folder("a", Some("b")), folder("b", Some("a"))];
+        let tree = tree(folders, &[]);
+        assert_eq!(ids(&tree), [("a", 0), ("b", 1)]);
+    }
+
+    #[test]
+    fn drop_positions() {
+        assert_eq!(DropPosition::at(2.0, 40.0), DropPosition::Before);
+        assert_eq!(DropPosition::at(20.0, 40.0), DropPosition::Into);
+        assert_eq!(DropPosition::at(38.0, 40.0), DropPosition::After);
+    }
+
+    #[test]
+    fn dropping_reparents_and_reorders() {
+        let folders = vec![folder("a", None), folder("b", Some("a")), folder("c", None)];
+        let shown = order(&["a", "b", "c"]);
+        assert_eq!(
+            drop_folder(&folders, &shown, "c", "a", DropPosition::Into),
+            Some((Some("a".to_string()), order(&["a", "b", "c"])))
+        );
+        assert_eq!(
+            drop_folder(&folders, &shown, "c", "b", DropPosition::Before),
+            Some((Some("a".to_string()), order(&["a", "c", "b"])))
+        );
+        assert_eq!(
+            drop_folder(&folders, &shown, "b", "c", DropPosition::After),
+            Some((None, order(&["a", "c", "b"])))
+        );
+    }
+
+    #[test]
+    fn folders_are_not_dropped_into_themselves() {
+        let folders = vec![
+            folder("a", None),
+            folder("b", Some("a")),
+            folder("c", Some("b")),
+        ];
+        let shown = order(&["a", "b", "c"]);
+        assert_eq!(
+            drop_folder(&folders, &shown, "a", "a", DropPosition::Into),
+            None
+        );
+        assert_eq!(
+            drop_folder(&folders, &shown, "a", "c", DropPosition::Into),
+            None
+        );
+        assert_eq!(
+            drop_folder(&folders, &shown, "a", "c", DropPosition::Before),
+            None
+        );
+        assert!(drop_folder(&folders, &shown, "c", "a", DropPosition::Before).is_some());
+    }
+}
@@ -17,3 +17,63 @@ pub fn list_add_symbolic() -> &'static str {
 pub fn view_refresh_symbolic() -> &'static str {
     "view-refresh-symbolic"
 }
+
+#[inline]
+pub fn system_search_symbolic() -> &'static str {
+    "system-search-symbolic"
+}
+
+#[inline]
+pub fn view_reveal_symbolic() -> &'static str {
+    "view-reveal-symbolic"
+}
+
+#[inline]
+pub fn format_text_bold_symbolic() -> &'static str {
+    "format-text-bold-symbolic"
+}
+
+#[inline]
+pub fn format_text_italic_symbolic() -> &'static str {
+    "format-text-italic-symbolic"
+}
+
+#[inline]
+pub fn insert_link_symbolic() -> &'static str {
+    "insert-link-symbolic"
+}
+
+#[inline]
+pub fn checkbox_checked_symbolic() -> &'static str {
+    "checkbox-checked-symbolic"
+}
+
+#[inline]
+pub fn view_grid_symbolic() -> &'static str {
+    "view-grid-symbolic"
+}
+
+#[inline]
+pub fn edit_copy_symbolic() -> &'static str {
+    "edit-copy-symbolic"
+}
+
+#[inline]
+pub fn network_offline_symbolic() -> &'static str {
+    "network-offline-symbolic"
+}
+
+#[inline]
+pub fn view_pin_symbolic() -> &'static str {
+    "view-pin-symbolic"
+}
+
+#[inline]
+pub fn starred_symbolic() -> &'static str {
+    "starred-symbolic"
+}
+
+#[inline]
+pub fn selection_mode_symbolic() -> &'static str {
+    "selection-mode-symbolic"
+}
@@ -0,0 +1,297 @@
+use std::{fs, path::Path};
+
+use quick_xml::{events::Event, Reader};
+
+use super::{ImportProgress, ImportResult, Importer};
+
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    resources: Vec<String>,
+}
+
+pub(super) fn import<F: FnMut(ImportProgress)>(
+    importer: &mut Importer<F>,
+    path: &Path,
+) -> ImportResult<()> {
+    let xml = fs::read_to_string(path)?;
+    let notes = parse_notes(&xml)?;
+    let total = notes.len();
+    let folder_title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Evernote".to_string());
+    let folder_id = importer.create_folder(folder_title, None)?;
+
+    for (done, note) in notes.into_iter().enumerate() {
+        importer.progress(done, total, note.title.clone());
+        for resource in note.resources {
+            importer.skip_attachment(format!("{} ({resource})", note.title));
+        }
+        let body = match enml_to_markdown(&note.content) {
+            Ok(body) => body,
+            Err(e) => {
+                importer.skip(note.title, e.to_string());
+                continue;
+            }
+        };
+        importer.not_kept("creation and update times");
+        importer.create_note(&note.title, &folder_id, note.title.clone(), body);
+    }
+    importer.progress(total, total, "");
+    Ok(())
+}
+
+fn parse_notes(xml: &str) -> ImportResult<Vec<EnexNote>> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut notes = Vec::new();
+    let mut current: Option<EnexNote> = None;
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if name == "note" {
+                    current = Some(EnexNote::default());
+                }
+                path.push(name);
+            }
+            Event::End(_) => {
+                if path.pop().as_deref() == Some("note") {
+                    notes.extend(current.take());
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?;
+                if let Some(note) = current.as_mut() {
+                    match path
+                        .iter()
+                        .rev()
+                        .take(2)
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()[..]
+                    {
+                        ["title", "note"] => note.title.push_str(&text),
+                        ["content", "note"] => note.content.push_str(&text),
+                        ["file-name", "resource-attributes"] => {
+                            note.resources.push(text.to_string())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::CData(e) => {
+                if let Some(note) = current.as_mut() {
+                    if path.last().map(String::as_str) == Some("content") {
+                        note.content
+                            .push_str(&String::from_utf8_lossy(&e.into_inner()));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(notes)
+}
+
+// ENML is XHTML with a few Evernote specific tags, only the common formatting is kept.
+fn enml_to_markdown(enml: &str) -> ImportResult<String> {
+    let mut reader = Reader::from_str(enml);
+    reader.check_end_names(false);
+    let mut out = String::new();
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut link: Option<String> = None;
+    let mut in_pre = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+                match name.as_str() {
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        let level = name[1..].parse().unwrap_or(1);
+                        start_block(&mut out);
+                        out.push_str(&"#".repeat(level));
+                        out.push(' ');
+                    }
+                    "p" | "div" => start_line(&mut out),
+                    "br" => out.push('\n'),
+                    "b" | "strong" => out.push_str("**"),
+                    "i" | "em" => out.push('*'),
+                    "s" | "strike" | "del" => out.push_str("~~"),
+                    "code" if !in_pre => out.push('`'),
+                    "pre" => {
+                        in_pre = true;
+                        start_block(&mut out);
+                        out.push_str("```\n");
+                    }
+                    "hr" => {
+                        start_block(&mut out);
+                        out.push_str("---\n");
+                    }
+                    "ul" => {
+                        start_line(&mut out);
+                        lists.push(None);
+                    }
+                    "ol" => {
+                        start_line(&mut out);
+                        lists.push(Some(0));
+                    }
+                    "li" => {
+                        start_line(&mut out);
+                        out.push_str(&"    ".repeat(lists.len().saturating_sub(1)));
+                        match lists.last_mut() {
+                            Some(Some(number)) => {
+                                *number += 1;
+                                out.push_str(&format!("{number}. "));
+                            }
+                            _ => out.push_str("- "),
+                        }
+                    }
+                    "en-todo" => {
+                        let checked = e
+                            .try_get_attribute("checked")?
+                            .map(|attr| attr.value.as_ref() == b"true")
+                            .unwrap_or(false);
+                        out.push_str(if checked { "- [x] " } else { "- [ ] " });
+                    }
+                    "a" => {
+                        link = e
+                            .try_get_attribute("href")?
+                            .map(|attr| attr.unescape_value().map(|v| v.to_string()))
+                            .transpose()?;
+                        out.push('[');
+                    }
+                    "en-media" | "img" => {
+                        let kind = e
+                            .try_get_attribute("type")?
+                            .map(|attr| String::from_utf8_lossy(&attr.value).to_string())
+                            .unwrap_or_default();
+                        out.push_str(&format!("_[attachment {kind}]_"));
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+                match name.as_str() {
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" => out.push('\n'),
+                    "div" => start_line(&mut out),
+                    "b" | "strong" => out.push_str("**"),
+                    "i" | "em" => out.push('*'),
+                    "s" | "strike" | "del" => out.push_str("~~"),
+                    "code" if !in_pre => out.push('`'),
+                    "pre" => {
+                        in_pre = false;
+                        start_line(&mut out);
+                        out.push_str("```\n");
+                    }
+                    "ul" | "ol" => {
+                        lists.pop();
+                        start_line(&mut out);
+                    }
+                    "a" => {
+                        let href = link.take().unwrap_or_default();
+                        out.push_str(&format!("]({href})"));
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape_with(html_entity)?;
+                if in_pre {
+                    out.push_str(&text);
+                } else {
+                    out.push_str(&collapse_whitespace(&text));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(out.trim().to_string())
+}
+
+// ENML is XHTML, so its text uses HTML's named entities besides XML's.
+fn html_entity(name: &str) -> Option<&'static str> {
+    if name == "nbsp" {
+        return Some(" ");
+    }
+    entities::ENTITIES
+        .iter()
+        .find(|entity| {
+            entity
+                .entity
+                .strip_prefix('&')
+                .and_then(|e| e.strip_suffix(';'))
+                == Some(name)
+        })
+        .map(|entity| entity.characters)
+}
+
+// Collapses runs of whitespace like HTML does, keeping a single space at the edges.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                collapsed.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            collapsed.push(c);
+            last_was_space = false;
+        }
+    }
+    collapsed
+}
+
+fn start_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn start_block(out: &mut String) {
+    start_line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_headings_formatting_and_links() {
+        let enml = r#"<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd"><en-note><h1>Trip</h1><div>Pack <b>light</b> and <i>early</i>.</div><div><a href="https://example.com">Tickets</a></div></en-note>"#;
+        assert_eq!(
+            enml_to_markdown(enml).unwrap(),
+            "# Trip\nPack **light** and *early*.\n[Tickets](https://example.com)"
+        );
+    }
+
+    #[test]
+    fn converts_lists_and_todos() {
+        let enml = r#"<en-note><ul><li>One</li><li>Two<ol><li>Nested</li></ol></li></ul><div><en-todo checked="true"/>Done</div><div><en-todo/>Open</div></en-note>"#;
+        assert_eq!(
+            enml_to_markdown(enml).unwrap(),
+            "- One\n- Two\n    1. Nested\n- [x] Done\n- [ ] Open"
+        );
+    }
+
+    #[test]
+    fn decodes_entities_and_keeps_code_whitespace() {
+        let enml = "<en-note><div>Caf&eacute;&nbsp;au lait &amp; more</div><pre>let  x = 1;\n  y</pre><en-media type=\"image/png\" hash=\"abc\"/></en-note>";
+        assert_eq!(
+            enml_to_markdown(enml).unwrap(),
+            "Café au lait & more\n\n```\nlet  x = 1;\n  y\n```\n_[attachment image/png]_"
+        );
+    }
+}
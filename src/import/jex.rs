@@ -0,0 +1,233 @@
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+use ruslin_data::Note;
+
+use super::{ImportProgress, ImportResult, Importer};
+use crate::markdown;
+
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_RESOURCE: &str = "4";
+
+// Joplin's `ModelType` names, for the items that can't be imported.
+fn type_name(type_: &str) -> &str {
+    match type_ {
+        "3" => "settings",
+        "5" => "tags",
+        "6" => "note tags",
+        "7" => "searches",
+        "8" => "alarms",
+        "9" => "master keys",
+        "10" => "item changes",
+        "11" => "note resources",
+        "12" => "resource local states",
+        "13" => "revisions",
+        "14" => "migrations",
+        "15" => "smart filters",
+        "16" => "commands",
+        _ => "unknown items",
+    }
+}
+
+// An item as serialized by Joplin: title, body, then a block of `key: value` properties.
+pub(crate) struct Item {
+    pub(crate) title: String,
+    pub(crate) body: String,
+    props: HashMap<String, String>,
+}
+
+impl Item {
+    pub(crate) fn parse(content: &str) -> Item {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut props = HashMap::new();
+        let mut end = lines.len();
+        while end > 0 {
+            let line = lines[end - 1];
+            if line.trim().is_empty() {
+                break;
+            }
+            match line.split_once(": ").or_else(|| line.split_once(':')) {
+                Some((key, value)) if !key.is_empty() && !key.contains(' ') => {
+                    props.insert(key.to_string(), value.trim().to_string());
+                }
+                _ => break,
+            }
+            end -= 1;
+        }
+        let lines = &lines[..end];
+        let title = lines.first().copied().unwrap_or_default().to_string();
+        let body = lines
+            .get(2..)
+            .map(|lines| lines.join("\n"))
+            .unwrap_or_default();
+        Item {
+            title,
+            body: body.trim_end().to_string(),
+            props,
+        }
+    }
+
+    pub(crate) fn prop(&self, key: &str) -> &str {
+        self.props.get(key).map(String::as_str).unwrap_or_default()
+    }
+}
+
+pub(super) fn import<F: FnMut(ImportProgress)>(
+    importer: &mut Importer<F>,
+    path: &Path,
+) -> ImportResult<()> {
+    let mut archive = tar::Archive::new(File::open(path)?);
+    let mut items = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.display().to_string();
+        if !name.ends_with(".md") || name.starts_with("resources/") {
+            continue;
+        }
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        items.push(Item::parse(&content));
+    }
+
+    let total = items.len();
+    let mut done = 0;
+
+    let folders: HashMap<&str, &Item> = items
+        .iter()
+        .filter(|item| item.prop("type_") == TYPE_FOLDER)
+        .map(|item| (item.prop("id"), item))
+        .collect();
+    let mut folder_ids: HashMap<String, String> = HashMap::new();
+    let mut folder_keys: Vec<&str> = folders.keys().copied().collect();
+    // Parents sort before their children.
+    folder_keys.sort_by_key(|id| folder_path(&folders, id));
+    for old_id in folder_keys {
+        let folder = folders[old_id];
+        importer.progress(done, total, folder_path(&folders, old_id).join("/"));
+        let parent_id = folder_ids.get(folder.prop("parent_id")).cloned();
+        match importer.create_folder(folder.title.clone(), parent_id.as_deref()) {
+            Ok(new_id) => {
+                folder_ids.insert(old_id.to_string(), new_id);
+            }
+            // Its notes go to the fallback folder, its subfolders to the top level.
+            Err(e) => importer.skip(folder.title.clone(), e.to_string()),
+        }
+        done += 1;
+    }
+
+    let mut fallback_folder = None;
+    let mut note_ids: HashMap<String, String> = HashMap::new();
+    let mut notes: Vec<Note> = Vec::new();
+    for item in items.iter() {
+        match item.prop("type_") {
+            TYPE_FOLDER => continue,
+            TYPE_NOTE => {}
+            TYPE_RESOURCE => {
+                importer.skip_attachment(item.title.clone());
+                done += 1;
+                continue;
+            }
+            type_ => {
+                importer.skip(
+                    item.title.clone(),
+                    format!("{} cannot be imported yet", type_name(type_)),
+                );
+                done += 1;
+                continue;
+            }
+        }
+        importer.progress(done, total, item.title.clone());
+        // Note::new has no way to set these.
+        importer.not_kept("creation and update times");
+        if item.prop("is_todo") == "1" {
+            importer.not_kept("to-do state, to-dos became plain notes");
+        }
+        let folder_id = match folder_ids.get(item.prop("parent_id")) {
+            Some(id) => id.clone(),
+            None => match &fallback_folder {
+                Some(id) => id.clone(),
+                None => {
+                    let title = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "Imported".to_string());
+                    let id = importer.create_folder(title, None)?;
+                    fallback_folder = Some(id.clone());
+                    id
+                }
+            },
+        };
+        if let Some(note) = importer.create_note(
+            &item.title,
+            &folder_id,
+            item.title.clone(),
+            item.body.clone(),
+        ) {
+            note_ids.insert(item.prop("id").to_string(), note.id.clone());
+            notes.push(note);
+        }
+        done += 1;
+    }
+
+    // Links between imported notes still point at the ids from the archive.
+    for mut note in notes {
+        let body = markdown::replace_item_links(&note.body, |id| {
+            note_ids.get(id).map(|new_id| format!(":/{new_id}"))
+        });
+        if body != note.body {
+            note.body = body;
+            importer.update_note(&note);
+        }
+    }
+    importer.progress(total, total, "");
+    Ok(())
+}
+
+// The titles from the root folder down to the folder.
+fn folder_path<'a>(folders: &HashMap<&str, &'a Item>, id: &str) -> Vec<&'a str> {
+    let mut titles = Vec::new();
+    let mut current = folders.get(id);
+    while let Some(folder) = current {
+        titles.push(folder.title.as_str());
+        if titles.len() > folders.len() {
+            break;
+        }
+        current = folders.get(folder.prop("parent_id"));
+    }
+    titles.reverse();
+    titles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_note() {
+        let item = Item::parse(
+            "Groceries\n\n- milk\n\nparent_id: 1a2b\n\nid: 3c4d\nparent_id: 1a2b\nis_todo: 0\ntype_: 1",
+        );
+        assert_eq!(item.title, "Groceries");
+        // A body line that looks like a property is kept, the block ends at the blank line.
+        assert_eq!(item.body, "- milk\n\nparent_id: 1a2b");
+        assert_eq!(item.prop("id"), "3c4d");
+        assert_eq!(item.prop("parent_id"), "1a2b");
+        assert_eq!(item.prop("type_"), TYPE_NOTE);
+        assert_eq!(item.prop("missing"), "");
+    }
+
+    #[test]
+    fn parses_an_item_without_body() {
+        let item = Item::parse("Work\n\nid: 5e6f\nparent_id: \ntype_: 2");
+        assert_eq!(item.title, "Work");
+        assert_eq!(item.body, "");
+        assert_eq!(item.prop("parent_id"), "");
+        assert_eq!(item.prop("type_"), TYPE_FOLDER);
+    }
+
+    #[test]
+    fn keeps_colons_in_property_values() {
+        let item = Item::parse("Clip\n\nsource_url: https://example.com/a:b\ntype_: 1");
+        assert_eq!(item.prop("source_url"), "https://example.com/a:b");
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{ImportProgress, ImportResult, Importer};
+
+pub(super) fn import<F: FnMut(ImportProgress)>(
+    importer: &mut Importer<F>,
+    path: &Path,
+) -> ImportResult<()> {
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+    let total = files.len();
+    let mut folders: HashMap<PathBuf, String> = HashMap::new();
+
+    for (done, file) in files.into_iter().enumerate() {
+        let name = file
+            .strip_prefix(path)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        importer.progress(done, total, name.clone());
+
+        if !is_note_file(&file) {
+            importer.skip_attachment(name);
+            continue;
+        }
+
+        let dir = file.parent().unwrap_or(path);
+        let folder_id = match folder_for(importer, path, dir, &mut folders) {
+            Ok(id) => id,
+            Err(e) => {
+                importer.skip(name, format!("its folder couldn't be created: {e}"));
+                continue;
+            }
+        };
+
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) => {
+                importer.skip(name, e.to_string());
+                continue;
+            }
+        };
+        let (front_title, body) = split_front_matter(&content);
+        let title = front_title.unwrap_or_else(|| {
+            file.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        importer.create_note(&name, &folder_id, title, body.to_string());
+    }
+    importer.progress(total, total, "");
+    Ok(())
+}
+
+fn is_note_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    matches!(extension.as_deref(), Some("md" | "markdown" | "txt"))
+}
+
+// Each directory becomes a folder inside its parent directory's folder.
+fn folder_for<F: FnMut(ImportProgress)>(
+    importer: &mut Importer<F>,
+    root: &Path,
+    dir: &Path,
+    folders: &mut HashMap<PathBuf, String>,
+) -> ImportResult<String> {
+    if let Some(id) = folders.get(dir) {
+        return Ok(id.clone());
+    }
+    let parent_id = match dir.parent() {
+        Some(parent) if dir != root && parent.starts_with(root) => {
+            Some(folder_for(importer, root, parent, folders)?)
+        }
+        _ => None,
+    };
+    let id = importer.create_folder(file_name(dir), parent_id.as_deref())?;
+    folders.insert(dir.to_path_buf(), id.clone());
+    Ok(id)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> ImportResult<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(true);
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string())
+}
+
+// Only the `title` key of a YAML front matter block is used, the block itself is dropped.
+fn split_front_matter(content: &str) -> (Option<String>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (None, content);
+    };
+    let front_matter = &rest[..end];
+    let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    let title = front_matter.lines().find_map(|line| {
+        let value = line.strip_prefix("title:")?.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        (!value.is_empty()).then(|| value.to_string())
+    });
+    (title, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_files_are_notes() {
+        assert!(is_note_file(Path::new("a/note.md")));
+        assert!(is_note_file(Path::new("a/note.markdown")));
+        assert!(is_note_file(Path::new("a/NOTE.TXT")));
+        assert!(!is_note_file(Path::new("a/image.png")));
+        assert!(!is_note_file(Path::new("a/README")));
+    }
+
+    #[test]
+    fn takes_the_title_from_front_matter() {
+        let (title, body) = split_front_matter("---\ntitle: \"Plan\"\ntags: [a]\n---\n\n# Body\n");
+        assert_eq!(title.as_deref(), Some("Plan"));
+        assert_eq!(body, "# Body\n");
+        let (title, body) = split_front_matter("---\r\ntitle: 'Plan'\r\n---\r\nBody");
+        assert_eq!(title.as_deref(), Some("Plan"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn drops_front_matter_without_title() {
+        let (title, body) = split_front_matter("---\ndate: 2022-12-01\n---\nBody");
+        assert_eq!(title, None);
+        assert_eq!(body, "Body");
+        let (title, _) = split_front_matter("---\ntitle:\n---\nBody");
+        assert_eq!(title, None);
+    }
+
+    #[test]
+    fn leaves_content_without_front_matter() {
+        assert_eq!(
+            split_front_matter("# Title\n---\n"),
+            (None, "# Title\n---\n")
+        );
+        assert_eq!(split_front_matter("---\nno end"), (None, "---\nno end"));
+    }
+}
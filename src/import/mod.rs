@@ -0,0 +1,170 @@
+mod enex;
+pub(crate) mod jex;
+mod markdown;
+
+use std::{collections::BTreeSet, fmt, io, path::Path, sync::Arc};
+
+use ruslin_data::{DatabaseError, Folder, Note, RuslinData, UpdateSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    MarkdownDirectory,
+    Jex,
+    Enex,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Database(DatabaseError),
+    Xml(quick_xml::Error),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "{e}"),
+            ImportError::Database(e) => write!(f, "{e}"),
+            ImportError::Xml(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<DatabaseError> for ImportError {
+    fn from(e: DatabaseError) -> Self {
+        ImportError::Database(e)
+    }
+}
+
+impl From<quick_xml::Error> for ImportError {
+    fn from(e: quick_xml::Error) -> Self {
+        ImportError::Xml(e)
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for ImportError {
+    fn from(e: quick_xml::events::attributes::AttrError) -> Self {
+        ImportError::Xml(e.into())
+    }
+}
+
+pub type ImportResult<T> = Result<T, ImportError>;
+
+#[derive(Debug, Clone)]
+pub struct ImportProgress {
+    pub done: usize,
+    pub total: usize,
+    pub current: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedItem {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub folders: usize,
+    pub notes: usize,
+    pub skipped: Vec<SkippedItem>,
+    // What the imported items had that ruslin-data has no place for yet.
+    pub not_kept: BTreeSet<&'static str>,
+}
+
+pub fn import(
+    data: Arc<RuslinData>,
+    source: ImportSource,
+    path: &Path,
+    on_progress: impl FnMut(ImportProgress),
+) -> ImportResult<ImportReport> {
+    let mut importer = Importer::new(data, on_progress);
+    match source {
+        ImportSource::MarkdownDirectory => markdown::import(&mut importer, path)?,
+        ImportSource::Jex => jex::import(&mut importer, path)?,
+        ImportSource::Enex => enex::import(&mut importer, path)?,
+    }
+    Ok(importer.report)
+}
+
+struct Importer<F: FnMut(ImportProgress)> {
+    data: Arc<RuslinData>,
+    on_progress: F,
+    report: ImportReport,
+}
+
+impl<F: FnMut(ImportProgress)> Importer<F> {
+    fn new(data: Arc<RuslinData>, on_progress: F) -> Self {
+        Self {
+            data,
+            on_progress,
+            report: ImportReport::default(),
+        }
+    }
+
+    fn progress(&mut self, done: usize, total: usize, current: impl Into<String>) {
+        (self.on_progress)(ImportProgress {
+            done,
+            total,
+            current: current.into(),
+        });
+    }
+
+    fn skip(&mut self, name: impl Into<String>, reason: impl Into<String>) {
+        self.report.skipped.push(SkippedItem {
+            name: name.into(),
+            reason: reason.into(),
+        });
+    }
+
+    fn skip_attachment(&mut self, name: impl Into<String>) {
+        self.skip(name, "ruslin-data cannot store attachments yet");
+    }
+
+    fn not_kept(&mut self, what: &'static str) {
+        self.report.not_kept.insert(what);
+    }
+
+    fn create_folder(&mut self, title: String, parent_id: Option<&str>) -> ImportResult<String> {
+        let folder = Folder::new(title, parent_id.map(str::to_string));
+        self.data.db.replace_folder(&folder)?;
+        self.report.folders += 1;
+        Ok(folder.id)
+    }
+
+    // A note that can't be saved is reported as skipped and the import goes on.
+    fn create_note(
+        &mut self,
+        name: &str,
+        folder_id: &str,
+        title: String,
+        body: String,
+    ) -> Option<Note> {
+        let note = Note::new(Some(folder_id.to_string()), title, body);
+        match self.data.db.replace_note(&note, UpdateSource::LocalEdit) {
+            Ok(_) => {
+                self.report.notes += 1;
+                Some(note)
+            }
+            Err(e) => {
+                self.skip(name, e.to_string());
+                None
+            }
+        }
+    }
+
+    fn update_note(&mut self, note: &Note) {
+        if let Err(e) = self.data.db.replace_note(note, UpdateSource::LocalEdit) {
+            self.skip(
+                note.get_title(),
+                format!("links to other imported notes not updated: {e}"),
+            );
+        }
+    }
+}
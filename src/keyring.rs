@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use gtk::{gio, glib};
+use libsecret::{Schema, SchemaAttributeType, SchemaFlags};
+use relm4::gtk;
+
+use crate::config::APP_ID;
+
+// Joplin Server credentials, looked up by server and account.
+fn schema() -> Schema {
+    let mut attributes = HashMap::new();
+    attributes.insert("host", SchemaAttributeType::String);
+    attributes.insert("email", SchemaAttributeType::String);
+    Schema::new(APP_ID, SchemaFlags::NONE, attributes)
+}
+
+fn attributes<'a>(host: &'a str, email: &'a str) -> HashMap<&'a str, &'a str> {
+    HashMap::from([("host", host), ("email", email)])
+}
+
+// These talk to the Secret Service over D-Bus and block, call them off the main thread.
+pub fn store_password(host: &str, email: &str, password: &str) -> Result<(), glib::Error> {
+    libsecret::password_store_sync(
+        Some(&schema()),
+        attributes(host, email),
+        Some(libsecret::COLLECTION_DEFAULT),
+        &format!("Joplin Server password for {email}"),
+        password,
+        gio::Cancellable::NONE,
+    )
+}
+
+pub fn lookup_password(host: &str, email: &str) -> Result<Option<String>, glib::Error> {
+    let password = libsecret::password_lookup_sync(
+        Some(&schema()),
+        attributes(host, email),
+        gio::Cancellable::NONE,
+    )?;
+    Ok(password.map(|password| password.to_string()))
+}
@@ -2,12 +2,13 @@ use adw::prelude::*;
 use relm4::{gtk, Component, ComponentParts, ComponentSender, RelmWidgetExt};
 use ruslin_data::sync::{SyncConfig, SyncResult};
 
-use crate::AppContext;
+use crate::{e2ee, keyring, sync_check, AppContext};
 
 pub struct LoginPageModel {
     url: String,
     email: String,
     password: String,
+    found_password: Option<String>,
     ctx: AppContext,
 }
 
@@ -16,12 +17,19 @@ pub enum LoginPageInput {
     ChangeUrl(String),
     ChangeEmail(String),
     ChangePassword(String),
+    LookupPassword,
     Login,
 }
 
 #[derive(Debug)]
 pub enum LoginPageCommandOutput {
     LoginResult(SyncResult<()>),
+    EncryptedTarget,
+    FoundPassword {
+        url: String,
+        email: String,
+        password: String,
+    },
 }
 
 #[derive(Debug)]
@@ -81,6 +89,7 @@ impl Component for LoginPageModel {
                                 sender.input(LoginPageInput::ChangeEmail(entry_row.text().to_string()));
                             }
                         },
+                        #[name = "password_row"]
                         adw::PasswordEntryRow {
                             set_title: "Password",
                             connect_text_notify[sender] => move |entry_row| {
@@ -106,6 +115,14 @@ impl Component for LoginPageModel {
         }
     }
 
+    fn pre_view() {
+        if let Some(password) = &model.found_password {
+            if password_row.text().is_empty() {
+                password_row.set_text(password);
+            }
+        }
+    }
+
     fn init(
         init: Self::Init,
         root: &Self::Root,
@@ -115,6 +132,7 @@ impl Component for LoginPageModel {
             url: String::new(),
             email: String::new(),
             password: String::new(),
+            found_password: None,
             ctx: init,
         };
 
@@ -127,22 +145,83 @@ impl Component for LoginPageModel {
         match input {
             LoginPageInput::ChangeUrl(url) => {
                 self.url = url;
+                sender.input(LoginPageInput::LookupPassword);
             }
             LoginPageInput::ChangeEmail(email) => {
                 self.email = email;
+                sender.input(LoginPageInput::LookupPassword);
             }
             LoginPageInput::ChangePassword(password) => {
+                if self.found_password.as_ref() != Some(&password) {
+                    self.found_password = None;
+                }
                 self.password = password;
             }
+            LoginPageInput::LookupPassword => {
+                if self.url.is_empty() || self.email.is_empty() || !self.password.is_empty() {
+                    return;
+                }
+                let url = self.url.clone();
+                let email = self.email.clone();
+                sender.oneshot_command(async move {
+                    let lookup = {
+                        let (url, email) = (url.clone(), email.clone());
+                        tokio::task::spawn_blocking(move || keyring::lookup_password(&url, &email))
+                            .await
+                    };
+                    let password = match lookup {
+                        Ok(Ok(password)) => password.unwrap_or_default(),
+                        Ok(Err(e)) => {
+                            log::debug!("keyring lookup failed: {e}");
+                            String::new()
+                        }
+                        Err(e) => {
+                            log::debug!("keyring lookup failed: {e}");
+                            String::new()
+                        }
+                    };
+                    LoginPageCommandOutput::FoundPassword {
+                        url,
+                        email,
+                        password,
+                    }
+                });
+            }
             LoginPageInput::Login => {
+                let host = self.url.clone();
+                let email = self.email.clone();
+                let password = self.password.clone();
                 let sync_config = SyncConfig::JoplinServer {
-                    host: self.url.clone(),
-                    email: self.email.clone(),
-                    password: self.password.clone(),
+                    host: host.clone(),
+                    email: email.clone(),
+                    password: password.clone(),
                 };
                 let data = self.ctx.data.clone();
                 sender.oneshot_command(async move {
-                    LoginPageCommandOutput::LoginResult(data.save_sync_config(sync_config).await)
+                    // Items on an encrypted target can't be read here, and our plain uploads
+                    // would sit next to them, so don't sync with it at all.
+                    match e2ee::target_is_encrypted(&host, &email, &password).await {
+                        Ok(true) => return LoginPageCommandOutput::EncryptedTarget,
+                        Ok(false) => {}
+                        // save_sync_config signs in as well and reports the failure.
+                        Err(e) => log::warn!("couldn't read the sync target info: {e}"),
+                    }
+                    let result = data.save_sync_config(sync_config).await;
+                    if result.is_ok() {
+                        // ruslin-data still keeps its own copy, the keyring lets us fill the form again.
+                        let stored = tokio::task::spawn_blocking(move || {
+                            keyring::store_password(&host, &email, &password)
+                        })
+                        .await;
+                        match stored {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => {
+                                log::warn!("no keyring available, password not stored: {e}")
+                            }
+                            Err(e) => log::warn!("no keyring available, password not stored: {e}"),
+                        }
+                    }
+                    LoginPageCommandOutput::LoginResult(result)
                 });
             }
         }
@@ -152,11 +231,42 @@ impl Component for LoginPageModel {
         &mut self,
         message: Self::CommandOutput,
         sender: ComponentSender<Self>,
-        _root: &Self::Root,
+        root: &Self::Root,
     ) {
         match message {
+            LoginPageCommandOutput::EncryptedTarget => {
+                let window = root
+                    .root()
+                    .and_then(|root| root.downcast::<gtk::Window>().ok());
+                let dialog = adw::MessageDialog::new(
+                    window.as_ref(),
+                    Some("Encrypted Sync Target"),
+                    Some("End-to-end encryption is turned on for this account. Ruslin can't decrypt Joplin notes yet, so it won't sync with it. Turn off encryption in Joplin to use this account here."),
+                );
+                dialog.add_response("close", "_Close");
+                dialog.present();
+            }
+            LoginPageCommandOutput::FoundPassword {
+                url,
+                email,
+                password,
+            } => {
+                // Only fill in the password if the form still matches the lookup.
+                if !password.is_empty()
+                    && self.password.is_empty()
+                    && url == self.url
+                    && email == self.email
+                {
+                    self.found_password = Some(password);
+                }
+            }
             LoginPageCommandOutput::LoginResult(result) => match result {
                 Ok(_) => {
+                    if let Err(e) =
+                        sync_check::save_server(self.ctx.profile.as_deref(), &self.url, &self.email)
+                    {
+                        log::warn!("save sync server failed: {e}");
+                    }
                     sender.output(LoginPageOutput::LoginSuccess).unwrap();
                 }
                 Err(e) => {
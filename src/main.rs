@@ -1,32 +1,101 @@
 #[rustfmt::skip]
 mod config;
 mod app;
+mod backlinks;
+mod cancel;
+mod bulk_change;
+mod capture;
+mod cli;
+mod clipper_server;
 mod components;
 mod content_page;
+mod data_dir;
+mod dbus;
+mod e2ee;
+mod export;
+mod favourites;
+mod folder_order;
 mod icons;
+mod import;
+mod keyring;
 mod login_page;
+mod markdown;
 mod modals;
+mod note_window;
+mod pending_changes;
+mod print;
 mod properties;
+mod saved_searches;
+mod search;
+mod search_provider;
 mod setup;
+mod spell;
+mod sync_check;
 
-use std::sync::Arc;
+use std::{
+    cell::OnceCell,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
 
-use gtk::prelude::ApplicationExt;
+use adw::prelude::*;
+use gtk::{gio, glib};
 use relm4::{
-    actions::{AccelsPlus, RelmAction, RelmActionGroup},
+    actions::{AccelsPlus, ActionName, RelmAction, RelmActionGroup},
     gtk, main_application, RelmApp,
 };
 
-use app::App;
 pub use app::AppContext;
-use ruslin_data::RuslinData;
+use app::{
+    App, DeleteNoteAction, FocusEditorAction, FocusNoteListAction, NewFolderAction, NewNoteAction,
+    NextNoteAction, PreviousNoteAction, PrintAction, SearchAction, SyncAction, TogglePreviewAction,
+    ToggleSidebarAction,
+};
 use setup::setup;
 
-use crate::{app::AppInit, config::APP_ID};
+use crate::{
+    app::{AppInit, AppMsg, SharedContext},
+    config::APP_ID,
+    data_dir::DataDir,
+};
 
 relm4::new_action_group!(AppActionGroup, "app");
 relm4::new_stateless_action!(QuitAction, AppActionGroup, "quit");
 
+relm4::new_stateless_action!(RemoteSyncAction, AppActionGroup, "sync");
+
+struct OpenNoteAction;
+
+impl ActionName for OpenNoteAction {
+    type Group = AppActionGroup;
+    type Target = String;
+    type State = ();
+
+    const NAME: &'static str = "open-note";
+}
+
+// (folder, title, body)
+struct CaptureNoteAction;
+
+impl ActionName for CaptureNoteAction {
+    type Group = AppActionGroup;
+    type Target = (String, String, String);
+    type State = ();
+
+    const NAME: &'static str = "new-note";
+}
+
+// (note id, text)
+struct AppendToNoteAction;
+
+impl ActionName for AppendToNoteAction {
+    type Group = AppActionGroup;
+    type Target = (String, String);
+    type State = ();
+
+    const NAME: &'static str = "append-to-note";
+}
+
 fn main() {
     #[cfg(debug_assertions)]
     let max_level = tracing::Level::DEBUG;
@@ -46,6 +115,40 @@ fn main() {
     app.set_application_id(Some(APP_ID));
     app.set_resource_base_path(Some("/org/dianqk/ruslin/"));
 
+    cli::add_main_options(&app);
+
+    let data_dir = match resolve_data_dir(&gio::Settings::new(APP_ID)) {
+        Ok(data_dir) => data_dir,
+        Err(e) => {
+            log::error!("{e}");
+            run_startup_error(&app, e);
+            return;
+        }
+    };
+    if data_dir.is_override {
+        // Another data directory runs as its own instance instead of raising the default one.
+        app.set_flags(app.flags() | gio::ApplicationFlags::NON_UNIQUE);
+    }
+
+    // Opened once this instance turns out to be the primary one, so `--help` and commands
+    // handed to a running instance never touch the database. Swapped by the window when
+    // switching profiles.
+    let app_context: Rc<OnceCell<SharedContext>> = Rc::default();
+
+    app.connect_handle_local_options(glib::clone!(@strong app_context => move |app, options| {
+        match cli::handle_local_options(app, options, &data_dir) {
+            cli::LocalOptions::Exit(status) => status,
+            cli::LocalOptions::Start => match start_primary(app, &data_dir, &app_context) {
+                Ok(()) => -1,
+                Err(e) => {
+                    log::error!("{e}");
+                    show_startup_error(&e);
+                    1
+                }
+            },
+        }
+    }));
+
     let actions = RelmActionGroup::<AppActionGroup>::new();
 
     let quit_action = {
@@ -56,19 +159,146 @@ fn main() {
     };
     actions.add_action(&quit_action);
 
+    let (app_sender, app_receiver) = relm4::channel();
+
+    let open_note_action = {
+        let sender = app_sender.clone();
+        RelmAction::<OpenNoteAction>::new_with_target_value(move |_, id| {
+            sender.send(AppMsg::OpenNote { id }).unwrap();
+        })
+    };
+    actions.add_action(&open_note_action);
+
+    let remote_sync_action = {
+        let sender = app_sender;
+        RelmAction::<RemoteSyncAction>::new_stateless(move |_| {
+            sender.send(AppMsg::Sync).unwrap();
+        })
+    };
+    actions.add_action(&remote_sync_action);
+
+    // These work without a window, so scripts can capture notes into a running service instance.
+    let capture_note_action = {
+        let ctx = app_context.clone();
+        RelmAction::<CaptureNoteAction>::new_with_target_value(move |_, (folder, title, body)| {
+            let Some(ctx) = ctx.get() else {
+                return;
+            };
+            let ctx = ctx.read().unwrap();
+            match capture::create_note(&ctx.data, &folder, &title, &body) {
+                Ok(note) => capture::notify_changed(&ctx, note.id),
+                Err(e) => log::warn!("new note failed: {e}"),
+            }
+        })
+    };
+    actions.add_action(&capture_note_action);
+
+    let append_to_note_action = {
+        let ctx = app_context.clone();
+        RelmAction::<AppendToNoteAction>::new_with_target_value(move |_, (id, text)| {
+            let Some(ctx) = ctx.get() else {
+                return;
+            };
+            let ctx = ctx.read().unwrap();
+            match capture::append_to_note(&ctx.data, &id, &text) {
+                Ok(note) => capture::notify_changed(&ctx, note.id),
+                Err(e) => log::warn!("append to note {id} failed: {e}"),
+            }
+        })
+    };
+    actions.add_action(&append_to_note_action);
+
     app.set_accelerators_for_action::<QuitAction>(&["<Control>q"]);
+    app.set_accelerators_for_action::<NewNoteAction>(&["<Control>n"]);
+    app.set_accelerators_for_action::<NewFolderAction>(&["<Control><Shift>n"]);
+    app.set_accelerators_for_action::<SearchAction>(&["<Control>f"]);
+    app.set_accelerators_for_action::<SyncAction>(&["<Control>r", "F5"]);
+    app.set_accelerators_for_action::<ToggleSidebarAction>(&["F9"]);
+    app.set_accelerators_for_action::<TogglePreviewAction>(&["<Control>l"]);
+    app.set_accelerators_for_action::<PrintAction>(&["<Control>p"]);
+    app.set_accelerators_for_action::<DeleteNoteAction>(&["<Control>Delete"]);
+    app.set_accelerators_for_action::<FocusNoteListAction>(&["<Control>1"]);
+    app.set_accelerators_for_action::<FocusEditorAction>(&["<Control>2"]);
+    app.set_accelerators_for_action::<NextNoteAction>(&["<Control>Page_Down", "<Alt>Down"]);
+    app.set_accelerators_for_action::<PreviousNoteAction>(&["<Control>Page_Up", "<Alt>Up"]);
 
     app.set_action_group(Some(&actions.into_action_group()));
 
     let app = RelmApp::with_app(app);
 
-    let data_dir = dirs::data_dir().unwrap();
-    log::info!("data dir: {}", data_dir.display());
-    let resources_dir = data_dir.join("resources");
+    // The arguments are parsed by `cli`, through the application's main options.
+    let args: Vec<String> = std::env::args().collect();
+    app.run_with_args::<App, String>(
+        AppInit {
+            ctx: app_context,
+            messages: app_receiver,
+        },
+        &args,
+    );
+}
 
-    let app_context = AppContext {
-        data: Arc::new(RuslinData::new(&data_dir, &resources_dir).unwrap()),
-    };
+fn resolve_data_dir(settings: &gio::Settings) -> Result<DataDir, String> {
+    let last_profile = Some(settings.string("profile").to_string()).filter(|p| !p.is_empty());
+    let data_dir = data_dir::resolve(last_profile)
+        .map_err(|e| format!("Couldn't create the data folder: {e}"))?;
+    log::info!("data dir: {}", data_dir.path.display());
+    Ok(data_dir)
+}
+
+// Registers the application, the primary instance then opens the notes and serves them on D-Bus.
+// A remote instance leaves everything to the running one.
+fn start_primary(
+    app: &impl IsA<gio::Application>,
+    data_dir: &DataDir,
+    app_context: &OnceCell<SharedContext>,
+) -> Result<(), String> {
+    app.register(gio::Cancellable::NONE)
+        .map_err(|e| e.to_string())?;
+    if app.is_remote() {
+        return Ok(());
+    }
+    let ctx = AppContext::open(data_dir)?;
+    let app_context = app_context.get_or_init(|| Arc::new(RwLock::new(ctx)));
+    if let (Some(connection), Some(object_path)) = (app.dbus_connection(), app.dbus_object_path()) {
+        if let Err(e) = search_provider::register(&connection, &object_path, app_context.clone()) {
+            log::warn!("register search provider failed: {e}");
+        }
+        if let Err(e) = capture::register(&connection, &object_path, app_context.clone()) {
+            log::warn!("register notes interface failed: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn startup_error_dialog(message: &str) -> adw::MessageDialog {
+    let dialog = adw::MessageDialog::new(
+        gtk::Window::NONE,
+        Some("Ruslin Couldn't Start"),
+        Some(message),
+    );
+    dialog.add_response("quit", "_Quit");
+    dialog
+}
+
+// Runs the application with only an error dialog, which quits it when closed.
+fn run_startup_error(app: &adw::Application, message: String) {
+    app.connect_activate(move |app| {
+        let dialog = startup_error_dialog(&message);
+        dialog.connect_response(None, glib::clone!(@weak app => move |_, _| app.quit()));
+        app.add_window(&dialog);
+        dialog.present();
+    });
+    app.run_with_args::<&str>(&[]);
+}
 
-    app.run::<App>(AppInit { ctx: app_context });
+// For errors after the application registered, before its main loop runs.
+fn show_startup_error(message: &str) {
+    let main_loop = glib::MainLoop::new(None, false);
+    let dialog = startup_error_dialog(message);
+    dialog.connect_response(
+        None,
+        glib::clone!(@strong main_loop => move |_, _| main_loop.quit()),
+    );
+    dialog.present();
+    main_loop.run();
 }
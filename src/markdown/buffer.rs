@@ -0,0 +1,276 @@
+use gtk::prelude::*;
+use relm4::gtk;
+
+use super::editing::{self, ListItem, ListMarker};
+
+pub trait MarkdownBufferExt {
+    fn toggle_inline_format(&self, marker: &str);
+    fn insert_link(&self);
+    fn continue_list(&self) -> bool;
+    fn toggle_checkbox(&self);
+    fn cycle_heading(&self);
+    fn format_table(&self);
+}
+
+impl<T: IsA<gtk::TextBuffer>> MarkdownBufferExt for T {
+    fn toggle_inline_format(&self, marker: &str) {
+        let buffer = self.as_ref();
+        let marker_len = marker.chars().count() as i32;
+        buffer.begin_user_action();
+        match buffer.selection_bounds() {
+            Some((mut start, mut end)) => {
+                let text = buffer.text(&start, &end, false);
+                let mut outer_start = start.clone();
+                let mut outer_end = end.clone();
+                outer_start.backward_chars(marker_len);
+                outer_end.forward_chars(marker_len);
+                let marker_char = marker.chars().next().unwrap_or_default();
+                if editing::is_wrapped_in(&text, marker) {
+                    let inner = text[marker.len()..text.len() - marker.len()].to_string();
+                    buffer.delete(&mut start, &mut end);
+                    buffer.insert(&mut start, &inner);
+                    select_backward(buffer, &start, inner.chars().count() as i32);
+                } else if editing::is_marker_run(run_before(&start, marker_char), marker)
+                    && editing::is_marker_run(run_after(&end, marker_char), marker)
+                {
+                    let text = text.to_string();
+                    buffer.delete(&mut outer_start, &mut outer_end);
+                    buffer.insert(&mut outer_start, &text);
+                    select_backward(buffer, &outer_start, text.chars().count() as i32);
+                } else {
+                    let wrapped = format!("{marker}{text}{marker}");
+                    buffer.delete(&mut start, &mut end);
+                    buffer.insert(&mut start, &wrapped);
+                    start.backward_chars(marker_len);
+                    select_backward(buffer, &start, text.chars().count() as i32);
+                }
+            }
+            None => {
+                buffer.insert_at_cursor(&format!("{marker}{marker}"));
+                let mut cursor = buffer.iter_at_mark(&buffer.get_insert());
+                cursor.backward_chars(marker_len);
+                buffer.place_cursor(&cursor);
+            }
+        }
+        buffer.end_user_action();
+    }
+
+    fn insert_link(&self) {
+        let buffer = self.as_ref();
+        buffer.begin_user_action();
+        match buffer.selection_bounds() {
+            Some((mut start, mut end)) => {
+                let text = buffer.text(&start, &end, false);
+                let is_url = text.contains("://");
+                let link = if is_url {
+                    format!("[]({text})")
+                } else {
+                    format!("[{text}]()")
+                };
+                buffer.delete(&mut start, &mut end);
+                buffer.insert(&mut start, &link);
+                // Leave the cursor where the missing half of the link goes.
+                let offset = if is_url {
+                    link.chars().count() as i32 - 2
+                } else {
+                    1
+                };
+                start.backward_chars(offset);
+                buffer.place_cursor(&start);
+            }
+            None => {
+                buffer.insert_at_cursor("[]()");
+                let mut cursor = buffer.iter_at_mark(&buffer.get_insert());
+                cursor.backward_chars(3);
+                buffer.place_cursor(&cursor);
+            }
+        }
+        buffer.end_user_action();
+    }
+
+    fn continue_list(&self) -> bool {
+        let buffer = self.as_ref();
+        if buffer.has_selection() {
+            return false;
+        }
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        let line_start = line_start(&cursor);
+        let line = buffer.text(&line_start, &line_end(&cursor), false);
+        let Some(item) = ListItem::parse(&line) else {
+            return false;
+        };
+        if editing::is_in_code_block(&buffer.text(&buffer.start_iter(), &line_start, false)) {
+            return false;
+        }
+        if cursor.line_offset() < item.prefix().chars().count() as i32 {
+            return false;
+        }
+
+        buffer.begin_user_action();
+        if item.content.trim().is_empty() {
+            // An empty item ends the list, like in most Markdown editors.
+            let mut start = line_start;
+            let mut end = line_end(&cursor);
+            buffer.delete(&mut start, &mut end);
+        } else {
+            let mut cursor = cursor;
+            buffer.insert(&mut cursor, &format!("\n{}", item.next_prefix()));
+            buffer.place_cursor(&cursor);
+            if matches!(item.marker, ListMarker::Ordered { .. }) {
+                renumber_list_at(buffer, cursor.line());
+            }
+        }
+        buffer.end_user_action();
+        true
+    }
+
+    fn toggle_checkbox(&self) {
+        let buffer = self.as_ref();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        replace_line(buffer, cursor.line(), editing::toggle_checkbox);
+    }
+
+    fn cycle_heading(&self) {
+        let buffer = self.as_ref();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        replace_line(buffer, cursor.line(), editing::cycle_heading);
+    }
+
+    fn format_table(&self) {
+        let buffer = self.as_ref();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        if !editing::is_table_line(&line_text(buffer, cursor.line())) {
+            return;
+        }
+        let mut first_line = cursor.line();
+        while first_line > 0 && editing::is_table_line(&line_text(buffer, first_line - 1)) {
+            first_line -= 1;
+        }
+        let mut last_line = cursor.line();
+        while last_line + 1 < buffer.line_count()
+            && editing::is_table_line(&line_text(buffer, last_line + 1))
+        {
+            last_line += 1;
+        }
+        let lines: Vec<String> = (first_line..=last_line)
+            .map(|line| line_text(buffer, line))
+            .collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        if let Some(formatted) = editing::format_table(&lines) {
+            replace_lines(buffer, first_line, last_line, &formatted.join("\n"));
+        }
+    }
+}
+
+fn select_backward(buffer: &gtk::TextBuffer, end: &gtk::TextIter, chars: i32) {
+    let mut start = end.clone();
+    start.backward_chars(chars);
+    buffer.select_range(&start, end);
+}
+
+// The number of `c` right before `iter`.
+fn run_before(iter: &gtk::TextIter, c: char) -> usize {
+    let mut iter = iter.clone();
+    let mut run = 0;
+    while iter.backward_char() && iter.char() == c {
+        run += 1;
+    }
+    run
+}
+
+fn run_after(iter: &gtk::TextIter, c: char) -> usize {
+    let mut iter = iter.clone();
+    let mut run = 0;
+    while iter.char() == c {
+        run += 1;
+        if !iter.forward_char() {
+            break;
+        }
+    }
+    run
+}
+
+fn line_start(iter: &gtk::TextIter) -> gtk::TextIter {
+    let mut start = iter.clone();
+    start.set_line_offset(0);
+    start
+}
+
+fn line_end(iter: &gtk::TextIter) -> gtk::TextIter {
+    let mut end = iter.clone();
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+    end
+}
+
+fn line_text(buffer: &gtk::TextBuffer, line: i32) -> String {
+    match buffer.iter_at_line(line) {
+        Some(start) => buffer.text(&start, &line_end(&start), false).to_string(),
+        None => String::new(),
+    }
+}
+
+fn replace_lines(buffer: &gtk::TextBuffer, first_line: i32, last_line: i32, text: &str) {
+    let (Some(mut start), Some(last)) = (
+        buffer.iter_at_line(first_line),
+        buffer.iter_at_line(last_line),
+    ) else {
+        return;
+    };
+    let mut end = line_end(&last);
+    buffer.begin_user_action();
+    buffer.delete(&mut start, &mut end);
+    buffer.insert(&mut start, text);
+    buffer.end_user_action();
+}
+
+fn replace_line(buffer: &gtk::TextBuffer, line: i32, f: impl Fn(&str) -> String) {
+    let text = line_text(buffer, line);
+    let replaced = f(&text);
+    if replaced != text {
+        replace_lines(buffer, line, line, &replaced);
+    }
+}
+
+fn renumber_list_at(buffer: &gtk::TextBuffer, line: i32) {
+    let Some(item) = ListItem::parse(&line_text(buffer, line)) else {
+        return;
+    };
+    // Walk back to the first item of this list so the whole list is renumbered.
+    let mut first_line = line;
+    while first_line > 0 {
+        match ListItem::parse(&line_text(buffer, first_line - 1)) {
+            Some(previous) if previous.indent.len() >= item.indent.len() => first_line -= 1,
+            _ => break,
+        }
+    }
+    while ListItem::parse(&line_text(buffer, first_line))
+        .map(|first| first.indent.len() != item.indent.len())
+        .unwrap_or(false)
+    {
+        first_line += 1;
+    }
+
+    let lines: Vec<String> = (first_line..buffer.line_count())
+        .map(|line| line_text(buffer, line))
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let renumbered = editing::renumber_list(&lines);
+    if renumbered.is_empty() || renumbered.iter().zip(lines.iter()).all(|(a, b)| a == b) {
+        return;
+    }
+
+    let cursor_line = line;
+    let cursor_offset = buffer.iter_at_mark(&buffer.get_insert()).line_offset();
+    replace_lines(
+        buffer,
+        first_line,
+        first_line + renumbered.len() as i32 - 1,
+        &renumbered.join("\n"),
+    );
+    if let Some(mut cursor) = buffer.iter_at_line(cursor_line) {
+        cursor.forward_chars(cursor_offset);
+        buffer.place_cursor(&cursor);
+    }
+}
@@ -0,0 +1,429 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListMarker {
+    Bullet(char),
+    Ordered { number: u64, delimiter: char },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem<'a> {
+    pub indent: &'a str,
+    pub marker: ListMarker,
+    pub checkbox: Option<bool>,
+    pub content: &'a str,
+}
+
+impl<'a> ListItem<'a> {
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let content_start = line.len() - line.trim_start().len();
+        let (indent, rest) = line.split_at(content_start);
+
+        let (marker, rest) = match rest.chars().next()? {
+            c @ ('-' | '*' | '+') => (ListMarker::Bullet(c), &rest[1..]),
+            c if c.is_ascii_digit() => {
+                let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+                let delimiter = rest[digits..].chars().next()?;
+                if delimiter != '.' && delimiter != ')' {
+                    return None;
+                }
+                let number = rest[..digits].parse().ok()?;
+                (
+                    ListMarker::Ordered { number, delimiter },
+                    &rest[digits + 1..],
+                )
+            }
+            _ => return None,
+        };
+
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+        let rest = rest.strip_prefix(' ').unwrap_or(rest);
+
+        let (checkbox, content) = if let Some(content) = rest.strip_prefix("[ ]") {
+            (Some(false), content)
+        } else if let Some(content) = rest
+            .strip_prefix("[x]")
+            .or_else(|| rest.strip_prefix("[X]"))
+        {
+            (Some(true), content)
+        } else {
+            (None, rest)
+        };
+        let content = if checkbox.is_some() {
+            content.strip_prefix(' ').unwrap_or(content)
+        } else {
+            content
+        };
+
+        Some(Self {
+            indent,
+            marker,
+            checkbox,
+            content,
+        })
+    }
+
+    pub fn prefix(&self) -> String {
+        let marker = match &self.marker {
+            ListMarker::Bullet(c) => c.to_string(),
+            ListMarker::Ordered { number, delimiter } => format!("{number}{delimiter}"),
+        };
+        match self.checkbox {
+            Some(true) => format!("{}{marker} [x] ", self.indent),
+            Some(false) => format!("{}{marker} [ ] ", self.indent),
+            None => format!("{}{marker} ", self.indent),
+        }
+    }
+
+    // The prefix of the item that follows this one, with checkboxes left unchecked.
+    pub fn next_prefix(&self) -> String {
+        let marker = match &self.marker {
+            ListMarker::Ordered { number, delimiter } => ListMarker::Ordered {
+                number: number + 1,
+                delimiter: *delimiter,
+            },
+            marker => marker.clone(),
+        };
+        Self {
+            indent: self.indent,
+            marker,
+            checkbox: self.checkbox.map(|_| false),
+            content: "",
+        }
+        .prefix()
+    }
+
+    pub fn to_line(&self) -> String {
+        format!("{}{}", self.prefix(), self.content)
+    }
+}
+
+// Renumbers the ordered list starting at `lines[0]`, leaving nested items untouched.
+// Returns the rewritten lines, which cover the list up to its last item.
+pub fn renumber_list(lines: &[&str]) -> Vec<String> {
+    let Some(first) = lines.first().and_then(|line| ListItem::parse(line)) else {
+        return Vec::new();
+    };
+    let ListMarker::Ordered { number, .. } = first.marker else {
+        return Vec::new();
+    };
+    let indent = first.indent.len();
+    let mut next_number = number;
+    let mut renumbered = Vec::new();
+
+    for line in lines {
+        match ListItem::parse(line) {
+            Some(item) if item.indent.len() == indent => match item.marker {
+                ListMarker::Ordered { delimiter, .. } => {
+                    renumbered.push(
+                        ListItem {
+                            marker: ListMarker::Ordered {
+                                number: next_number,
+                                delimiter,
+                            },
+                            ..item
+                        }
+                        .to_line(),
+                    );
+                    next_number += 1;
+                }
+                ListMarker::Bullet(_) => break,
+            },
+            Some(item) if item.indent.len() > indent => renumbered.push(line.to_string()),
+            _ if line.starts_with(&" ".repeat(indent + 1)) => renumbered.push(line.to_string()),
+            _ => break,
+        }
+    }
+
+    renumbered
+}
+
+// Whether `text` starts and ends with `marker`. A `*` doesn't wrap `**bold**`, whose asterisks all
+// belong to the bold marker, but does wrap `***both***`.
+pub fn is_wrapped_in(text: &str, marker: &str) -> bool {
+    let Some(c) = marker.chars().next() else {
+        return false;
+    };
+    let leading = text.chars().take_while(|x| *x == c).count();
+    let trailing = text.chars().rev().take_while(|x| *x == c).count();
+    text.chars().count() >= 2 * marker.chars().count()
+        && is_marker_run(leading, marker)
+        && is_marker_run(trailing, marker)
+}
+
+// Whether a run of `run` marker characters contains `marker` as a whole emphasis marker.
+pub fn is_marker_run(run: usize, marker: &str) -> bool {
+    let len = marker.chars().count();
+    run == len || (run == 3 && len < 3 && !marker.starts_with('`'))
+}
+
+// Whether the line after `text_before` lies inside a fenced code block.
+pub fn is_in_code_block(text_before: &str) -> bool {
+    let mut fence: Option<&str> = None;
+    for line in text_before.lines() {
+        let line = line.trim_start();
+        match fence {
+            Some(open) if line.starts_with(open) => fence = None,
+            None if line.starts_with("```") => fence = Some("```"),
+            None if line.starts_with("~~~") => fence = Some("~~~"),
+            _ => {}
+        }
+    }
+    fence.is_some()
+}
+
+pub fn toggle_checkbox(line: &str) -> String {
+    match ListItem::parse(line) {
+        Some(item) => ListItem {
+            checkbox: Some(!item.checkbox.unwrap_or(true)),
+            ..item
+        }
+        .to_line(),
+        None => {
+            let content_start = line.len() - line.trim_start().len();
+            let (indent, content) = line.split_at(content_start);
+            format!("{indent}- [ ] {content}")
+        }
+    }
+}
+
+// Cycles a line through `# `, `## `, ... `###### ` and back to plain text.
+pub fn cycle_heading(line: &str) -> String {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let content = if level > 0 && line[level..].starts_with(' ') {
+        &line[level + 1..]
+    } else if level > 0 && line.len() == level {
+        ""
+    } else {
+        return format!("# {line}");
+    };
+    if level >= 6 {
+        content.to_string()
+    } else {
+        format!("{} {content}", "#".repeat(level + 1))
+    }
+}
+
+pub fn is_table_line(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+fn split_table_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn parse_alignment(cell: &str) -> Option<Alignment> {
+    let dashes = cell.trim_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    })
+}
+
+// Pads every cell so the columns line up, honouring the delimiter row's alignment.
+pub fn format_table(lines: &[&str]) -> Option<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = lines.iter().map(|line| split_table_row(line)).collect();
+    let alignments: Vec<Alignment> = rows
+        .get(1)?
+        .iter()
+        .map(|cell| parse_alignment(cell))
+        .collect::<Option<_>>()?;
+    let columns = rows.iter().map(Vec::len).max()?.max(alignments.len());
+    for row in rows.iter_mut() {
+        row.resize(columns, String::new());
+    }
+
+    let width = |cell: &str| cell.chars().count();
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .enumerate()
+                .filter(|(index, _)| *index != 1)
+                .map(|(_, row)| width(&row[column]))
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+
+    let formatted = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .enumerate()
+                .map(|(column, (cell, &cell_width))| {
+                    let alignment = alignments.get(column).copied().unwrap_or(Alignment::None);
+                    if index == 1 {
+                        return match alignment {
+                            Alignment::None => "-".repeat(cell_width),
+                            Alignment::Left => format!(":{}", "-".repeat(cell_width - 1)),
+                            Alignment::Right => format!("{}:", "-".repeat(cell_width - 1)),
+                            Alignment::Center => format!(":{}:", "-".repeat(cell_width - 2)),
+                        };
+                    }
+                    let padding = cell_width - width(cell);
+                    match alignment {
+                        Alignment::Right => format!("{}{cell}", " ".repeat(padding)),
+                        Alignment::Center => format!(
+                            "{}{cell}{}",
+                            " ".repeat(padding / 2),
+                            " ".repeat(padding - padding / 2)
+                        ),
+                        Alignment::None | Alignment::Left => {
+                            format!("{cell}{}", " ".repeat(padding))
+                        }
+                    }
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        })
+        .collect();
+
+    Some(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list_items() {
+        let item = ListItem::parse("  - [x] done").unwrap();
+        assert_eq!(item.indent, "  ");
+        assert_eq!(item.marker, ListMarker::Bullet('-'));
+        assert_eq!(item.checkbox, Some(true));
+        assert_eq!(item.content, "done");
+
+        let item = ListItem::parse("12) twelve").unwrap();
+        assert_eq!(
+            item.marker,
+            ListMarker::Ordered {
+                number: 12,
+                delimiter: ')'
+            }
+        );
+        assert_eq!(item.checkbox, None);
+        assert_eq!(item.content, "twelve");
+        assert_eq!(item.next_prefix(), "13) ");
+
+        assert_eq!(ListItem::parse("-").unwrap().content, "");
+        assert_eq!(ListItem::parse("-not a list"), None);
+        assert_eq!(ListItem::parse("1.5 apples"), None);
+        assert_eq!(ListItem::parse("plain text"), None);
+    }
+
+    #[test]
+    fn next_prefix_unchecks_checkboxes() {
+        let item = ListItem::parse("* [x] done").unwrap();
+        assert_eq!(item.next_prefix(), "* [ ] ");
+    }
+
+    #[test]
+    fn renumber_ordered_list() {
+        let lines = [
+            "3. a",
+            "   nested text",
+            "1. b",
+            "  - sub",
+            "7. c",
+            "",
+            "1. next",
+        ];
+        assert_eq!(
+            renumber_list(&lines),
+            ["3. a", "   nested text", "4. b", "  - sub", "5. c"]
+        );
+        assert!(renumber_list(&["- a", "- b"]).is_empty());
+        assert_eq!(renumber_list(&["1. a", "- b"]), ["1. a"]);
+    }
+
+    #[test]
+    fn cycle_headings() {
+        assert_eq!(cycle_heading("Title"), "# Title");
+        assert_eq!(cycle_heading("# Title"), "## Title");
+        assert_eq!(cycle_heading("###### Title"), "Title");
+        assert_eq!(cycle_heading("#"), "## ");
+        assert_eq!(cycle_heading("#hashtag"), "# #hashtag");
+    }
+
+    #[test]
+    fn toggle_checkboxes() {
+        assert_eq!(toggle_checkbox("- [ ] task"), "- [x] task");
+        assert_eq!(toggle_checkbox("- [x] task"), "- [ ] task");
+        assert_eq!(toggle_checkbox("- item"), "- [ ] item");
+        assert_eq!(toggle_checkbox("  text"), "  - [ ] text");
+    }
+
+    #[test]
+    fn format_tables() {
+        let lines = ["|a|long header|", "|:-|-:|", "|wide cell|b|"];
+        assert_eq!(
+            format_table(&lines).unwrap(),
+            [
+                "| a         | long header |",
+                "| :-------- | ----------: |",
+                "| wide cell |           b |",
+            ]
+        );
+        assert_eq!(
+            format_table(&["| a | b |", "|:-:|---|", "| c |"]).unwrap(),
+            ["|  a  | b   |", "| :-: | --- |", "|  c  |     |"]
+        );
+        assert_eq!(format_table(&["| a |", "| b |"]), None);
+        assert_eq!(format_table(&["| a |"]), None);
+    }
+
+    #[test]
+    fn inline_markers() {
+        assert!(is_wrapped_in("*italic*", "*"));
+        assert!(!is_wrapped_in("**bold**", "*"));
+        assert!(is_wrapped_in("**bold**", "**"));
+        assert!(is_wrapped_in("***both***", "*"));
+        assert!(is_wrapped_in("***both***", "**"));
+        assert!(!is_wrapped_in("*italic*", "**"));
+        assert!(is_wrapped_in("`code`", "`"));
+        assert!(!is_wrapped_in("*", "*"));
+        assert!(!is_wrapped_in("plain", "*"));
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert!(!is_in_code_block("- item\n"));
+        assert!(is_in_code_block("text\n```rust\n"));
+        assert!(!is_in_code_block("```\n- a\n```\n"));
+        assert!(is_in_code_block("~~~\n```\n"));
+        assert!(!is_in_code_block("  ```\ncode\n  ```"));
+    }
+}
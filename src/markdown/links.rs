@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{Event, Tag};
+
+// Joplin links to other items as `:/<32 hex digit id>`.
+pub fn note_id_from_url(url: &str) -> Option<&str> {
+    let id = url.strip_prefix(":/")?;
+    let id = id.split('#').next().unwrap_or(id);
+    (id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())).then_some(id)
+}
+
+pub fn note_link(title: &str, id: &str) -> String {
+    let title = title.replace('[', "\\[").replace(']', "\\]");
+    format!("[{title}](:/{id})")
+}
+
+// Replaces every `:/<id>` item link for which `f` returns a new destination.
+pub fn replace_item_links(body: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(index) = rest.find(":/") {
+        result.push_str(&rest[..index]);
+        let candidate = &rest[index..];
+        let replacement = candidate
+            .get(..34)
+            .and_then(note_id_from_url)
+            .and_then(&mut f);
+        match replacement {
+            Some(replacement) => {
+                result.push_str(&replacement);
+                rest = &candidate[34..];
+            }
+            None => {
+                result.push_str(":/");
+                rest = &candidate[2..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// The ids of the items the body's links and images point to.
+pub fn linked_item_ids(body: &str) -> HashSet<String> {
+    super::parser(body)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link(_, url, _) | Tag::Image(_, url, _)) => {
+                note_id_from_url(&url).map(str::to_string)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Returns the destination of the link that covers the byte `offset` of `text`, if any.
+pub fn link_at(text: &str, offset: usize) -> Option<String> {
+    super::parser(text)
+        .into_offset_iter()
+        .find_map(|(event, range)| match event {
+            Event::Start(Tag::Link(_, url, _)) if range.contains(&offset) => Some(url.to_string()),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn linked_ids_are_link_targets() {
+        let body = format!("[a](:/{ID}) ![b](:/{ID}#top) `:/{ID}`\n\n    :/{ID}\n");
+        assert_eq!(linked_item_ids(&body), HashSet::from([ID.to_string()]));
+        assert!(linked_item_ids(&format!("see :/{ID} or `[a](:/{ID})`")).is_empty());
+        assert!(linked_item_ids("[a](:/not-an-id)").is_empty());
+    }
+}
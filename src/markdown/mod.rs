@@ -0,0 +1,33 @@
+mod buffer;
+mod editing;
+mod links;
+mod pango;
+
+pub use buffer::MarkdownBufferExt;
+pub use links::{link_at, linked_item_ids, note_id_from_url, note_link, replace_item_links};
+pub use pango::{to_pango_markup, to_print_markup};
+
+use pulldown_cmark::{Event, Options, Parser};
+
+pub fn parser(text: &str) -> Parser {
+    Parser::new_ext(
+        text,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+}
+
+// Mirrors Joplin's `Note.defaultTitle`: the first non-empty line of the body, without Markdown.
+pub fn title_from_body(body: &str) -> String {
+    let first_line = body
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let mut title = String::new();
+    for event in parser(first_line) {
+        if let Event::Text(text) | Event::Code(text) = event {
+            title.push_str(&text);
+        }
+    }
+    title.trim().chars().take(80).collect()
+}
@@ -0,0 +1,135 @@
+use gtk::glib;
+use pulldown_cmark::{Event, HeadingLevel, Tag};
+use relm4::gtk;
+
+pub fn to_pango_markup(text: &str) -> String {
+    render(text, true)
+}
+
+// Links are only understood by GtkLabel, a plain pango layout needs them as styled text.
+pub fn to_print_markup(text: &str) -> String {
+    render(text, false)
+}
+
+// Pango markup only knows inline spans, so blocks are flattened into lines.
+fn render(text: &str, links: bool) -> String {
+    let mut markup = String::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut in_code_block = false;
+
+    for event in super::parser(text) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => {}
+                Tag::Heading(level, _, _) => {
+                    let size = match level {
+                        HeadingLevel::H1 => "xx-large",
+                        HeadingLevel::H2 => "x-large",
+                        HeadingLevel::H3 => "large",
+                        _ => "medium",
+                    };
+                    markup.push_str(&format!("<span size=\"{size}\" weight=\"bold\">"));
+                }
+                Tag::BlockQuote => markup.push_str("<i>"),
+                Tag::CodeBlock(_) => {
+                    in_code_block = true;
+                    markup.push_str("<tt>");
+                }
+                Tag::List(start) => {
+                    if !lists.is_empty() {
+                        markup.push('\n');
+                    }
+                    lists.push(start);
+                }
+                Tag::Item => {
+                    let depth = lists.len().saturating_sub(1);
+                    markup.push_str(&"    ".repeat(depth));
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            markup.push_str(&format!("{number}. "));
+                            *number += 1;
+                        }
+                        _ => markup.push_str("• "),
+                    }
+                }
+                Tag::FootnoteDefinition(label) => {
+                    markup.push_str(&format!("<sup>{}</sup> ", glib::markup_escape_text(&label)));
+                }
+                Tag::Table(_) | Tag::TableHead | Tag::TableRow => {}
+                Tag::TableCell => markup.push_str(" │ "),
+                Tag::Emphasis => markup.push_str("<i>"),
+                Tag::Strong => markup.push_str("<b>"),
+                Tag::Strikethrough => markup.push_str("<s>"),
+                Tag::Link(_, _, _) | Tag::Image(_, _, _) if !links => {
+                    markup.push_str("<span underline=\"single\">");
+                    if matches!(tag, Tag::Image(_, _, _)) {
+                        markup.push_str("🖼 ");
+                    }
+                }
+                Tag::Link(_, url, title) => {
+                    markup.push_str(&format!(
+                        "<a href=\"{}\" title=\"{}\">",
+                        glib::markup_escape_text(&url),
+                        glib::markup_escape_text(&title)
+                    ));
+                }
+                Tag::Image(_, url, _) => {
+                    markup.push_str(&format!(
+                        "<a href=\"{}\">🖼 ",
+                        glib::markup_escape_text(&url)
+                    ));
+                }
+            },
+            Event::End(tag) => match tag {
+                Tag::Paragraph => {
+                    markup.push('\n');
+                    if lists.is_empty() {
+                        markup.push('\n');
+                    }
+                }
+                Tag::Heading(_, _, _) => markup.push_str("</span>\n\n"),
+                Tag::BlockQuote => markup.push_str("</i>"),
+                Tag::CodeBlock(_) => {
+                    in_code_block = false;
+                    markup.push_str("</tt>\n");
+                }
+                Tag::List(_) => {
+                    lists.pop();
+                    if lists.is_empty() {
+                        markup.push('\n');
+                    }
+                }
+                Tag::Item => {
+                    if !markup.ends_with('\n') {
+                        markup.push('\n');
+                    }
+                }
+                Tag::FootnoteDefinition(_) => {}
+                Tag::Table(_) => markup.push('\n'),
+                Tag::TableHead | Tag::TableRow => markup.push_str(" │\n"),
+                Tag::TableCell => {}
+                Tag::Emphasis => markup.push_str("</i>"),
+                Tag::Strong => markup.push_str("</b>"),
+                Tag::Strikethrough => markup.push_str("</s>"),
+                Tag::Link(_, _, _) | Tag::Image(_, _, _) if !links => markup.push_str("</span>"),
+                Tag::Link(_, _, _) | Tag::Image(_, _, _) => markup.push_str("</a>"),
+            },
+            Event::Text(text) => markup.push_str(&glib::markup_escape_text(&text)),
+            Event::Code(code) => {
+                markup.push_str(&format!("<tt>{}</tt>", glib::markup_escape_text(&code)));
+            }
+            Event::Html(html) => markup.push_str(&glib::markup_escape_text(&html)),
+            Event::FootnoteReference(label) => {
+                markup.push_str(&format!("<sup>{}</sup>", glib::markup_escape_text(&label)));
+            }
+            Event::SoftBreak => markup.push(if in_code_block { '\n' } else { ' ' }),
+            Event::HardBreak => markup.push('\n'),
+            Event::Rule => markup.push_str("────────────────\n\n"),
+            Event::TaskListMarker(checked) => {
+                markup.push_str(if checked { "☑ " } else { "☐ " })
+            }
+        }
+    }
+
+    markup.trim_end().to_string()
+}
@@ -0,0 +1,451 @@
+use adw::prelude::*;
+use gtk::{gdk, gio, glib};
+use relm4::{gtk, Component, ComponentParts, ComponentSender};
+
+use crate::{
+    app::SharedContext,
+    clipper_server::{self, PORT},
+    config::APP_ID,
+    icons, keyring,
+    sync_check::{self, SyncReport},
+    AppContext,
+};
+
+pub struct PreferencesModel {
+    settings: gio::Settings,
+    visible: bool,
+    token: String,
+    ctx: SharedContext,
+    // A check or upload is talking to the server.
+    is_busy: bool,
+}
+
+// Work on the sync target that needs the account's password.
+#[derive(Debug, Clone, Copy)]
+pub enum ServerTask {
+    Check,
+    UploadMissing,
+}
+
+#[derive(Debug)]
+pub enum PreferencesInput {
+    Show,
+    Hide,
+    TokenChanged,
+    CopyToken,
+    RegenerateToken,
+    ConfirmSyncFromScratch,
+    ConfirmUploadMissing,
+    StartServerTask(ServerTask),
+    AskPassword {
+        task: ServerTask,
+        host: String,
+        email: String,
+    },
+    RunServerTask {
+        task: ServerTask,
+        host: String,
+        email: String,
+        password: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum PreferencesOutput {
+    SyncRemote,
+    SyncFromScratch,
+}
+
+#[derive(Debug)]
+pub enum PreferencesCommand {
+    SyncChecked(Result<SyncReport, String>),
+    MissingMarked(Result<usize, String>),
+    NoStoredPassword {
+        task: ServerTask,
+        host: String,
+        email: String,
+    },
+}
+
+#[relm4::component(pub)]
+impl Component for PreferencesModel {
+    type Init = SharedContext;
+    type Input = PreferencesInput;
+    type Output = PreferencesOutput;
+    type Widgets = PreferencesWidgets;
+    type CommandOutput = PreferencesCommand;
+
+    view! {
+        adw::PreferencesWindow {
+            #[watch]
+            set_visible: model.visible,
+            set_modal: true,
+            set_search_enabled: false,
+
+            add = &adw::PreferencesPage {
+                add = &adw::PreferencesGroup {
+                    set_title: "Web Clipper",
+                    set_description: Some(&format!(
+                        "Lets the Joplin Web Clipper and scripts use the Joplin Data API on port {PORT}"
+                    )),
+
+                    add = &adw::ActionRow {
+                        set_title: "Enable Web Clipper Service",
+                        set_activatable_widget: Some(&clipper_switch),
+
+                        add_suffix: clipper_switch = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                        },
+                    },
+                    add = &adw::ActionRow {
+                        set_title: "Authorization Token",
+                        #[watch]
+                        set_subtitle: &model.token,
+
+                        add_suffix = &gtk::Button {
+                            set_icon_name: icons::edit_copy_symbolic(),
+                            set_tooltip_text: Some("Copy Token"),
+                            set_valign: gtk::Align::Center,
+                            add_css_class: "flat",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesInput::CopyToken);
+                            },
+                        },
+                        add_suffix = &gtk::Button {
+                            set_icon_name: icons::view_refresh_symbolic(),
+                            set_tooltip_text: Some("Generate New Token"),
+                            set_valign: gtk::Align::Center,
+                            add_css_class: "flat",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesInput::RegenerateToken);
+                            },
+                        },
+                    },
+                },
+
+                add = &adw::PreferencesGroup {
+                    set_title: "Sync",
+                    set_description: Some("Recover from a broken sync without deleting the local data"),
+
+                    add = &adw::ActionRow {
+                        set_title: "Check Sync Target",
+                        set_subtitle: "Verify the sign in, version, locks and item counts on the server",
+
+                        add_suffix = &gtk::Button {
+                            set_label: "Check",
+                            set_valign: gtk::Align::Center,
+                            #[watch]
+                            set_sensitive: !model.is_busy,
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesInput::StartServerTask(ServerTask::Check));
+                            },
+                        },
+                    },
+                    add = &adw::ActionRow {
+                        set_title: "Upload Missing Items",
+                        set_subtitle: "Upload the local folders and notes the server doesn't have, leaving the others alone",
+
+                        add_suffix = &gtk::Button {
+                            set_label: "Upload",
+                            set_valign: gtk::Align::Center,
+                            #[watch]
+                            set_sensitive: !model.is_busy,
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesInput::ConfirmUploadMissing);
+                            },
+                        },
+                    },
+                    add = &adw::ActionRow {
+                        set_title: "Re-download Everything",
+                        set_subtitle: "Fetch every item from the server again",
+
+                        add_suffix = &gtk::Button {
+                            set_label: "Re-download",
+                            set_valign: gtk::Align::Center,
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesInput::ConfirmSyncFromScratch);
+                            },
+                        },
+                    },
+                },
+
+                add = &adw::PreferencesGroup {
+                    set_title: "Spell Checking",
+
+                    add = &adw::ActionRow {
+                        set_title: "Check Spelling",
+                        set_subtitle: "Underline misspelled words in note titles and bodies",
+                        set_activatable_widget: Some(&spell_check_switch),
+
+                        add_suffix: spell_check_switch = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                        },
+                    },
+                    #[name = "spell_language_row"]
+                    add = &adw::EntryRow {
+                        set_title: "Language, e.g. en_US, empty for the system language",
+                        set_show_apply_button: true,
+                    },
+                },
+            },
+
+            connect_close_request[sender] => move |_| {
+                sender.input(PreferencesInput::Hide);
+                gtk::Inhibit(true)
+            },
+        }
+    }
+
+    fn init(
+        ctx: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let settings = gio::Settings::new(APP_ID);
+        settings.connect_changed(Some("clipper-token"), {
+            let sender = sender.clone();
+            move |_, _| sender.input(PreferencesInput::TokenChanged)
+        });
+        let model = PreferencesModel {
+            token: settings.string("clipper-token").to_string(),
+            settings,
+            visible: false,
+            ctx,
+            is_busy: false,
+        };
+
+        let widgets = view_output!();
+
+        model
+            .settings
+            .bind("clipper-server-enabled", &widgets.clipper_switch, "active")
+            .build();
+        model
+            .settings
+            .bind("spell-check", &widgets.spell_check_switch, "active")
+            .build();
+        model
+            .settings
+            .bind("spell-check", &widgets.spell_language_row, "sensitive")
+            .get_only()
+            .build();
+        widgets
+            .spell_language_row
+            .set_text(&model.settings.string("spell-language"));
+        widgets.spell_language_row.connect_apply(
+            glib::clone!(@strong model.settings as settings => move |row| {
+                if let Err(e) = settings.set_string("spell-language", row.text().trim()) {
+                    log::warn!("save spell language failed: {e}");
+                }
+            }),
+        );
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, input: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match input {
+            PreferencesInput::Show => {
+                self.visible = true;
+            }
+            PreferencesInput::Hide => {
+                self.visible = false;
+            }
+            PreferencesInput::TokenChanged => {
+                self.token = self.settings.string("clipper-token").to_string();
+            }
+            PreferencesInput::CopyToken => {
+                if let Some(display) = gdk::Display::default() {
+                    display.clipboard().set_text(&self.token);
+                }
+            }
+            PreferencesInput::RegenerateToken => {
+                if let Err(e) = self
+                    .settings
+                    .set_string("clipper-token", &clipper_server::new_token())
+                {
+                    log::warn!("save clipper token failed: {e}");
+                }
+            }
+            PreferencesInput::ConfirmSyncFromScratch => {
+                let dialog = adw::MessageDialog::new(
+                    Some(root),
+                    Some("Re-download Everything?"),
+                    Some(
+                        "Every item on the server is downloaded again, replacing the local copies.",
+                    ),
+                );
+                dialog.add_response("cancel", "_Cancel");
+                dialog.add_response("resync", "_Re-download");
+                dialog.set_response_appearance("resync", adw::ResponseAppearance::Destructive);
+                dialog.connect_response(None, move |_, response| {
+                    if response == "resync" {
+                        sender.output(PreferencesOutput::SyncFromScratch).unwrap();
+                    }
+                });
+                dialog.present();
+            }
+            PreferencesInput::ConfirmUploadMissing => {
+                let dialog = adw::MessageDialog::new(
+                    Some(root),
+                    Some("Upload Missing Items?"),
+                    Some(
+                        "The local folders and notes the server doesn't have are uploaded with the next sync. Items on the server are not changed.",
+                    ),
+                );
+                dialog.add_response("cancel", "_Cancel");
+                dialog.add_response("upload", "_Upload");
+                dialog.set_response_appearance("upload", adw::ResponseAppearance::Suggested);
+                dialog.connect_response(None, move |_, response| {
+                    if response == "upload" {
+                        sender.input(PreferencesInput::StartServerTask(ServerTask::UploadMissing));
+                    }
+                });
+                dialog.present();
+            }
+            PreferencesInput::StartServerTask(task) => {
+                let ctx = self.ctx.read().unwrap().clone();
+                let server = sync_check::load_server(ctx.profile.as_deref());
+                let Some((host, email)) = server.filter(|_| ctx.data.sync_exists()) else {
+                    show_message(
+                        root,
+                        "No Sync Target",
+                        "Sign in to a Joplin Server first. If you signed in with an older version, sign in again.",
+                    );
+                    return;
+                };
+                self.is_busy = true;
+                sender.oneshot_command(async move {
+                    let lookup = {
+                        let (host, email) = (host.clone(), email.clone());
+                        tokio::task::spawn_blocking(move || keyring::lookup_password(&host, &email))
+                            .await
+                    };
+                    let password = match lookup {
+                        Ok(Ok(password)) => password,
+                        Ok(Err(e)) => {
+                            log::warn!("no keyring available: {e}");
+                            None
+                        }
+                        Err(e) => {
+                            log::warn!("no keyring available: {e}");
+                            None
+                        }
+                    };
+                    let Some(password) = password else {
+                        return PreferencesCommand::NoStoredPassword { task, host, email };
+                    };
+                    run_server_task(task, ctx, &host, &email, &password).await
+                });
+            }
+            PreferencesInput::AskPassword { task, host, email } => {
+                // Only without a keyring, or when the login happened without one.
+                let heading = match task {
+                    ServerTask::Check => "Check Sync Target",
+                    ServerTask::UploadMissing => "Upload Missing Items",
+                };
+                let dialog = adw::MessageDialog::new(
+                    Some(root),
+                    Some(heading),
+                    Some(&format!(
+                        "No keyring holds the password for {email} on {host}. Enter it to continue."
+                    )),
+                );
+                let password_row = adw::PasswordEntryRow::builder().title("Password").build();
+                let group = adw::PreferencesGroup::new();
+                group.add(&password_row);
+                dialog.set_extra_child(Some(&group));
+                dialog.add_response("cancel", "_Cancel");
+                dialog.add_response("continue", "_Continue");
+                dialog.set_response_appearance("continue", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("continue"));
+                dialog.connect_response(None, move |_, response| {
+                    if response == "continue" {
+                        sender.input(PreferencesInput::RunServerTask {
+                            task,
+                            host: host.clone(),
+                            email: email.clone(),
+                            password: password_row.text().to_string(),
+                        });
+                    }
+                });
+                dialog.present();
+            }
+            PreferencesInput::RunServerTask {
+                task,
+                host,
+                email,
+                password,
+            } => {
+                let ctx = self.ctx.read().unwrap().clone();
+                self.is_busy = true;
+                sender.oneshot_command(async move {
+                    run_server_task(task, ctx, &host, &email, &password).await
+                });
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        root: &Self::Root,
+    ) {
+        match message {
+            PreferencesCommand::NoStoredPassword { task, host, email } => {
+                self.is_busy = false;
+                sender.input(PreferencesInput::AskPassword { task, host, email });
+            }
+            PreferencesCommand::SyncChecked(result) => {
+                self.is_busy = false;
+                match result {
+                    Ok(report) => show_message(root, "Sync Target Is Working", &report.summary()),
+                    Err(e) => show_message(root, "Sync Target Check Failed", &e),
+                }
+            }
+            PreferencesCommand::MissingMarked(result) => {
+                self.is_busy = false;
+                match result {
+                    Ok(0) => show_message(
+                        root,
+                        "Nothing to Upload",
+                        "The server has every local folder and note.",
+                    ),
+                    Ok(count) => {
+                        show_message(
+                            root,
+                            "Uploading Missing Items",
+                            &format!("{count} folders and notes the server didn't have are being uploaded."),
+                        );
+                        sender.output(PreferencesOutput::SyncRemote).unwrap();
+                    }
+                    Err(e) => show_message(root, "Upload Failed", &e),
+                }
+            }
+        }
+    }
+}
+
+async fn run_server_task(
+    task: ServerTask,
+    ctx: AppContext,
+    host: &str,
+    email: &str,
+    password: &str,
+) -> PreferencesCommand {
+    match task {
+        ServerTask::Check => PreferencesCommand::SyncChecked(
+            sync_check::check(ctx.data.clone(), host, email, password).await,
+        ),
+        ServerTask::UploadMissing => PreferencesCommand::MissingMarked(
+            sync_check::upload_missing(ctx.data.clone(), host, email, password).await,
+        ),
+    }
+}
+
+fn show_message(root: &adw::PreferencesWindow, heading: &str, body: &str) {
+    let dialog = adw::MessageDialog::new(Some(root), Some(heading), Some(body));
+    dialog.add_response("close", "_Close");
+    dialog.present();
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use adw::prelude::*;
+use gtk::{gio, glib};
+use relm4::{
+    gtk, main_application, Component, ComponentController, ComponentParts, ComponentSender,
+    Controller, SimpleComponent,
+};
+use ruslin_data::RuslinData;
+
+use crate::{
+    config::{APP_ID, PROFILE},
+    content_page::note_editor_column::{
+        NoteEditorColumnInit, NoteEditorColumnInput, NoteEditorColumnModel,
+    },
+    print::PrintSession,
+    AppContext,
+};
+
+pub struct NoteWindowModel {
+    note_editor: Controller<NoteEditorColumnModel>,
+}
+
+pub struct NoteWindowInit {
+    pub ctx: AppContext,
+    pub print_session: PrintSession,
+    pub note_id: String,
+}
+
+#[derive(Debug)]
+pub enum NoteWindowOutput {
+    Closed { note_id: String },
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for NoteWindowModel {
+    type Init = NoteWindowInit;
+    type Input = ();
+    type Output = NoteWindowOutput;
+    type Widgets = NoteWindowWidgets;
+
+    view! {
+        window = adw::ApplicationWindow::new(&main_application()) {
+            set_title: Some("Ruslin"),
+            set_width_request: 360,
+
+            add_css_class?: if PROFILE == "Devel" {
+                    Some("devel")
+                } else {
+                    None
+                },
+
+            set_content: Some(model.note_editor.widget()),
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let note_editor = NoteEditorColumnModel::builder()
+            .launch(NoteEditorColumnInit {
+                ctx: init.ctx.clone(),
+                print_session: init.print_session,
+            })
+            .detach();
+        note_editor.widgets().back_button.set_visible(false);
+        note_editor.emit(NoteEditorColumnInput::OpenNote {
+            id: init.note_id.clone(),
+        });
+
+        let model = NoteWindowModel { note_editor };
+
+        let widgets = view_output!();
+
+        load_window_size(&widgets.window, &init.note_id);
+
+        let note_id = init.note_id;
+        let data = init.ctx.data.clone();
+        widgets
+            .window
+            .connect_close_request(glib::clone!(@strong sender => move |window| {
+                if let Err(e) = save_window_size(window, &data, &note_id) {
+                    log::warn!("save note window size failed: {e}");
+                }
+                // Fails only once the content page is gone, e.g. while switching profiles.
+                let _ = sender.output(NoteWindowOutput::Closed {
+                    note_id: note_id.clone(),
+                });
+                gtk::Inhibit(false)
+            }));
+
+        widgets.window.present();
+
+        ComponentParts { model, widgets }
+    }
+}
+
+// Sizes of deleted notes are dropped along the way.
+fn save_window_size(
+    window: &adw::ApplicationWindow,
+    data: &RuslinData,
+    note_id: &str,
+) -> Result<(), glib::BoolError> {
+    let settings = gio::Settings::new(APP_ID);
+    let mut sizes: HashMap<String, (i32, i32)> = settings.get("note-window-sizes");
+    sizes.insert(note_id.to_string(), window.default_size());
+    match data.db.load_abbr_notes(None) {
+        Ok(notes) => {
+            let ids: HashSet<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+            sizes.retain(|id, _| ids.contains(id.as_str()));
+        }
+        Err(e) => log::warn!("load notes to prune window sizes failed: {e:?}"),
+    }
+    settings.set("note-window-sizes", &sizes)
+}
+
+fn load_window_size(window: &adw::ApplicationWindow, note_id: &str) {
+    let settings = gio::Settings::new(APP_ID);
+    let sizes: HashMap<String, (i32, i32)> = settings.get("note-window-sizes");
+    let (width, height) = sizes.get(note_id).copied().unwrap_or((600, 700));
+    window.set_default_size(width, height);
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use gtk::{gio, glib, prelude::*};
+use relm4::gtk;
+use ruslin_data::DatabaseError;
+
+use crate::{config::APP_ID, AppContext};
+
+// ruslin-data doesn't expose which items still have to be uploaded, so the items updated since
+// the last successful sync started are counted. Deleted items leave nothing to count.
+pub fn count(ctx: &AppContext) -> Result<usize, DatabaseError> {
+    if !ctx.data.sync_exists() {
+        return Ok(0);
+    }
+    let last_sync = last_sync(ctx.profile.as_deref());
+    let notes = ctx.data.db.load_abbr_notes(None)?;
+    let folders = ctx.data.db.load_folders()?;
+    let updated_times = notes
+        .iter()
+        .map(|abbr_note| abbr_note.user_updated_time.format_ymd_hms())
+        .chain(
+            folders
+                .iter()
+                .map(|folder| folder.updated_time.format_ymd_hms()),
+        );
+    Ok(count_since(updated_times, last_sync.as_deref()))
+}
+
+// The local time formatted like the update times, taken when a sync starts.
+pub fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// By profile, the start of the last sync that finished.
+pub fn save_last_sync(profile: Option<&str>, started: &str) -> Result<(), glib::BoolError> {
+    let settings = gio::Settings::new(APP_ID);
+    let mut last_syncs: HashMap<String, String> = settings.get("last-sync-times");
+    last_syncs.insert(profile.unwrap_or_default().to_string(), started.to_string());
+    settings.set("last-sync-times", &last_syncs)
+}
+
+fn last_sync(profile: Option<&str>) -> Option<String> {
+    let settings = gio::Settings::new(APP_ID);
+    let last_syncs: HashMap<String, String> = settings.get("last-sync-times");
+    last_syncs.get(profile.unwrap_or_default()).cloned()
+}
+
+// Both are `%Y-%m-%d %H:%M:%S`, so they compare as strings. Times are kept in seconds, an edit
+// in the second the sync started counts as pending rather than being missed.
+fn count_since(updated_times: impl Iterator<Item = String>, last_sync: Option<&str>) -> usize {
+    match last_sync {
+        Some(last_sync) => updated_times
+            .filter(|updated| updated.as_str() >= last_sync)
+            .count(),
+        None => updated_times.count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_items_updated_since_the_last_sync() {
+        let times = || {
+            [
+                "2022-12-01 09:00:00",
+                "2022-12-02 10:30:00",
+                "2022-12-02 10:30:01",
+                "2022-12-03 08:00:00",
+            ]
+            .map(str::to_string)
+            .into_iter()
+        };
+        assert_eq!(count_since(times(), Some("2022-12-02 10:30:01")), 2);
+        assert_eq!(count_since(times(), Some("2022-12-04 00:00:00")), 0);
+    }
+
+    #[test]
+    fn everything_is_pending_before_the_first_sync() {
+        let times = ["2022-12-01 09:00:00", "2022-12-02 10:30:00"]
+            .map(str::to_string)
+            .into_iter();
+        assert_eq!(count_since(times, None), 2);
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+};
+
+use gtk::{gio, glib, pango, prelude::*};
+use relm4::gtk;
+
+use crate::{config::APP_ID, markdown};
+
+pub struct PrintNote {
+    pub title: String,
+    pub body: String,
+    pub metadata: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrintOptions {
+    pub include_title: bool,
+    pub include_metadata: bool,
+}
+
+impl PrintOptions {
+    pub fn load() -> PrintOptions {
+        let settings = gio::Settings::new(APP_ID);
+        PrintOptions {
+            include_title: settings.boolean("print-include-title"),
+            include_metadata: settings.boolean("print-include-metadata"),
+        }
+    }
+
+    fn save(&self) -> Result<(), glib::BoolError> {
+        let settings = gio::Settings::new(APP_ID);
+        settings.set_boolean("print-include-title", self.include_title)?;
+        settings.set_boolean("print-include-metadata", self.include_metadata)
+    }
+}
+
+// The paper size, orientation and printer last picked in the print dialog, for the next one.
+#[derive(Debug, Clone, Default)]
+pub struct PrintSession {
+    settings: Rc<RefCell<Option<gtk::PrintSettings>>>,
+}
+
+struct Page {
+    layout: pango::Layout,
+    top: f64,
+    bottom: f64,
+}
+
+// Every note starts on a new page, pages are cut between layout lines.
+pub fn print_operation(
+    notes: Vec<PrintNote>,
+    options: PrintOptions,
+    settings: Option<&gtk::PrintSettings>,
+) -> gtk::PrintOperation {
+    let operation = gtk::PrintOperation::new();
+    operation.set_unit(gtk::Unit::Points);
+    operation.set_embed_page_setup(true);
+    if let [note] = notes.as_slice() {
+        operation.set_job_name(&note.title);
+    }
+    operation.set_print_settings(settings);
+
+    let options = Rc::new(Cell::new(options));
+    operation.set_custom_tab_label(Some("Note"));
+    operation.connect_create_custom_widget(glib::clone!(@strong options => move |_| {
+        let options_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        options_box.set_margin_top(12);
+        options_box.set_margin_bottom(12);
+        options_box.set_margin_start(12);
+        options_box.set_margin_end(12);
+        let title_check = gtk::CheckButton::with_label("Include title");
+        title_check.set_active(options.get().include_title);
+        title_check.connect_toggled(glib::clone!(@strong options => move |check| {
+            options.set(PrintOptions {
+                include_title: check.is_active(),
+                ..options.get()
+            });
+        }));
+        let metadata_check = gtk::CheckButton::with_label("Include metadata");
+        metadata_check.set_active(options.get().include_metadata);
+        metadata_check.connect_toggled(glib::clone!(@strong options => move |check| {
+            options.set(PrintOptions {
+                include_metadata: check.is_active(),
+                ..options.get()
+            });
+        }));
+        options_box.append(&title_check);
+        options_box.append(&metadata_check);
+        options_box.upcast()
+    }));
+    operation.connect_custom_widget_apply(glib::clone!(@strong options => move |_, _| {
+        if let Err(e) = options.get().save() {
+            log::warn!("save print options failed: {e}");
+        }
+    }));
+
+    let pages: Rc<RefCell<Vec<Page>>> = Rc::default();
+    operation.connect_begin_print(glib::clone!(@strong pages => move |operation, context| {
+        let mut new_pages = Vec::new();
+        for note in notes.iter() {
+            let layout = context.create_pango_layout();
+            layout.set_width((context.width() * pango::SCALE as f64) as i32);
+            layout.set_wrap(pango::WrapMode::WordChar);
+            layout.set_markup(&note_markup(note, options.get()));
+            paginate(layout, context.height(), &mut new_pages);
+        }
+        operation.set_n_pages(new_pages.len().max(1) as i32);
+        *pages.borrow_mut() = new_pages;
+    }));
+    operation.connect_draw_page(move |_, context, page_nr| {
+        let pages = pages.borrow();
+        let Some(page) = pages.get(page_nr as usize) else {
+            return;
+        };
+        let cr = context.cairo_context();
+        cr.rectangle(0.0, 0.0, context.width(), page.bottom - page.top);
+        cr.clip();
+        cr.translate(0.0, -page.top);
+        pangocairo::functions::show_layout(&cr, &page.layout);
+    });
+    operation
+}
+
+// Shows the print dialog, which also offers the page setup and a preview.
+pub fn print(
+    notes: Vec<PrintNote>,
+    session: &PrintSession,
+    parent: Option<&impl IsA<gtk::Window>>,
+) {
+    let operation = print_operation(
+        notes,
+        PrintOptions::load(),
+        session.settings.borrow().as_ref(),
+    );
+    let settings = session.settings.clone();
+    operation.connect_done(move |operation, result| {
+        if result == gtk::PrintOperationResult::Apply {
+            *settings.borrow_mut() = operation.print_settings();
+        }
+    });
+    operation.set_allow_async(true);
+    if let Err(e) = operation.run(gtk::PrintOperationAction::PrintDialog, parent) {
+        log::warn!("print failed: {e}");
+    }
+}
+
+// Doesn't need a printer or a dialog, `parent` can be `None` when there is no window.
+pub fn export_pdf(
+    notes: Vec<PrintNote>,
+    options: PrintOptions,
+    path: &Path,
+    parent: Option<&impl IsA<gtk::Window>>,
+) -> Result<(), glib::Error> {
+    let operation = print_operation(notes, options, None);
+    operation.set_export_filename(path);
+    operation.run(gtk::PrintOperationAction::Export, parent)?;
+    Ok(())
+}
+
+fn note_markup(note: &PrintNote, options: PrintOptions) -> String {
+    let mut markup = String::new();
+    if options.include_title {
+        markup.push_str(&format!(
+            "<span size=\"xx-large\" weight=\"bold\">{}</span>\n",
+            glib::markup_escape_text(&note.title)
+        ));
+    }
+    if options.include_metadata {
+        for (key, value) in note.metadata.iter() {
+            markup.push_str(&format!(
+                "<span size=\"small\" foreground=\"#666666\">{}: {}</span>\n",
+                glib::markup_escape_text(key),
+                glib::markup_escape_text(value)
+            ));
+        }
+    }
+    if !markup.is_empty() {
+        markup.push('\n');
+    }
+    markup.push_str(&markdown::to_print_markup(&note.body));
+    markup
+}
+
+fn paginate(layout: pango::Layout, page_height: f64, pages: &mut Vec<Page>) {
+    let scale = pango::SCALE as f64;
+    let mut lines = Vec::new();
+    let mut iter = layout.iter();
+    loop {
+        let (_, logical) = iter.line_extents();
+        lines.push((
+            logical.y() as f64 / scale,
+            (logical.y() + logical.height()) as f64 / scale,
+        ));
+        if !iter.next_line() {
+            break;
+        }
+    }
+    pages.extend(
+        page_ranges(&lines, page_height)
+            .into_iter()
+            .map(|(top, bottom)| Page {
+                layout: layout.clone(),
+                top,
+                bottom,
+            }),
+    );
+}
+
+// The top and bottom of each page, given the top and bottom of each line. A line taller than a
+// page gets a page of its own.
+fn page_ranges(lines: &[(f64, f64)], page_height: f64) -> Vec<(f64, f64)> {
+    let mut pages = Vec::new();
+    let mut top = 0.0;
+    let mut bottom = 0.0;
+    for &(line_top, line_bottom) in lines {
+        if line_bottom - top > page_height && line_top > top {
+            pages.push((top, line_top));
+            top = line_top;
+        }
+        bottom = line_bottom;
+    }
+    pages.push((top, bottom));
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> PrintNote {
+        PrintNote {
+            title: "Fish & Chips".to_string(),
+            body: "Body".to_string(),
+            metadata: vec![("Folder".to_string(), "<Recipes>".to_string())],
+        }
+    }
+
+    #[test]
+    fn markup_follows_options() {
+        let all = note_markup(
+            &note(),
+            PrintOptions {
+                include_title: true,
+                include_metadata: true,
+            },
+        );
+        assert!(all.contains(">Fish &amp; Chips</span>"));
+        assert!(all.contains(">Folder: &lt;Recipes&gt;</span>"));
+        assert!(all.find("Fish").unwrap() < all.find("Folder").unwrap());
+        assert!(all.find("Folder").unwrap() < all.find("Body").unwrap());
+
+        let body_only = note_markup(
+            &note(),
+            PrintOptions {
+                include_title: false,
+                include_metadata: false,
+            },
+        );
+        assert!(!body_only.contains("Fish"));
+        assert!(!body_only.contains("Folder"));
+        assert!(body_only.contains("Body"));
+        assert!(!body_only.starts_with('\n'));
+    }
+
+    #[test]
+    fn pages_break_between_lines() {
+        let lines: Vec<(f64, f64)> = (0..10)
+            .map(|line| (line as f64 * 20.0, (line + 1) as f64 * 20.0))
+            .collect();
+        assert_eq!(
+            page_ranges(&lines, 50.0),
+            [
+                (0.0, 40.0),
+                (40.0, 80.0),
+                (80.0, 120.0),
+                (120.0, 160.0),
+                (160.0, 200.0)
+            ]
+        );
+        assert_eq!(page_ranges(&lines, 1000.0), [(0.0, 200.0)]);
+    }
+
+    #[test]
+    fn tall_lines_get_their_own_page() {
+        let lines = [(0.0, 10.0), (10.0, 200.0), (200.0, 210.0)];
+        assert_eq!(
+            page_ranges(&lines, 100.0),
+            [(0.0, 10.0), (10.0, 200.0), (200.0, 210.0)]
+        );
+        assert_eq!(page_ranges(&[], 100.0), [(0.0, 0.0)]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use gtk::{gio, glib, prelude::*};
+use relm4::gtk;
+
+use crate::{config::APP_ID, AppContext};
+
+// Queries shown as smart folders in the sidebar, by profile, empty for the default one. Each
+// one is named by its query. They are kept in GSettings on purpose: the searches are part of
+// how this device is set up, Joplin has nowhere to sync them to.
+type SavedSearches = HashMap<String, Vec<String>>;
+
+pub fn load(ctx: &AppContext) -> Vec<String> {
+    let settings = gio::Settings::new(APP_ID);
+    let searches: SavedSearches = settings.get("saved-searches");
+    searches
+        .get(ctx.profile.as_deref().unwrap_or_default())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn add(ctx: &AppContext, query: &str) -> Result<(), glib::BoolError> {
+    update(ctx, |queries| add_query(queries, query))
+}
+
+pub fn remove(ctx: &AppContext, query: &str) -> Result<(), glib::BoolError> {
+    update(ctx, |queries| queries.retain(|other| other != query))
+}
+
+pub fn replace(ctx: &AppContext, old: &str, new: &str) -> Result<(), glib::BoolError> {
+    update(ctx, |queries| replace_query(queries, old, new))
+}
+
+fn add_query(queries: &mut Vec<String>, query: &str) {
+    if !queries.iter().any(|other| other == query) {
+        queries.push(query.to_string());
+    }
+}
+
+// Keeps the place of the edited search. When the new query is saved already, the old one goes.
+fn replace_query(queries: &mut Vec<String>, old: &str, new: &str) {
+    if old == new {
+        return;
+    }
+    if queries.iter().any(|other| other == new) {
+        queries.retain(|other| other != old);
+        return;
+    }
+    match queries.iter_mut().find(|other| *other == old) {
+        Some(query) => *query = new.to_string(),
+        None => queries.push(new.to_string()),
+    }
+}
+
+fn update(ctx: &AppContext, f: impl FnOnce(&mut Vec<String>)) -> Result<(), glib::BoolError> {
+    let mut queries = load(ctx);
+    f(&mut queries);
+    let settings = gio::Settings::new(APP_ID);
+    let mut all: SavedSearches = settings.get("saved-searches");
+    all.insert(ctx.profile.clone().unwrap_or_default(), queries);
+    settings.set("saved-searches", &all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries(queries: &[&str]) -> Vec<String> {
+        queries.iter().map(|query| query.to_string()).collect()
+    }
+
+    #[test]
+    fn adding_keeps_each_query_once() {
+        let mut saved = Vec::new();
+        add_query(&mut saved, "tag:meeting");
+        add_query(&mut saved, "updated:day-7");
+        add_query(&mut saved, "tag:meeting");
+        assert_eq!(saved, queries(&["tag:meeting", "updated:day-7"]));
+    }
+
+    #[test]
+    fn editing_keeps_the_place() {
+        let mut saved = queries(&["a", "b", "c"]);
+        replace_query(&mut saved, "b", "d");
+        assert_eq!(saved, queries(&["a", "d", "c"]));
+        // Saved already, the edited one goes instead of listing it twice.
+        replace_query(&mut saved, "d", "a");
+        assert_eq!(saved, queries(&["a", "c"]));
+        // Removed meanwhile, the new query is added.
+        replace_query(&mut saved, "gone", "e");
+        assert_eq!(saved, queries(&["a", "c", "e"]));
+    }
+
+    #[test]
+    fn stored_by_profile() {
+        let all: SavedSearches = HashMap::from([
+            (String::new(), queries(&["tag:meeting"])),
+            (
+                "work".to_string(),
+                queries(&["notebook:Projects", "-tag:done"]),
+            ),
+        ]);
+        let variant = all.to_variant();
+        assert_eq!(variant.type_().as_str(), "a{sas}");
+        let read = variant.get::<SavedSearches>().unwrap();
+        assert_eq!(read, all);
+        assert_eq!(read[""], queries(&["tag:meeting"]));
+    }
+}
@@ -0,0 +1,567 @@
+use std::{collections::HashMap, path::Path};
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime};
+use ruslin_data::{AbbrNote, DatabaseError, Folder, RuslinData};
+
+use crate::{export, markdown};
+
+// Joplin's search syntax, e.g. `"team meeting" notebook:Work -tag:done updated:day-7 resource:image/*`.
+//
+// Words and quoted phrases are searched in the title and body, `*` is a wildcard, `-` negates a
+// term, and `any:1` makes a note match when any term does instead of all of them. `notebook:`
+// includes the notebooks inside the matching ones, as in Joplin.
+//
+// ruslin-data has no tags and no to-dos, so `tag:`, `type:todo` and `iscompleted:` are parsed
+// but refused, see `Query::unsupported`. Every note is of `type:note`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+    any: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    filter: Filter,
+    negated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Text(String),
+    Title(String),
+    Body(String),
+    Tag(String),
+    Notebook(String),
+    // Created or updated on or after the day.
+    Created(NaiveDate),
+    Updated(NaiveDate),
+    Todo(bool),
+    IsCompleted(bool),
+    // A mime type pattern, `*` for any attachment.
+    Resource(String),
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Query {
+        let today = Local::now().date_naive();
+        let mut query = Query {
+            terms: Vec::new(),
+            any: false,
+        };
+        for token in tokens(text) {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+            if let Some(any) = token.strip_prefix("any:") {
+                query.any = any == "1";
+                continue;
+            }
+            let filter = match Filter::parse(token, today) {
+                Some(filter) => filter,
+                None => match unquote(token) {
+                    text if text.is_empty() => continue,
+                    text => Filter::Text(text),
+                },
+            };
+            query.terms.push(Term { filter, negated });
+        }
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // Says which filters can't be searched, so the query isn't taken to match no note.
+    pub fn unsupported(&self) -> Option<String> {
+        let mut names: Vec<&str> = Vec::new();
+        for term in &self.terms {
+            let name = match term.filter {
+                Filter::Tag(_) => "tag:",
+                Filter::Todo(true) => "type:todo",
+                Filter::IsCompleted(_) => "iscompleted:",
+                _ => continue,
+            };
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if names.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} can't be searched, tags and to-dos aren't supported yet",
+            names.join(", ")
+        ))
+    }
+
+    // Reading each note is what makes a search slow, only these filters need more than the
+    // title and update time.
+    fn needs_notes(&self) -> bool {
+        self.terms.iter().any(|term| {
+            matches!(
+                term.filter,
+                Filter::Text(_) | Filter::Body(_) | Filter::Created(_) | Filter::Resource(_)
+            )
+        })
+    }
+
+    fn needs_folders(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|term| matches!(term.filter, Filter::Notebook(_)))
+    }
+
+    fn matches(&self, note: &Candidate) -> bool {
+        let matches = |term: &Term| term.filter.matches(note) != term.negated;
+        match self.any {
+            _ if self.terms.is_empty() => true,
+            true => self.terms.iter().any(matches),
+            false => self.terms.iter().all(matches),
+        }
+    }
+}
+
+impl Filter {
+    // `None` when the token isn't a known `name:value` filter, it is searched as text then.
+    fn parse(token: &str, today: NaiveDate) -> Option<Filter> {
+        let (name, value) = token.split_once(':')?;
+        if name.contains('"') {
+            return None;
+        }
+        let value = unquote(value);
+        if value.is_empty() {
+            return None;
+        }
+        let filter = match name.to_lowercase().as_str() {
+            "title" => Filter::Title(value),
+            "body" => Filter::Body(value),
+            "tag" => Filter::Tag(value),
+            "notebook" => Filter::Notebook(value),
+            "created" => Filter::Created(parse_date(&value, today)?),
+            "updated" => Filter::Updated(parse_date(&value, today)?),
+            "type" => match value.as_str() {
+                "todo" => Filter::Todo(true),
+                "note" => Filter::Todo(false),
+                _ => return None,
+            },
+            "iscompleted" => match value.as_str() {
+                "1" => Filter::IsCompleted(true),
+                "0" => Filter::IsCompleted(false),
+                _ => return None,
+            },
+            "resource" => Filter::Resource(value),
+            _ => return None,
+        };
+        Some(filter)
+    }
+
+    fn matches(&self, note: &Candidate) -> bool {
+        let contains = |text: &str, field: &str| matches_pattern(&format!("*{text}*"), field);
+        match self {
+            Filter::Text(text) => contains(text, &note.title) || contains(text, &note.body),
+            Filter::Title(text) => contains(text, &note.title),
+            Filter::Body(text) => contains(text, &note.body),
+            Filter::Tag(_) => false,
+            Filter::Notebook(pattern) => note
+                .folder_titles
+                .iter()
+                .any(|title| matches_pattern(pattern, title)),
+            Filter::Created(day) => note.created.map_or(false, |created| created.date() >= *day),
+            Filter::Updated(day) => note.updated.map_or(false, |updated| updated.date() >= *day),
+            Filter::Todo(is_todo) => !is_todo,
+            Filter::IsCompleted(_) => false,
+            Filter::Resource(pattern) => note
+                .resource_mimes
+                .iter()
+                .any(|mime| matches_pattern(pattern, mime)),
+        }
+    }
+}
+
+// Splits on whitespace outside of double quotes, the quotes are kept for the filters to strip.
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c.is_whitespace() && !quoted {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn unquote(text: &str) -> String {
+    text.replace('"', "").to_lowercase()
+}
+
+// What the filters look at, lowercased where they ignore case. Only what the query needs is
+// filled in.
+#[derive(Default)]
+struct Candidate {
+    title: String,
+    body: String,
+    // The note's folder and the folders around it.
+    folder_titles: Vec<String>,
+    created: Option<NaiveDateTime>,
+    updated: Option<NaiveDateTime>,
+    resource_mimes: Vec<&'static str>,
+}
+
+pub fn query_notes(
+    data: &RuslinData,
+    resources_dir: &Path,
+    query: &Query,
+) -> Result<Vec<AbbrNote>, DatabaseError> {
+    filter_notes(data, resources_dir, data.db.load_abbr_notes(None)?, query)
+}
+
+// Keeps the notes matching the query, in their order.
+pub fn filter_notes(
+    data: &RuslinData,
+    resources_dir: &Path,
+    notes: Vec<AbbrNote>,
+    query: &Query,
+) -> Result<Vec<AbbrNote>, DatabaseError> {
+    let needs_notes = query.needs_notes();
+    let folders: HashMap<String, Folder> = if query.needs_folders() {
+        data.db
+            .load_folders()?
+            .into_iter()
+            .map(|folder| (folder.id.clone(), folder))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    // Without reading the notes, their folders come from one list per folder.
+    let mut note_folders: HashMap<String, String> = HashMap::new();
+    if !needs_notes {
+        for folder_id in folders.keys() {
+            for abbr_note in data.db.load_abbr_notes(Some(folder_id))? {
+                note_folders.insert(abbr_note.id, folder_id.clone());
+            }
+        }
+    }
+    let resources = if needs_notes {
+        resource_mimes(resources_dir)
+    } else {
+        HashMap::new()
+    };
+    let mut matching = Vec::new();
+    for abbr_note in notes {
+        let mut candidate = Candidate {
+            title: abbr_note.title.to_lowercase(),
+            updated: parse_time(abbr_note.user_updated_time.format_ymd_hms()),
+            ..Candidate::default()
+        };
+        let mut folder_id = note_folders.get(&abbr_note.id).cloned();
+        if needs_notes {
+            let note = data.db.load_note(&abbr_note.id)?;
+            candidate.body = note.body.to_lowercase();
+            candidate.created = parse_time(note.user_created_time.format_ymd_hms());
+            candidate.resource_mimes = item_ids(&note.body)
+                .filter_map(|id| resources.get(id).copied())
+                .collect();
+            folder_id = note.parent_id;
+        }
+        candidate.folder_titles = folder_titles(&folders, folder_id.as_deref());
+        if query.matches(&candidate) {
+            matching.push(abbr_note);
+        }
+    }
+    Ok(matching)
+}
+
+fn parse_time(time: String) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M:%S").ok()
+}
+
+// From the folder up to the top level. A parent cycle stops once every folder was seen.
+fn folder_titles(folders: &HashMap<String, Folder>, folder_id: Option<&str>) -> Vec<String> {
+    let mut titles = Vec::new();
+    let mut folder_id = folder_id;
+    while let Some(folder) = folder_id.and_then(|id| folders.get(id)) {
+        if titles.len() == folders.len() {
+            break;
+        }
+        titles.push(folder.title.to_lowercase());
+        folder_id = folder.parent_id.as_deref();
+    }
+    titles
+}
+
+// Resource files are named by their id.
+fn resource_mimes(resources_dir: &Path) -> HashMap<String, &'static str> {
+    std::fs::read_dir(resources_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path.file_stem()?.to_str()?.to_string();
+            Some((id, export::mime_type(&path)))
+        })
+        .collect()
+}
+
+fn item_ids(body: &str) -> impl Iterator<Item = &str> {
+    body.match_indices(":/")
+        .filter_map(|(index, _)| body.get(index..index + 34))
+        .filter_map(markdown::note_id_from_url)
+}
+
+// `*` stands for any run of characters.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+// Joplin's `20230115`, `202301`, `2023`, `day-7`, `week-2`, `month-1`, `year-1`, and `last-7-days`.
+fn parse_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Some((unit, count)) = value.split_once('-').filter(|(unit, _)| *unit != "last") {
+        return relative_date(unit, count.parse().ok()?, today);
+    }
+    if let Some(relative) = value.strip_prefix("last-") {
+        let (count, unit) = relative.split_once('-')?;
+        return relative_date(
+            unit.strip_suffix('s').unwrap_or(unit),
+            count.parse().ok()?,
+            today,
+        );
+    }
+    if !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match value.len() {
+        8 => NaiveDate::parse_from_str(value, "%Y%m%d").ok(),
+        6 => NaiveDate::from_ymd_opt(value[..4].parse().ok()?, value[4..].parse().ok()?, 1),
+        4 => NaiveDate::from_ymd_opt(value.parse().ok()?, 1, 1),
+        _ => None,
+    }
+}
+
+fn relative_date(unit: &str, count: u32, today: NaiveDate) -> Option<NaiveDate> {
+    match unit {
+        "day" => today.checked_sub_signed(Duration::days(count.into())),
+        "week" => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+            monday.checked_sub_signed(Duration::weeks(count.into()))
+        }
+        "month" => {
+            let months = today.year() * 12 + today.month0() as i32 - count as i32;
+            NaiveDate::from_ymd_opt(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+        }
+        "year" => NaiveDate::from_ymd_opt(today.year() - count as i32, 1, 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str, negated: bool) -> Term {
+        Term {
+            filter: Filter::Text(text.to_string()),
+            negated,
+        }
+    }
+
+    fn candidate(title: &str, body: &str) -> Candidate {
+        Candidate {
+            title: title.to_string(),
+            body: body.to_string(),
+            folder_titles: vec!["work".to_string()],
+            created: None,
+            updated: None,
+            resource_mimes: vec!["image/png"],
+        }
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn quoted_phrases_are_one_term() {
+        let query = Query::parse(r#""Team Meeting" notes"#);
+        assert_eq!(
+            query.terms,
+            vec![text("team meeting", false), text("notes", false)]
+        );
+        let query = Query::parse(r#"title:"weekly plan""#);
+        assert_eq!(
+            query.terms[0].filter,
+            Filter::Title("weekly plan".to_string())
+        );
+    }
+
+    #[test]
+    fn dashes_negate_terms() {
+        let query = Query::parse("-draft -notebook:Archive -");
+        assert_eq!(
+            query.terms,
+            vec![
+                text("draft", true),
+                Term {
+                    filter: Filter::Notebook("archive".to_string()),
+                    negated: true,
+                },
+                text("-", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn any_switches_to_matching_one_term() {
+        let query = Query::parse("any:1 apple pear");
+        assert!(query.any);
+        assert_eq!(query.terms.len(), 2);
+        assert!(query.matches(&candidate("apple pie", "")));
+        assert!(!Query::parse("apple pear").matches(&candidate("apple pie", "")));
+        assert!(!Query::parse("any:0 apple").any);
+    }
+
+    #[test]
+    fn unknown_filters_are_searched_as_text() {
+        let query = Query::parse("color:red https://example.com updated:soon");
+        assert_eq!(
+            query.terms,
+            vec![
+                text("color:red", false),
+                text("https://example.com", false),
+                text("updated:soon", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_match_their_fields() {
+        let note = candidate("groceries", "buy apples");
+        assert!(Query::parse("apples").matches(&note));
+        assert!(Query::parse("body:apple title:gro*").matches(&note));
+        assert!(!Query::parse("title:apples").matches(&note));
+        assert!(Query::parse("notebook:w*").matches(&note));
+        assert!(Query::parse("resource:image/*").matches(&note));
+        assert!(!Query::parse("resource:application/pdf").matches(&note));
+        assert!(!Query::parse("-apples").matches(&note));
+        assert!(Query::parse("type:note").matches(&note));
+        assert!(Query::parse("").matches(&note));
+    }
+
+    #[test]
+    fn notebooks_include_their_children() {
+        let mut note = candidate("groceries", "");
+        note.folder_titles = vec!["2023".to_string(), "projects".to_string()];
+        assert!(Query::parse("notebook:Projects").matches(&note));
+        assert!(Query::parse("notebook:2023").matches(&note));
+        assert!(!Query::parse("notebook:work").matches(&note));
+    }
+
+    #[test]
+    fn unsupported_filters_are_named() {
+        assert_eq!(Query::parse("notebook:work apples").unsupported(), None);
+        assert_eq!(Query::parse("type:note").unsupported(), None);
+        assert_eq!(
+            Query::parse("tag:a -tag:b iscompleted:0").unsupported(),
+            Some(
+                "tag:, iscompleted: can't be searched, tags and to-dos aren't supported yet"
+                    .to_string()
+            )
+        );
+        assert!(Query::parse("type:todo").unsupported().is_some());
+    }
+
+    #[test]
+    fn only_some_filters_read_the_notes() {
+        assert!(!Query::parse("title:plan notebook:work updated:day-7").needs_notes());
+        assert!(Query::parse("plan").needs_notes());
+        assert!(Query::parse("resource:image/*").needs_notes());
+        assert!(Query::parse("created:2023").needs_notes());
+        assert!(Query::parse("-notebook:archive").needs_folders());
+        assert!(!Query::parse("plan").needs_folders());
+    }
+
+    #[test]
+    fn folder_titles_walk_up_to_the_top() {
+        let folder = |id: &str, title: &str, parent_id: Option<&str>| {
+            let mut folder = Folder::new(title.to_string(), parent_id.map(str::to_string));
+            folder.id = id.to_string();
+            (id.to_string(), folder)
+        };
+        let folders = HashMap::from([
+            folder("a", "Projects", None),
+            folder("b", "2023", Some("a")),
+            folder("x", "Loop", Some("y")),
+            folder("y", "Back", Some("x")),
+        ]);
+        assert_eq!(folder_titles(&folders, Some("b")), ["2023", "projects"]);
+        assert_eq!(folder_titles(&folders, None), Vec::<String>::new());
+        assert_eq!(folder_titles(&folders, Some("x")).len(), folders.len());
+    }
+
+    #[test]
+    fn absolute_dates() {
+        let today = day(2023, 3, 15);
+        assert_eq!(parse_date("20230115", today), Some(day(2023, 1, 15)));
+        assert_eq!(parse_date("202301", today), Some(day(2023, 1, 1)));
+        assert_eq!(parse_date("2023", today), Some(day(2023, 1, 1)));
+        assert_eq!(parse_date("20231345", today), None);
+        assert_eq!(parse_date("12345", today), None);
+        assert_eq!(parse_date("2023x", today), None);
+    }
+
+    #[test]
+    fn relative_dates() {
+        // A Wednesday.
+        let today = day(2023, 3, 15);
+        assert_eq!(parse_date("day-7", today), Some(day(2023, 3, 8)));
+        assert_eq!(parse_date("week-2", today), Some(day(2023, 2, 27)));
+        assert_eq!(parse_date("month-1", today), Some(day(2023, 2, 1)));
+        assert_eq!(parse_date("month-3", today), Some(day(2022, 12, 1)));
+        assert_eq!(parse_date("year-1", today), Some(day(2022, 1, 1)));
+        assert_eq!(parse_date("last-7-days", today), Some(day(2023, 3, 8)));
+        assert_eq!(parse_date("last-1-month", today), Some(day(2023, 2, 1)));
+        assert_eq!(parse_date("hour-1", today), None);
+        assert_eq!(parse_date("day-x", today), None);
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("image/*", "image/png"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*app*", "pineapple"));
+        assert!(matches_pattern("a*b*c", "axxbyyc"));
+        assert!(matches_pattern("work", "work"));
+        assert!(!matches_pattern("work", "workshop"));
+        assert!(!matches_pattern("a*b*c", "axxcyyb"));
+        assert!(!matches_pattern("ab*ba", "aba"));
+        assert!(!matches_pattern("image/*", "text/plain"));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use gtk::{
+    gio,
+    glib::{self, ToVariant},
+    prelude::*,
+};
+use relm4::{gtk, main_application};
+
+use ruslin_data::DatabaseError;
+
+use crate::{app::SharedContext, config::APP_ID, dbus, search, AppContext};
+
+const INTERFACE: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in"/>
+      <arg type="aa{sv}" name="metas" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+// Exported next to the application object, the path must match the shipped search provider .ini.
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+    ctx: SharedContext,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTERFACE)?;
+    let interface = node
+        .lookup_interface("org.gnome.Shell.SearchProvider2")
+        .unwrap();
+    connection.register_object(
+        &format!("{object_path}/SearchProvider"),
+        &interface,
+        move |_, _, _, _, method, parameters, invocation| {
+            let ctx = ctx.read().unwrap().clone();
+            match method {
+                "GetInitialResultSet" => {
+                    let Some((terms,)) = parameters.get::<(Vec<String>,)>() else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    dbus::reply_in_background(invocation, move || {
+                        let ids = initial_results(&ctx, &terms).map_err(search_failed)?;
+                        Ok(Some((ids,).to_variant()))
+                    });
+                }
+                "GetSubsearchResultSet" => {
+                    let Some((previous, terms)) = parameters.get::<(Vec<String>, Vec<String>)>()
+                    else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    dbus::reply_in_background(invocation, move || {
+                        let ids =
+                            subsearch_results(&ctx, &previous, &terms).map_err(search_failed)?;
+                        Ok(Some((ids,).to_variant()))
+                    });
+                }
+                "GetResultMetas" => {
+                    let Some((ids,)) = parameters.get::<(Vec<String>,)>() else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    dbus::reply_in_background(invocation, move || {
+                        Ok(Some((result_metas(&ctx, &ids),).to_variant()))
+                    });
+                }
+                "ActivateResult" => {
+                    let Some((id, _, _)) = parameters.get::<(String, Vec<String>, u32)>() else {
+                        return dbus::invalid_args(invocation, method);
+                    };
+                    // The result can be stale, the note may have been deleted since the search.
+                    relm4::spawn_local(async move {
+                        let note_id = id.clone();
+                        let exists =
+                            relm4::spawn_blocking(move || ctx.data.db.load_note(&note_id).is_ok())
+                                .await
+                                .unwrap_or(false);
+                        let app = main_application();
+                        app.activate();
+                        if exists {
+                            app.activate_action("open-note", Some(&id.to_variant()));
+                        } else {
+                            log::warn!("search result {id} no longer exists");
+                        }
+                        invocation.return_value(None);
+                    });
+                }
+                "LaunchSearch" => {
+                    main_application().activate();
+                    invocation.return_value(None);
+                }
+                _ => dbus::unknown_method(invocation, method),
+            }
+        },
+        |_, _, _, _, property| dbus::get_property(property),
+        |_, _, _, _, property, _| dbus::set_property(property),
+    )
+}
+
+fn search_failed(e: DatabaseError) -> (&'static str, String) {
+    log::warn!("search provider failed: {:?}", e);
+    (dbus::FAILED, format!("{e:?}"))
+}
+
+fn initial_results(ctx: &AppContext, terms: &[String]) -> Result<Vec<String>, DatabaseError> {
+    let query = search::Query::parse(&terms.join(" "));
+    if let Some(unsupported) = query.unsupported() {
+        log::info!("{unsupported}");
+        return Ok(Vec::new());
+    }
+    let notes = search::query_notes(&ctx.data, &ctx.resources_dir, &query)?;
+    Ok(notes.into_iter().map(|abbr_note| abbr_note.id).collect())
+}
+
+// Narrows the previous results down, only their notes are read again.
+fn subsearch_results(
+    ctx: &AppContext,
+    previous: &[String],
+    terms: &[String],
+) -> Result<Vec<String>, DatabaseError> {
+    let query = search::Query::parse(&terms.join(" "));
+    if let Some(unsupported) = query.unsupported() {
+        log::info!("{unsupported}");
+        return Ok(Vec::new());
+    }
+    let notes = in_order(ctx.data.db.load_abbr_notes(None)?, previous, |abbr_note| {
+        abbr_note.id.as_str()
+    });
+    let notes = search::filter_notes(&ctx.data, &ctx.resources_dir, notes, &query)?;
+    Ok(notes.into_iter().map(|abbr_note| abbr_note.id).collect())
+}
+
+// The items with the given ids, in the order of the ids. Unknown ids are skipped.
+fn in_order<T>(items: Vec<T>, ids: &[String], id: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut items: HashMap<String, T> = items
+        .into_iter()
+        .map(|item| (id(&item).to_string(), item))
+        .collect();
+    ids.iter().filter_map(|id| items.remove(id)).collect()
+}
+
+fn result_metas(ctx: &AppContext, ids: &[String]) -> Vec<HashMap<String, glib::Variant>> {
+    ids.iter()
+        .filter_map(|id| ctx.data.db.load_note(id).ok())
+        .map(|note| {
+            HashMap::from([
+                ("id".to_string(), note.id.to_variant()),
+                ("name".to_string(), note.get_title().to_variant()),
+                (
+                    "description".to_string(),
+                    description(note.get_title(), &note.body).to_variant(),
+                ),
+                ("gicon".to_string(), APP_ID.to_variant()),
+            ])
+        })
+        .collect()
+}
+
+// The start of the body on one line, without the title when the body repeats it.
+fn description(title: &str, body: &str) -> String {
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && *line != title)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(120)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_order_follows_the_ids() {
+        let items = vec![("a", 1), ("b", 2), ("c", 3)];
+        let ids = ["c", "x", "a"].map(String::from);
+        assert_eq!(in_order(items, &ids, |item| item.0), [("c", 3), ("a", 1)]);
+    }
+
+    #[test]
+    fn description_skips_title_and_blank_lines() {
+        assert_eq!(
+            description("Groceries", "Groceries\n\n  milk \n\neggs\n"),
+            "milk eggs"
+        );
+        assert_eq!(description("A", &"x".repeat(200)).len(), 120);
+        assert_eq!(description("A", ""), "");
+    }
+}
@@ -0,0 +1,355 @@
+use std::ops::Range;
+
+use gtk::{gio, glib, pango, prelude::*};
+use pulldown_cmark::{Event, LinkType, Tag};
+use relm4::{actions::ActionName, gtk};
+
+use crate::{config::APP_ID, markdown};
+
+const MISSPELLED_TAG: &str = "misspelled";
+const MAX_SUGGESTIONS: usize = 6;
+
+relm4::new_action_group!(pub SpellingActionGroup, "spelling");
+
+// The misspelled word and its replacement.
+pub struct ReplaceWordAction;
+
+impl ActionName for ReplaceWordAction {
+    type Group = SpellingActionGroup;
+    type Target = (String, String);
+    type State = ();
+
+    const NAME: &'static str = "replace";
+}
+
+pub struct AddWordAction;
+
+impl ActionName for AddWordAction {
+    type Group = SpellingActionGroup;
+    type Target = String;
+    type State = ();
+
+    const NAME: &'static str = "add-word";
+}
+
+// The Enchant dictionary of the language picked in the preferences. Without a dictionary, e.g.
+// while spell checking is off, no word is misspelled.
+pub struct SpellChecker {
+    // Declared first to be dropped before the broker that loaded it.
+    dict: Option<enchant::Dict>,
+    broker: enchant::Broker,
+}
+
+impl SpellChecker {
+    pub fn new() -> SpellChecker {
+        let mut checker = SpellChecker {
+            dict: None,
+            broker: enchant::Broker::new(),
+        };
+        checker.reload();
+        checker
+    }
+
+    // Follows the `spell-check` and `spell-language` settings.
+    pub fn reload(&mut self) {
+        self.dict = None;
+        let settings = gio::Settings::new(APP_ID);
+        if !settings.boolean("spell-check") {
+            return;
+        }
+        let language = match settings.string("spell-language").as_str() {
+            "" => system_language(),
+            language => language.to_string(),
+        };
+        match self.broker.request_dict(&language) {
+            Ok(dict) => self.dict = Some(dict),
+            Err(e) => log::warn!("no spelling dictionary for {language}: {e}"),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dict.is_some()
+    }
+
+    fn is_misspelled(&self, word: &str) -> bool {
+        self.dict
+            .as_ref()
+            .map_or(false, |dict| !dict.check(word).unwrap_or(true))
+    }
+
+    pub fn suggestions(&self, word: &str) -> Vec<String> {
+        let mut suggestions = self
+            .dict
+            .as_ref()
+            .map(|dict| dict.suggest(word))
+            .unwrap_or_default();
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+
+    pub fn add_word(&self, word: &str) {
+        if let Some(dict) = &self.dict {
+            dict.add(word);
+        }
+    }
+
+    // Byte ranges of the misspelled words.
+    pub fn misspelled_in_text(&self, text: &str) -> Vec<Range<usize>> {
+        if self.dict.is_none() {
+            return Vec::new();
+        }
+        words(text)
+            .into_iter()
+            .filter(|range| self.is_misspelled(&text[range.clone()]))
+            .collect()
+    }
+
+    // Like `misspelled_in_text`, for the words of `markdown_words`.
+    pub fn misspelled_in_markdown(&self, text: &str) -> Vec<Range<usize>> {
+        if self.dict.is_none() {
+            return Vec::new();
+        }
+        markdown_words(text)
+            .into_iter()
+            .filter(|range| self.is_misspelled(&text[range.clone()]))
+            .collect()
+    }
+}
+
+// The words of the Markdown text, leaving out code and autolinks. Link targets aren't text.
+fn markdown_words(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut in_code_block = false;
+    let mut in_autolink = false;
+    for (event, range) in markdown::parser(text).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Link(LinkType::Autolink | LinkType::Email, _, _)) => {
+                in_autolink = true
+            }
+            Event::End(Tag::Link(LinkType::Autolink | LinkType::Email, _, _)) => {
+                in_autolink = false
+            }
+            Event::Text(_) if !in_code_block && !in_autolink => {
+                let start = range.start;
+                ranges.extend(
+                    words(&text[range])
+                        .into_iter()
+                        .map(|word| word.start + start..word.end + start),
+                );
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+// Words without digits, split on anything but letters and inner apostrophes. URLs are skipped.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    for token in text.split(char::is_whitespace) {
+        if token.contains("://") || token.starts_with("www.") || token.starts_with(":/") {
+            continue;
+        }
+        let token_start = token.as_ptr() as usize - text.as_ptr() as usize;
+        let mut word_start = None;
+        for (index, c) in token.char_indices().chain([(token.len(), ' ')]) {
+            let in_word = c.is_alphanumeric() || (c == '\'' && word_start.is_some());
+            match (in_word, word_start) {
+                (true, None) => word_start = Some(index),
+                (false, Some(start)) => {
+                    word_start = None;
+                    let word = token[start..index].trim_end_matches('\'');
+                    if !word.chars().any(|c| c.is_numeric()) {
+                        words.push(token_start + start..token_start + start + word.len());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    words
+}
+
+// `en_US` from a locale like `en_US.UTF-8`.
+fn system_language() -> String {
+    glib::language_names()
+        .iter()
+        .map(|name| {
+            name.split(|c| c == '.' || c == '@')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .find(|name| !name.is_empty() && name != "C" && name != "POSIX")
+        .unwrap_or_else(|| "en_US".to_string())
+}
+
+// Underlines the misspelled words of a Markdown buffer.
+pub fn check_buffer(checker: &SpellChecker, buffer: &gtk::TextBuffer) {
+    let tag = misspelled_tag(buffer);
+    let (start, end) = buffer.bounds();
+    buffer.remove_tag(&tag, &start, &end);
+    let text = buffer.text(&start, &end, true);
+    let mut offsets = CharOffsets::new(&text);
+    for range in checker.misspelled_in_markdown(&text) {
+        let start = buffer.iter_at_offset(offsets.char_offset(range.start));
+        let end = buffer.iter_at_offset(offsets.char_offset(range.end));
+        buffer.apply_tag(&tag, &start, &end);
+    }
+}
+
+// The misspelled word at `iter`, with its bounds.
+pub fn misspelled_word_at(
+    buffer: &gtk::TextBuffer,
+    iter: &gtk::TextIter,
+) -> Option<(String, gtk::TextIter, gtk::TextIter)> {
+    let tag = misspelled_tag(buffer);
+    if !iter.has_tag(&tag) {
+        return None;
+    }
+    let mut start = iter.clone();
+    if !start.starts_tag(Some(&tag)) {
+        start.backward_to_tag_toggle(Some(&tag));
+    }
+    let mut end = iter.clone();
+    end.forward_to_tag_toggle(Some(&tag));
+    Some((buffer.text(&start, &end, true).to_string(), start, end))
+}
+
+fn misspelled_tag(buffer: &gtk::TextBuffer) -> gtk::TextTag {
+    let tag_table = buffer.tag_table();
+    tag_table.lookup(MISSPELLED_TAG).unwrap_or_else(|| {
+        let tag = gtk::TextTag::new(Some(MISSPELLED_TAG));
+        tag.set_underline(pango::Underline::Error);
+        tag_table.add(&tag);
+        tag
+    })
+}
+
+// Underlines the misspelled words of an entry.
+pub fn check_entry(checker: &SpellChecker, entry: &gtk::Entry) {
+    let attributes = pango::AttrList::new();
+    for range in checker.misspelled_in_text(&entry.text()) {
+        let mut underline = pango::AttrInt::new_underline(pango::Underline::Error);
+        underline.set_start_index(range.start as u32);
+        underline.set_end_index(range.end as u32);
+        attributes.insert(underline);
+    }
+    entry.set_attributes(&attributes);
+}
+
+// The suggestions for each misspelled word of an entry. Looking them up is slow, so this runs
+// when the context menu opens instead of on every keystroke.
+pub fn entry_suggestions_menu(checker: &SpellChecker, entry: &gtk::Entry) -> gio::Menu {
+    let text = entry.text();
+    let menu = gio::Menu::new();
+    for range in checker.misspelled_in_text(&text) {
+        let word = &text[range];
+        menu.append_section(Some(word), &suggestions_menu(checker, word));
+    }
+    menu
+}
+
+pub fn suggestions_menu(checker: &SpellChecker, word: &str) -> gio::Menu {
+    let menu = gio::Menu::new();
+    let suggestions = gio::Menu::new();
+    for suggestion in checker.suggestions(word) {
+        let item = gio::MenuItem::new(Some(&suggestion), None);
+        item.set_action_and_target_value(
+            Some(&ReplaceWordAction::action_name()),
+            Some(&(word, suggestion.as_str()).to_variant()),
+        );
+        suggestions.append_item(&item);
+    }
+    menu.append_section(None, &suggestions);
+    let add_word = gio::MenuItem::new(Some(&format!("Add “{word}” to Dictionary")), None);
+    add_word.set_action_and_target_value(
+        Some(&AddWordAction::action_name()),
+        Some(&word.to_variant()),
+    );
+    menu.append_item(&add_word);
+    menu
+}
+
+// Buffer offsets count chars, the checker's ranges are bytes in increasing order.
+struct CharOffsets<'a> {
+    text: &'a str,
+    byte: usize,
+    char: i32,
+}
+
+impl<'a> CharOffsets<'a> {
+    fn new(text: &'a str) -> CharOffsets<'a> {
+        CharOffsets {
+            text,
+            byte: 0,
+            char: 0,
+        }
+    }
+
+    fn char_offset(&mut self, byte: usize) -> i32 {
+        self.char += self.text[self.byte..byte].chars().count() as i32;
+        self.byte = byte;
+        self.char
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked<'a>(text: &'a str, ranges: Vec<Range<usize>>) -> Vec<&'a str> {
+        ranges.into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn words_keep_inner_apostrophes() {
+        let text = "Don't say 'hello' twice.";
+        assert_eq!(
+            checked(text, words(text)),
+            ["Don't", "say", "hello", "twice"]
+        );
+    }
+
+    #[test]
+    fn words_skip_numbers_and_urls() {
+        let text = "see https://example.com/typo www.exmple.org :/0123 or mp3 and 42";
+        assert_eq!(checked(text, words(text)), ["see", "or", "and"]);
+    }
+
+    #[test]
+    fn words_are_byte_ranges() {
+        let text = "café naïve";
+        assert_eq!(words(text), [0..5, 6..12]);
+    }
+
+    #[test]
+    fn markdown_leaves_out_code() {
+        let text = "Some `inlne` code\n\n```\nfn mian() {}\n```\n\n    indnted code\n\nafter";
+        assert_eq!(
+            checked(text, markdown_words(text)),
+            ["Some", "code", "after"]
+        );
+    }
+
+    #[test]
+    fn markdown_leaves_out_link_targets_and_autolinks() {
+        let text =
+            "A [lnk text](https://example.com/pth) and <https://exmple.org> or <me@exmple.org>";
+        assert_eq!(
+            checked(text, markdown_words(text)),
+            ["A", "lnk", "text", "and", "or"]
+        );
+    }
+
+    #[test]
+    fn markdown_keeps_emphasis_and_headings() {
+        let text = "# Titel\n\n*emphsis* and **bold** ~~gone~~";
+        assert_eq!(
+            checked(text, markdown_words(text)),
+            ["Titel", "emphsis", "and", "bold", "gone"]
+        );
+    }
+}
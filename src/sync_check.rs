@@ -0,0 +1,336 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use gtk::{gio, glib, prelude::*};
+use relm4::gtk;
+use ruslin_data::{RuslinData, UpdateSource};
+use serde_json::{json, Value};
+
+use crate::config::APP_ID;
+
+// ruslin-data doesn't hand its sync config back, the login page remembers the server per profile.
+pub fn save_server(profile: Option<&str>, host: &str, email: &str) -> Result<(), glib::BoolError> {
+    let settings = gio::Settings::new(APP_ID);
+    let mut servers: HashMap<String, (String, String)> = settings.get("sync-servers");
+    servers.insert(
+        profile.unwrap_or_default().to_string(),
+        (host.to_string(), email.to_string()),
+    );
+    settings.set("sync-servers", &servers)
+}
+
+pub fn load_server(profile: Option<&str>) -> Option<(String, String)> {
+    let settings = gio::Settings::new(APP_ID);
+    let servers: HashMap<String, (String, String)> = settings.get("sync-servers");
+    servers.get(profile.unwrap_or_default()).cloned()
+}
+
+#[derive(Debug)]
+pub struct SyncReport {
+    pub host: String,
+    pub server: ServerInfo,
+    pub locks: Vec<String>,
+    pub remote_items: usize,
+    pub local_folders: usize,
+    pub local_notes: usize,
+}
+
+impl SyncReport {
+    pub fn summary(&self) -> String {
+        let locks = match self.locks.len() {
+            0 => "none".to_string(),
+            _ => self.locks.join(", "),
+        };
+        [
+            format!("Server: {}", self.host),
+            format!(
+                "Version: {}",
+                self.server
+                    .version
+                    .as_deref()
+                    .unwrap_or("not reported by the server")
+            ),
+            format!("Status: {}", self.server.status),
+            "Signed in: yes".to_string(),
+            format!("Locks: {locks}"),
+            // The server also stores resources, tags and sync metadata, the two counts don't match.
+            format!(
+                "Stored on the server: {} items, including resources, tags and sync data",
+                self.remote_items
+            ),
+            format!(
+                "On this device: {} folders, {} notes",
+                self.local_folders, self.local_notes
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+#[derive(Debug)]
+pub struct ServerInfo {
+    pub status: String,
+    pub version: Option<String>,
+}
+
+// Answers with the server's status, so a failed sync can tell an unreachable server from one
+// that refused it.
+pub async fn ping(client: &reqwest::Client, host: &str) -> Result<ServerInfo, String> {
+    let response = client
+        .get(format!("{}/api/ping", host.trim_end_matches('/')))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Server not reachable: {e}"))?;
+    let version_header = response
+        .headers()
+        .get("X-Joplin-Server-Version")
+        .and_then(|version| version.to_str().ok())
+        .map(str::to_string);
+    let ping: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Not a Joplin Server: {e}"))?;
+    Ok(server_info(&ping, version_header))
+}
+
+// Joplin Server answers `{"status": "ok", "message": "Joplin Server is running"}`, the version
+// is taken from a `version` field or header when the server sends one.
+fn server_info(ping: &Value, version_header: Option<String>) -> ServerInfo {
+    let version = ping["version"]
+        .as_str()
+        .map(str::to_string)
+        .or(version_header)
+        .filter(|version| !version.trim().is_empty());
+    ServerInfo {
+        status: ping["status"].as_str().unwrap_or("unknown").to_string(),
+        version,
+    }
+}
+
+// Talks to the Joplin Server API directly, each step fails with a message saying what went wrong.
+pub async fn check(
+    data: Arc<RuslinData>,
+    host: &str,
+    email: &str,
+    password: &str,
+) -> Result<SyncReport, String> {
+    let client = reqwest::Client::new();
+    let server = ping(&client, host).await?;
+    // The session is dropped here once the check is done, the server expires it.
+    let session_id = sign_in(&client, host, email, password).await?;
+    let api = |path: &str| format!("{}/api/{path}", host.trim_end_matches('/'));
+
+    let locks: Value = client
+        .get(api("locks"))
+        .header("X-API-AUTH", &session_id)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Couldn't read the sync locks: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("Couldn't read the sync locks: {e}"))?;
+    let locks = locks["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|lock| {
+            let kind = match lock["type"].as_i64() {
+                Some(2) => "exclusive",
+                _ => "sync",
+            };
+            format!(
+                "{kind} lock by {} {}",
+                lock["clientType"].as_i64().map_or("unknown", client_type),
+                lock["clientId"].as_str().unwrap_or_default()
+            )
+        })
+        .collect();
+
+    let remote_items = remote_item_names(&client, host, &session_id).await?.len();
+
+    let (local_folders, local_notes) = tokio::task::spawn_blocking(move || {
+        let folders = data.db.load_folders().map_err(|e| format!("{e:?}"))?;
+        let notes = data
+            .db
+            .load_abbr_notes(None)
+            .map_err(|e| format!("{e:?}"))?;
+        Ok::<_, String>((folders.len(), notes.len()))
+    })
+    .await
+    .map_err(|e| format!("Couldn't count the local notes: {e}"))??;
+
+    Ok(SyncReport {
+        host: host.to_string(),
+        server,
+        locks,
+        remote_items,
+        local_folders,
+        local_notes,
+    })
+}
+
+// Saves the local folders and notes the server doesn't have again, so the next sync uploads
+// them. Items the server has are left alone, their copy there may be newer. Returns how many
+// were marked.
+pub async fn upload_missing(
+    data: Arc<RuslinData>,
+    host: &str,
+    email: &str,
+    password: &str,
+) -> Result<usize, String> {
+    let client = reqwest::Client::new();
+    ping(&client, host).await?;
+    let session_id = sign_in(&client, host, email, password).await?;
+    let remote_names = remote_item_names(&client, host, &session_id).await?;
+    tokio::task::spawn_blocking(move || {
+        let folders = data.db.load_folders().map_err(|e| format!("{e:?}"))?;
+        let notes = data
+            .db
+            .load_abbr_notes(None)
+            .map_err(|e| format!("{e:?}"))?;
+        let folder_ids = missing_items(
+            folders.iter().map(|folder| folder.id.as_str()),
+            &remote_names,
+        );
+        let note_ids = missing_items(notes.iter().map(|note| note.id.as_str()), &remote_names);
+        // Folders first, so their notes aren't uploaded into a folder the server doesn't know.
+        for folder in folders
+            .iter()
+            .filter(|folder| folder_ids.contains(&folder.id))
+        {
+            data.db
+                .replace_folder(folder)
+                .map_err(|e| format!("Couldn't mark “{}”: {e:?}", folder.title))?;
+        }
+        for id in &note_ids {
+            let note = data.db.load_note(id).map_err(|e| format!("{e:?}"))?;
+            data.db
+                .replace_note(&note, UpdateSource::LocalEdit)
+                .map_err(|e| format!("Couldn't mark “{}”: {e:?}", note.get_title()))?;
+        }
+        Ok(folder_ids.len() + note_ids.len())
+    })
+    .await
+    .map_err(|e| format!("Couldn't mark the missing items: {e}"))?
+}
+
+async fn sign_in(
+    client: &reqwest::Client,
+    host: &str,
+    email: &str,
+    password: &str,
+) -> Result<String, String> {
+    let session: Value = client
+        .post(format!("{}/api/sessions", host.trim_end_matches('/')))
+        .json(&json!({ "email": email, "password": password }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Sign in failed: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("Sign in failed: {e}"))?;
+    session["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Sign in failed: no session".to_string())
+}
+
+// The names of the items at the root of the sync target, page by page.
+async fn remote_item_names(
+    client: &reqwest::Client,
+    host: &str,
+    session_id: &str,
+) -> Result<HashSet<String>, String> {
+    let mut names = HashSet::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = client
+            .get(format!(
+                "{}/api/items/root:/:/children",
+                host.trim_end_matches('/')
+            ))
+            .header("X-API-AUTH", session_id);
+        if let Some(cursor) = &cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let page: Value = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Couldn't list the items on the server: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("Couldn't list the items on the server: {e}"))?;
+        names.extend(
+            page["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|item| item["name"].as_str())
+                .map(str::to_string),
+        );
+        match (page["has_more"].as_bool(), page["cursor"].as_str()) {
+            (Some(true), Some(next)) => cursor = Some(next.to_string()),
+            _ => break,
+        }
+    }
+    Ok(names)
+}
+
+// Joplin stores each folder and note as `<id>.md` at the root of the target.
+fn missing_items<'a>(
+    local_ids: impl Iterator<Item = &'a str>,
+    remote_names: &HashSet<String>,
+) -> Vec<String> {
+    local_ids
+        .filter(|id| !remote_names.contains(&format!("{id}.md")))
+        .map(str::to_string)
+        .collect()
+}
+
+fn client_type(client_type: i64) -> &'static str {
+    match client_type {
+        1 => "desktop",
+        2 => "mobile",
+        3 => "terminal",
+        _ => "unknown client",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_comes_from_the_ping_or_its_header() {
+        let ping = json!({ "status": "ok", "message": "Joplin Server is running" });
+        let info = server_info(&ping, None);
+        assert_eq!(info.status, "ok");
+        assert_eq!(info.version, None);
+        let info = server_info(&ping, Some("2.10.5".to_string()));
+        assert_eq!(info.version.as_deref(), Some("2.10.5"));
+        let ping = json!({ "status": "ok", "version": "2.9.17" });
+        assert_eq!(server_info(&ping, None).version.as_deref(), Some("2.9.17"));
+    }
+
+    #[test]
+    fn missing_items_are_the_ids_without_a_remote_file() {
+        let remote_names = HashSet::from([
+            "0123456789abcdef0123456789abcdef.md".to_string(),
+            "info.json".to_string(),
+        ]);
+        let local_ids = [
+            "0123456789abcdef0123456789abcdef",
+            "fedcba9876543210fedcba9876543210",
+        ];
+        assert_eq!(
+            missing_items(local_ids.into_iter(), &remote_names),
+            ["fedcba9876543210fedcba9876543210"]
+        );
+    }
+}